@@ -0,0 +1,134 @@
+//! Errors returned by the backends
+
+use std::{io, time::Duration};
+
+use thiserror::Error;
+
+use hsc_core::error::{exit_description, ControlError, NotifyError};
+
+/// Failure to make sense of a program's output
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[cfg(any(
+        feature = "extras",
+        feature = "pactl",
+        feature = "plugins",
+        feature = "screencast"
+    ))]
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unknown status {status:?} for {device}")]
+    UnknownStatus { device: String, status: String },
+}
+
+/// Failure of an external program or plugin
+#[derive(Debug, Error)]
+pub enum BackendError {
+    /// The program could not be started
+    #[error("cannot run {program}: {source}")]
+    Spawn { program: String, source: io::Error },
+    /// The program reported a failure
+    #[error("{program} {}", exit_description(*code))]
+    Exit { program: String, code: Option<i32> },
+    /// The program was killed after running for too long
+    #[error("{program} did not answer within {}s", timeout.as_secs())]
+    Timeout { program: String, timeout: Duration },
+    /// The program's output could not be understood
+    #[error("invalid output from {program}: {source}")]
+    Parse { program: String, source: ParseError },
+    /// A WebAssembly module could not be loaded or trapped
+    #[error("WebAssembly plugin failed: {0}")]
+    Wasm(String),
+    /// What was asked for cannot be done with the programs on this system
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+impl BackendError {
+    /// Whether trying again later may succeed
+    ///
+    /// Programs that are missing or may not be run will not appear by themselves, nor will
+    /// unsupported features start working, so callers polling in a loop can stop asking them;
+    /// anything else may be temporary.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            BackendError::Spawn { source, .. } => !matches!(
+                source.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+            ),
+            BackendError::Unsupported(_) => false,
+            BackendError::Exit { .. }
+            | BackendError::Timeout { .. }
+            | BackendError::Parse { .. }
+            | BackendError::Wasm(_) => true,
+        }
+    }
+
+    /// Error for `program` failing to start
+    pub(crate) fn spawn(program: &str, source: io::Error) -> Self {
+        BackendError::Spawn {
+            program: program.to_string(),
+            source,
+        }
+    }
+
+    /// Error for `program` ending with `code`
+    pub(crate) fn exit(program: &str, code: Option<i32>) -> Self {
+        BackendError::Exit {
+            program: program.to_string(),
+            code,
+        }
+    }
+
+    /// Error for `program` killed after running for `timeout`
+    #[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+    pub(crate) fn timeout(program: &str, timeout: Duration) -> Self {
+        BackendError::Timeout {
+            program: program.to_string(),
+            timeout,
+        }
+    }
+
+    /// Error for unexpected output of `program`
+    #[cfg_attr(
+        not(any(
+            feature = "extras",
+            feature = "pactl",
+            feature = "plugins",
+            feature = "screencast"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn parse(program: &str, source: impl Into<ParseError>) -> Self {
+        BackendError::Parse {
+            program: program.to_string(),
+            source: source.into(),
+        }
+    }
+}
+
+impl From<BackendError> for NotifyError {
+    fn from(e: BackendError) -> Self {
+        NotifyError::Sink(Box::new(e))
+    }
+}
+
+impl From<BackendError> for ControlError {
+    fn from(e: BackendError) -> Self {
+        ControlError::Backend(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_programs_are_not_retried() {
+        let missing = BackendError::spawn("mouse-battery", io::ErrorKind::NotFound.into());
+        assert!(!missing.is_retryable());
+        let busy = BackendError::spawn("mouse-battery", io::ErrorKind::WouldBlock.into());
+        assert!(busy.is_retryable());
+        assert!(BackendError::exit("mouse-battery", None).is_retryable());
+    }
+}
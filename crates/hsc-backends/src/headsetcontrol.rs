@@ -0,0 +1,281 @@
+//! The `headsetcontrol` command line tool
+
+#[cfg(feature = "extras")]
+use serde::Deserialize;
+
+use hsc_core::{
+    command::{CommandRunner, SystemRunner},
+    control::{Controller, Setting},
+    device::parse_device_names,
+    error::ControlError,
+};
+#[cfg(feature = "extras")]
+use hsc_core::{
+    device::device_name,
+    extras::{Chatmix, Extras},
+};
+
+use crate::error::BackendError;
+#[cfg(feature = "extras")]
+use crate::error::ParseError;
+
+const PROGRAM: &str = "headsetcontrol";
+
+/// Get the output from the headsetcontrol command
+pub fn get_headsetcontrol_output() -> Result<String, BackendError> {
+    headsetcontrol_output(&SystemRunner)
+}
+
+/// Get the output from the headsetcontrol command, run by `runner`
+///
+/// headsetcontrol exits with an error when no device is connected, so only failing to start it
+/// is an error; its output is returned whatever its exit code.
+pub fn headsetcontrol_output(runner: &impl CommandRunner) -> Result<String, BackendError> {
+    runner
+        .run(PROGRAM, &["-b"])
+        .map(|output| output.stdout)
+        .map_err(|e| BackendError::spawn(PROGRAM, e))
+}
+
+/// headsetcontrol's JSON output
+#[cfg(feature = "extras")]
+#[derive(Deserialize)]
+struct JsonOutput {
+    #[serde(default)]
+    devices: Vec<JsonDevice>,
+}
+
+/// A device in headsetcontrol's JSON output
+#[cfg(feature = "extras")]
+#[derive(Deserialize)]
+struct JsonDevice {
+    device: String,
+    #[serde(default)]
+    capabilities_str: Vec<String>,
+    /// Negative when the headset cannot be asked
+    #[serde(default)]
+    chatmix: Option<i64>,
+}
+
+/// Read chatmix and capabilities of every headset
+#[cfg(feature = "extras")]
+pub fn get_extras() -> Result<Vec<(String, Extras)>, BackendError> {
+    extras_with(&SystemRunner)
+}
+
+/// Read chatmix and capabilities of every headset, running headsetcontrol with `runner`
+///
+/// Like the battery query, the exit code is ignored since headsetcontrol fails when no device is
+/// connected.
+#[cfg(feature = "extras")]
+pub fn extras_with(runner: &impl CommandRunner) -> Result<Vec<(String, Extras)>, BackendError> {
+    let output = runner
+        .run(PROGRAM, &["-o", "json"])
+        .map_err(|e| BackendError::spawn(PROGRAM, e))?;
+    parse_extras(&output.stdout).map_err(|e| BackendError::parse(PROGRAM, e))
+}
+
+/// Parse `headsetcontrol -o json` output into the extras of every device, keyed by the name
+/// `headsetcontrol -b` gives it
+#[cfg(feature = "extras")]
+pub fn parse_extras(stdout: &str) -> Result<Vec<(String, Extras)>, ParseError> {
+    let output: JsonOutput = serde_json::from_str(stdout)?;
+    Ok(output
+        .devices
+        .into_iter()
+        .map(|d| {
+            let chatmix = d
+                .chatmix
+                .and_then(|value| u8::try_from(value).ok())
+                .and_then(Chatmix::new);
+            let extras = Extras {
+                chatmix,
+                capabilities: d.capabilities_str,
+            };
+            (device_name(&d.device).to_string(), extras)
+        })
+        .collect())
+}
+
+/// Sends settings to the headset with headsetcontrol, which it runs with its runner
+///
+/// headsetcontrol selects headsets by their position in its output rather than by name, so the
+/// device is looked up in the battery query first and settings for devices it does not report
+/// are refused.
+pub struct HeadsetControl<R = SystemRunner>(pub R);
+
+impl Default for HeadsetControl {
+    fn default() -> Self {
+        HeadsetControl(SystemRunner)
+    }
+}
+
+impl<R: CommandRunner> Controller for HeadsetControl<R> {
+    fn apply(&self, device: &str, setting: Setting) -> Result<(), ControlError> {
+        // Headsets without a battery reading still count towards headsetcontrol's indices
+        let index = parse_device_names(&headsetcontrol_output(&self.0)?)
+            .iter()
+            .position(|name| name == device)
+            .ok_or_else(|| ControlError::NotConnected(device.to_string()))?;
+        apply_to(&self.0, index, setting).map_err(Into::into)
+    }
+}
+
+/// Send `setting` to the first headset, running headsetcontrol with `runner`
+pub fn apply_with(runner: &impl CommandRunner, setting: Setting) -> Result<(), BackendError> {
+    apply_to(runner, 0, setting)
+}
+
+/// Send `setting` to the headset at `index` in headsetcontrol's output, running it with `runner`
+///
+/// The first headset is not selected explicitly, for versions of headsetcontrol without `-d`.
+pub fn apply_to(
+    runner: &impl CommandRunner,
+    index: usize,
+    setting: Setting,
+) -> Result<(), BackendError> {
+    let (option, value) = match setting {
+        Setting::Lights(on) => ("-l", u8::from(on)),
+        Setting::Sidetone(level) => ("-s", level),
+        Setting::InactiveTime(minutes) => ("-i", minutes),
+    };
+    let index = index.to_string();
+    let value = value.to_string();
+    let mut args = Vec::new();
+    if index != "0" {
+        args.extend(["-d", &index]);
+    }
+    args.extend([option, &value]);
+    let output = runner
+        .run(PROGRAM, &args)
+        .map_err(|e| BackendError::spawn(PROGRAM, e))?;
+    if !output.is_success() {
+        return Err(BackendError::exit(PROGRAM, output.code));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use hsc_core::command::{CommandOutput, FakeRunner};
+
+    use super::*;
+
+    #[test]
+    fn output_is_read_from_battery_query() {
+        let runner = FakeRunner::with_output(CommandOutput::success("Found Arctis 7!\n"));
+        assert_eq!(headsetcontrol_output(&runner).unwrap(), "Found Arctis 7!\n");
+        assert_eq!(runner.calls(), [["headsetcontrol", "-b"]]);
+    }
+
+    #[cfg(feature = "extras")]
+    #[test]
+    fn extras_are_read_from_json_output() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(0),
+            stdout: r#"{"name": "HeadsetControl", "device_count": 2, "devices": [
+                {"status": "success", "device": "SteelSeries Arctis 7 (2019 Edition)",
+                 "capabilities_str": ["sidetone", "battery", "chatmix"], "chatmix": 96},
+                {"status": "success", "device": "Logitech G PRO", "chatmix": -1}]}"#
+                .to_string(),
+        });
+        let extras = extras_with(&runner).unwrap();
+
+        assert_eq!(runner.calls(), [["headsetcontrol", "-o", "json"]]);
+        assert_eq!(extras[0].0, "SteelSeries Arctis 7");
+        assert_eq!(extras[0].1.chatmix.map(Chatmix::value), Some(96));
+        assert_eq!(extras[0].1.capabilities, ["sidetone", "battery", "chatmix"]);
+        assert_eq!(extras[1].1, Extras::default());
+    }
+
+    #[test]
+    fn settings_are_passed_as_options() {
+        let runner = FakeRunner::default();
+        apply_with(&runner, Setting::Lights(true)).unwrap();
+        apply_with(&runner, Setting::Lights(false)).unwrap();
+        apply_with(&runner, Setting::Sidetone(64)).unwrap();
+        apply_with(&runner, Setting::InactiveTime(15)).unwrap();
+        assert_eq!(
+            runner.calls(),
+            [
+                ["headsetcontrol", "-l", "1"],
+                ["headsetcontrol", "-l", "0"],
+                ["headsetcontrol", "-s", "64"],
+                ["headsetcontrol", "-i", "15"]
+            ]
+        );
+    }
+
+    /// Battery query output listing two headsets
+    const TWO_HEADSETS: &str = "Found SteelSeries Arctis 7!\n\
+        Battery:\n\tStatus: BATTERY_AVAILABLE\n\tLevel: 50%\n\
+        Found Logitech G PRO!\n\
+        Battery:\n\tStatus: BATTERY_CHARGING\n\tLevel: 80%\n";
+
+    #[test]
+    fn settings_go_to_the_named_headset() {
+        let controller = HeadsetControl(FakeRunner::default());
+        controller.0.push(CommandOutput::success(TWO_HEADSETS));
+        controller.0.push(CommandOutput::success(""));
+        controller.0.push(CommandOutput::success(TWO_HEADSETS));
+        controller
+            .apply("SteelSeries Arctis 7", Setting::Lights(false))
+            .unwrap();
+        controller
+            .apply("Logitech G PRO", Setting::Sidetone(10))
+            .unwrap();
+        assert_eq!(
+            controller.0.calls(),
+            [
+                vec!["headsetcontrol", "-b"],
+                vec!["headsetcontrol", "-l", "0"],
+                vec!["headsetcontrol", "-b"],
+                vec!["headsetcontrol", "-d", "1", "-s", "10"],
+            ]
+        );
+    }
+
+    #[test]
+    fn headsets_without_a_battery_reading_keep_their_index() {
+        let controller = HeadsetControl(FakeRunner::with_output(CommandOutput::success(
+            include_str!("../../hsc-core/tests/fixtures/multiple_one_unavailable.txt"),
+        )));
+        controller
+            .apply("HyperX Cloud Flight", Setting::Lights(false))
+            .unwrap();
+        assert_eq!(
+            controller.0.calls(),
+            [
+                vec!["headsetcontrol", "-b"],
+                vec!["headsetcontrol", "-d", "1", "-l", "0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_for_unknown_devices_are_refused() {
+        let controller = HeadsetControl(FakeRunner::with_output(CommandOutput::success(
+            TWO_HEADSETS,
+        )));
+        let err = controller.apply("G502", Setting::Lights(true)).unwrap_err();
+        assert!(matches!(err, ControlError::NotConnected(name) if name == "G502"));
+        assert_eq!(controller.0.calls().len(), 1);
+    }
+
+    #[test]
+    fn failed_settings_are_reported() {
+        let controller = HeadsetControl(FakeRunner::default());
+        controller.0.push(CommandOutput::success(TWO_HEADSETS));
+        controller.0.push(CommandOutput {
+            code: Some(1),
+            stdout: String::new(),
+        });
+        let err = controller
+            .apply("SteelSeries Arctis 7", Setting::Lights(true))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot apply setting: headsetcontrol exited with code 1"
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Integrations with external tools used by the headset battery notifier
+//!
+//! Each module wraps one program: [`headsetcontrol`] reads battery levels and other values and
+//! controls the headset, [`pactl`] talks to the PipeWire/PulseAudio sound server, [`screencast`]
+//! tells whether the screen is shared, and [`plugin`] and `wasm` run user-supplied providers,
+//! sinks and policies.
+
+pub mod error;
+pub mod headsetcontrol;
+#[cfg(feature = "pactl")]
+pub mod pactl;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+#[cfg(feature = "screencast")]
+pub mod screencast;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,562 @@
+//! Integration with the PipeWire/PulseAudio sound server
+//!
+//! Sinks and sources are listed through `pactl`, which works against both PulseAudio and
+//! `pipewire-pulse`. Changing their properties is not part of what `pactl` can do, so it goes
+//! through `pacmd`, which only PulseAudio has. PipeWire only takes node descriptions from
+//! session manager rules when a node is created, so they cannot be annotated there.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use hsc_core::{
+    audio::{node_matches, AudioNode, NodeKind},
+    command::CommandRunner,
+    config::Config,
+    device::{BatteryStatus, Device},
+};
+
+use crate::error::{BackendError, ParseError};
+
+/// A sink or source as listed by `pactl --format=json`
+#[derive(Deserialize)]
+struct PactlNode {
+    name: String,
+    description: String,
+    /// `RUNNING`, `IDLE` or `SUSPENDED`
+    #[serde(default)]
+    state: String,
+}
+
+/// `pactl`/`pacmd` object type of a node kind
+fn object(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Sink => "sink",
+        NodeKind::Source => "source",
+    }
+}
+
+/// List sinks and sources, leaving out sink monitors, running pactl with `runner`
+pub fn list_nodes(runner: &impl CommandRunner) -> Result<Vec<AudioNode>, BackendError> {
+    let mut nodes = list(runner, NodeKind::Sink)?;
+    nodes.extend(list(runner, NodeKind::Source)?);
+    Ok(nodes)
+}
+
+/// List sources, leaving out sink monitors, running pactl with `runner`
+pub fn list_sources(runner: &impl CommandRunner) -> Result<Vec<AudioNode>, BackendError> {
+    list(runner, NodeKind::Source)
+}
+
+/// List nodes of `kind`, leaving out sink monitors
+fn list(runner: &impl CommandRunner, kind: NodeKind) -> Result<Vec<AudioNode>, BackendError> {
+    let objects = format!("{}s", object(kind));
+    let stdout = run(runner, "pactl", &["--format=json", "list", &objects])?;
+    parse_nodes(stdout.as_bytes(), kind).map_err(|e| BackendError::parse("pactl", e))
+}
+
+/// Parse the output of `pactl --format=json list sinks` or `sources`, leaving out sink monitors
+pub fn parse_nodes(json: &[u8], kind: NodeKind) -> Result<Vec<AudioNode>, ParseError> {
+    let listed: Vec<PactlNode> = serde_json::from_slice(json)?;
+    Ok(listed
+        .into_iter()
+        .filter(|n| !n.name.ends_with(".monitor"))
+        .map(|n| AudioNode {
+            name: n.name,
+            description: n.description,
+            kind,
+            running: n.state == "RUNNING",
+        })
+        .collect())
+}
+
+/// Name of the default node of `kind`
+fn default_name(runner: &impl CommandRunner, kind: NodeKind) -> Result<String, BackendError> {
+    let stdout = run(runner, "pactl", &[&format!("get-default-{}", object(kind))])?;
+    Ok(stdout.trim().to_string())
+}
+
+/// The default sink and source, running pactl with `runner`
+pub fn default_nodes(runner: &impl CommandRunner) -> Result<Vec<AudioNode>, BackendError> {
+    let defaults = [
+        default_name(runner, NodeKind::Sink)?,
+        default_name(runner, NodeKind::Source)?,
+    ];
+
+    Ok(list_nodes(runner)?
+        .into_iter()
+        .filter(|n| defaults.contains(&n.name))
+        .collect())
+}
+
+/// Sound server behind `pactl`, which decides whether node descriptions can be changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundServer {
+    PulseAudio,
+    PipeWire,
+}
+
+/// The sound server `pactl` talks to, running it with `runner`
+pub fn sound_server(runner: &impl CommandRunner) -> Result<SoundServer, BackendError> {
+    run(runner, "pactl", &["info"]).map(|stdout| parse_sound_server(&stdout))
+}
+
+/// Sound server named by the `Server Name` line of `pactl info`, which reads like
+/// `PulseAudio (on PipeWire 1.0.5)` for `pipewire-pulse`
+pub fn parse_sound_server(stdout: &str) -> SoundServer {
+    let pipewire = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Server Name:"))
+        .any(|name| name.contains("PipeWire"));
+    if pipewire {
+        SoundServer::PipeWire
+    } else {
+        SoundServer::PulseAudio
+    }
+}
+
+/// Change the description a node is shown with on PulseAudio, running pacmd with `runner`
+pub fn set_description(
+    runner: &impl CommandRunner,
+    node: &AudioNode,
+    description: &str,
+) -> Result<(), BackendError> {
+    let command = format!("update-{}-proplist", object(node.kind));
+    let property = format!("device.description=\"{}\"", description.replace('"', "'"));
+    run(runner, "pacmd", &[&command, &node.name, &property]).map(drop)
+}
+
+/// `description` without the battery level [`DescriptionAnnotator`] appends, such as ` (64%)` or
+/// ` (3/4)`, left over from an earlier run
+pub fn strip_annotation(description: &str) -> &str {
+    let Some((original, level)) = description
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+    else {
+        return description;
+    };
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let annotated = match level.strip_suffix('%') {
+        Some(percent) => is_number(percent),
+        None => level
+            .split_once('/')
+            .is_some_and(|(current, max)| is_number(current) && is_number(max)),
+    };
+    if annotated {
+        original
+    } else {
+        description
+    }
+}
+
+/// Make `node` the default sink or source, running pactl with `runner`
+pub fn set_default(runner: &impl CommandRunner, node: &AudioNode) -> Result<(), BackendError> {
+    set_default_name(runner, node.kind, &node.name)
+}
+
+/// Make the node of `kind` called `name` the default
+fn set_default_name(
+    runner: &impl CommandRunner,
+    kind: NodeKind,
+    name: &str,
+) -> Result<(), BackendError> {
+    let command = format!("set-default-{}", object(kind));
+    run(runner, "pactl", &[&command, name]).map(drop)
+}
+
+/// Run `program` with `args` through `runner`, returning its standard output if it succeeds
+fn run(runner: &impl CommandRunner, program: &str, args: &[&str]) -> Result<String, BackendError> {
+    let output = runner
+        .run(program, args)
+        .map_err(|e| BackendError::spawn(program, e))?;
+    if !output.is_success() {
+        return Err(BackendError::exit(program, output.code));
+    }
+    Ok(output.stdout)
+}
+
+/// Keeps audio node descriptions annotated with the battery level of their headset
+///
+/// Descriptions are restored when their headset disconnects, and by [`restore`] before the
+/// program exits. A description still annotated by a run that could not restore it is
+/// recognized and annotated afresh.
+///
+/// Only PulseAudio can change descriptions of existing nodes. On PipeWire the first update
+/// fails with [`BackendError::Unsupported`] and nothing is renamed.
+///
+/// [`restore`]: DescriptionAnnotator::restore
+#[derive(Default)]
+pub struct DescriptionAnnotator {
+    /// Sound server, found out at the first update
+    server: Option<SoundServer>,
+    /// Every node we have renamed with its original description, keyed by node name
+    originals: HashMap<String, AudioNode>,
+}
+
+impl DescriptionAnnotator {
+    /// Annotate nodes of connected devices and restore those of disconnected ones, running the
+    /// sound server's tools with `runner`
+    ///
+    /// A node that cannot be changed does not stop the others from being updated; the first
+    /// error is returned once all were tried.
+    pub fn update(
+        &mut self,
+        runner: &impl CommandRunner,
+        config: &Config,
+        devices: &[Device],
+    ) -> Result<(), BackendError> {
+        let server = match self.server {
+            Some(server) => server,
+            None => *self.server.insert(sound_server(runner)?),
+        };
+        if server == SoundServer::PipeWire {
+            return Err(BackendError::Unsupported(
+                "audio device descriptions cannot be changed on PipeWire".to_string(),
+            ));
+        }
+        let mut first_error = None;
+        for node in list_nodes(runner)? {
+            let original = self
+                .originals
+                .get(&node.name)
+                .cloned()
+                .unwrap_or_else(|| AudioNode {
+                    description: strip_annotation(&node.description).to_string(),
+                    ..node.clone()
+                });
+
+            let level = devices
+                .iter()
+                .filter(|d| d.battery_status != BatteryStatus::Disconnected)
+                .find(|d| node_matches(config, &original, &d.name))
+                .and_then(|d| d.battery);
+            let wanted = match level {
+                Some(battery) => format!("{} ({})", original.description, battery),
+                None => original.description.clone(),
+            };
+
+            if node.description != wanted {
+                if let Err(e) = set_description(runner, &node, &wanted) {
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            }
+            if wanted == original.description {
+                self.originals.remove(&node.name);
+            } else {
+                self.originals.insert(node.name, original);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Give every annotated node its original description back
+    pub fn restore(&mut self, runner: &impl CommandRunner) -> Result<(), BackendError> {
+        let mut first_error = None;
+        for (_, original) in self.originals.drain() {
+            if let Err(e) = set_description(runner, &original, &original.description) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// Makes the sink of a headset the default output while it is connected
+///
+/// Headsets watched on another machine are not played through by this one, so nothing is
+/// switched while [`Config::remote`] is set.
+#[derive(Default)]
+pub struct DefaultSwitcher {
+    /// Connected devices whose sink was made the default, or already was; one whose sink has
+    /// not appeared yet is left out and tried again at the next update
+    connected: HashSet<String>,
+    /// Default sink before switching, keyed by the device switched to
+    previous: HashMap<String, String>,
+}
+
+impl DefaultSwitcher {
+    /// Switch to the sinks of newly connected devices and back from those of disconnected ones,
+    /// running pactl with `runner`
+    pub fn update(
+        &mut self,
+        runner: &impl CommandRunner,
+        config: &Config,
+        devices: &[Device],
+    ) -> Result<(), BackendError> {
+        if config.remote.is_some() {
+            return Ok(());
+        }
+        let connected: HashSet<String> = devices
+            .iter()
+            .filter(|d| d.battery_status != BatteryStatus::Disconnected)
+            .filter(|d| config.switch_default_output_for(&d.name))
+            .map(|d| d.name.clone())
+            .collect();
+        if connected == self.connected {
+            return Ok(());
+        }
+
+        let sinks = list(runner, NodeKind::Sink)?;
+        let mut current = default_name(runner, NodeKind::Sink)?;
+        for name in self.connected.difference(&connected) {
+            let Some(previous) = self.previous.remove(name) else {
+                continue;
+            };
+            // Leave the default alone if it was changed since, or the old one is gone
+            let still_switched = sinks
+                .iter()
+                .any(|n| n.name == current && node_matches(config, n, name));
+            if still_switched && sinks.iter().any(|n| n.name == previous) {
+                set_default_name(runner, NodeKind::Sink, &previous)?;
+                current = previous;
+            }
+        }
+        let mut without_sink = Vec::new();
+        for name in connected.difference(&self.connected) {
+            let Some(sink) = sinks.iter().find(|n| node_matches(config, n, name)) else {
+                without_sink.push(name.clone());
+                continue;
+            };
+            if sink.name != current {
+                set_default(runner, sink)?;
+                self.previous.insert(
+                    name.clone(),
+                    std::mem::replace(&mut current, sink.name.clone()),
+                );
+            }
+        }
+        self.connected = connected;
+        for name in without_sink {
+            self.connected.remove(&name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hsc_core::{
+        command::{CommandOutput, FakeRunner},
+        device::BatteryReading,
+    };
+
+    use super::*;
+
+    #[test]
+    fn default_nodes_are_looked_up_in_the_listed_ones() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success("alsa_output.arctis_7\n"));
+        runner.push(CommandOutput::success("alsa_input.arctis_7\n"));
+        runner.push(CommandOutput::success(
+            r#"[{"name": "alsa_output.arctis_7", "description": "Arctis 7 Game"},
+                {"name": "alsa_output.speakers", "description": "Speakers"}]"#,
+        ));
+        runner.push(CommandOutput::success(
+            r#"[{"name": "alsa_output.arctis_7.monitor", "description": "Monitor"},
+                {"name": "alsa_input.arctis_7", "description": "Arctis 7 Chat"}]"#,
+        ));
+
+        let names: Vec<String> = default_nodes(&runner)
+            .unwrap()
+            .into_iter()
+            .map(|n| n.name)
+            .collect();
+        assert_eq!(names, ["alsa_output.arctis_7", "alsa_input.arctis_7"]);
+        assert_eq!(
+            runner.calls(),
+            [
+                vec!["pactl", "get-default-sink"],
+                vec!["pactl", "get-default-source"],
+                vec!["pactl", "--format=json", "list", "sinks"],
+                vec!["pactl", "--format=json", "list", "sources"],
+            ]
+        );
+    }
+
+    #[test]
+    fn earlier_annotations_are_stripped() {
+        assert_eq!(strip_annotation("Arctis 7 Game (64%)"), "Arctis 7 Game");
+        assert_eq!(strip_annotation("G533 (3/4)"), "G533");
+        assert_eq!(strip_annotation("Speakers (Front)"), "Speakers (Front)");
+        assert_eq!(strip_annotation("Arctis 7 Game"), "Arctis 7 Game");
+    }
+
+    #[test]
+    fn annotations_are_set_with_pacmd_and_restored() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success("Server Name: pulseaudio\n"));
+        // Left annotated by a run that was killed
+        runner.push(CommandOutput::success(
+            r#"[{"name": "alsa_output.arctis_7", "description": "Arctis 7 Game (70%)"}]"#,
+        ));
+        runner.push(CommandOutput::success("[]"));
+        let devices = [arctis(BatteryStatus::Discharging)];
+
+        let mut annotator = DescriptionAnnotator::default();
+        annotator
+            .update(&runner, &Config::default(), &devices)
+            .unwrap();
+        annotator.restore(&runner).unwrap();
+        let calls = runner.calls();
+        assert_eq!(
+            calls[3..],
+            [
+                vec![
+                    "pacmd",
+                    "update-sink-proplist",
+                    "alsa_output.arctis_7",
+                    r#"device.description="Arctis 7 Game (64%)""#
+                ],
+                vec![
+                    "pacmd",
+                    "update-sink-proplist",
+                    "alsa_output.arctis_7",
+                    r#"device.description="Arctis 7 Game""#
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn annotations_are_not_supported_on_pipewire() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success(
+            "Server Name: PulseAudio (on PipeWire 1.0.5)\n",
+        ));
+        let mut annotator = DescriptionAnnotator::default();
+
+        let err = annotator
+            .update(
+                &runner,
+                &Config::default(),
+                &[arctis(BatteryStatus::Discharging)],
+            )
+            .unwrap_err();
+        assert!(!err.is_retryable());
+        annotator.restore(&runner).unwrap();
+        assert_eq!(runner.calls(), [["pactl", "info"]]);
+    }
+
+    /// The Arctis 7 in `status`
+    fn arctis(battery_status: BatteryStatus) -> Device {
+        Device {
+            name: "Arctis 7".to_string(),
+            battery_status,
+            battery: Some(BatteryReading::Percent(64)),
+            last_notif_battery_level: None,
+        }
+    }
+
+    /// `pactl --format=json list sinks` output with the speakers and the Arctis 7
+    const SINKS: &str = r#"[{"name": "alsa_output.speakers", "description": "Speakers"},
+                             {"name": "alsa_output.arctis_7", "description": "Arctis 7 Game"}]"#;
+
+    /// Config switching to every headset
+    fn switching() -> Config {
+        let mut config = Config::default();
+        config.audio.switch_default_output = true;
+        config
+    }
+
+    #[test]
+    fn switcher_switches_to_the_headset_and_back() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success(SINKS));
+        runner.push(CommandOutput::success("alsa_output.speakers\n"));
+        let mut switcher = DefaultSwitcher::default();
+        let config = switching();
+
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+        assert_eq!(
+            runner.calls()[2],
+            ["pactl", "set-default-sink", "alsa_output.arctis_7"]
+        );
+        // Nothing changed, so pactl is not asked again
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+        assert_eq!(runner.calls().len(), 3);
+
+        runner.push(CommandOutput::success(SINKS));
+        runner.push(CommandOutput::success("alsa_output.arctis_7\n"));
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Disconnected)])
+            .unwrap();
+        assert_eq!(
+            runner.calls()[5],
+            ["pactl", "set-default-sink", "alsa_output.speakers"]
+        );
+    }
+
+    #[test]
+    fn switcher_waits_for_the_headset_sink_to_appear() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success(
+            r#"[{"name": "alsa_output.speakers", "description": "Speakers"}]"#,
+        ));
+        runner.push(CommandOutput::success("alsa_output.speakers\n"));
+        let mut switcher = DefaultSwitcher::default();
+        let config = switching();
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+        assert_eq!(runner.calls().len(), 2);
+
+        runner.push(CommandOutput::success(SINKS));
+        runner.push(CommandOutput::success("alsa_output.speakers\n"));
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+        assert_eq!(
+            runner.calls()[4],
+            ["pactl", "set-default-sink", "alsa_output.arctis_7"]
+        );
+    }
+
+    #[test]
+    fn switcher_keeps_a_default_chosen_meanwhile() {
+        let runner = FakeRunner::default();
+        runner.push(CommandOutput::success(SINKS));
+        runner.push(CommandOutput::success("alsa_output.speakers\n"));
+        let mut switcher = DefaultSwitcher::default();
+        let config = switching();
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+
+        runner.push(CommandOutput::success(SINKS));
+        runner.push(CommandOutput::success("alsa_output.hdmi\n"));
+        switcher
+            .update(&runner, &config, &[arctis(BatteryStatus::Disconnected)])
+            .unwrap();
+        assert_eq!(runner.calls().len(), 5);
+    }
+
+    #[test]
+    fn switcher_leaves_the_local_output_alone_for_remote_headsets() {
+        let runner = FakeRunner::default();
+        let config = Config {
+            remote: Some("me@htpc".to_string()),
+            ..switching()
+        };
+        DefaultSwitcher::default()
+            .update(&runner, &config, &[arctis(BatteryStatus::Discharging)])
+            .unwrap();
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn failing_pactl_is_an_error() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(1),
+            stdout: String::new(),
+        });
+        assert!(matches!(
+            list_sources(&runner),
+            Err(BackendError::Exit { code: Some(1), .. })
+        ));
+    }
+}
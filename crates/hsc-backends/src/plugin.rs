@@ -0,0 +1,314 @@
+//! External plugins speaking JSON over standard input and output
+//!
+//! A plugin is started once per request. It reads a single JSON object from standard input and,
+//! for requests that expect an answer, prints a single JSON object to standard output before
+//! exiting with status 0, within the plugin's `timeout`. Every request carries the protocol
+//! `version`, currently 1.
+//!
+//! Providers are asked for readings on every poll:
+//!
+//! ```json
+//! {"version": 1, "request": "poll"}
+//! ```
+//!
+//! and answer with the devices they know about, `level` being optional:
+//!
+//! ```json
+//! {"devices": [{"name": "Wireless Mouse", "status": "discharging", "level": 42}]}
+//! ```
+//!
+//! `status` is one of `charging`, `discharging` or `disconnected`. `level` is a percentage, or out
+//! of `max_level` for devices that only report a few discrete levels:
+//!
+//! ```json
+//! {"devices": [{"name": "Wireless Keyboard", "status": "discharging", "level": 2, "max_level": 4}]}
+//! ```
+//!
+//! Providers able to read the firmware version of a device may add it as `firmware`, which is
+//! compared with the `[firmware]` table of known latest versions:
+//!
+//! ```json
+//! {"devices": [{"name": "Wireless Mouse", "status": "charging", "level": 80, "firmware": "1.04"}]}
+//! ```
+//!
+//! Sinks are handed every notification that is sent, and their output is ignored:
+//!
+//! ```json
+//! {"version": 1, "request": "notify", "device": "Arctis 7", "display_name": "Headset",
+//!  "message": "Battery level low: 9%", "icon": "battery-low"}
+//! ```
+
+use std::{io, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use hsc_core::{
+    command::{CommandRunner, SystemRunner},
+    config::PluginCommand,
+    device::{BatteryReading, BatteryStatus, Device},
+    error::NotifyError,
+    notify::NotificationSink,
+    schema::{EventKind, Status},
+};
+
+use crate::error::{BackendError, ParseError};
+
+/// Version of the protocol spoken with plugins
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A request sent to a plugin
+#[derive(Serialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub(crate) enum Request<'a> {
+    Poll,
+    Notify {
+        device: &'a str,
+        display_name: &'a str,
+        message: &'a str,
+        icon: &'a str,
+    },
+    /// Only sent to WebAssembly policies
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    Event {
+        device: &'a str,
+        display_name: &'a str,
+        status: Status,
+        level: Option<u8>,
+        event: EventKind,
+    },
+}
+
+/// A provider's answer to a poll
+#[derive(Deserialize)]
+struct PollResponse {
+    devices: Vec<PluginDevice>,
+}
+
+/// A device reported by a provider
+#[derive(Deserialize)]
+struct PluginDevice {
+    name: String,
+    status: String,
+    #[serde(default)]
+    level: Option<u8>,
+    /// Number of discrete levels, if `level` is not a percentage
+    #[serde(default)]
+    max_level: Option<u8>,
+    #[serde(default)]
+    firmware: Option<String>,
+}
+
+/// What a provider reported
+#[derive(Clone, Debug, Default)]
+pub struct Poll {
+    pub devices: Vec<Device>,
+    /// Firmware version of the devices whose provider can read it, keyed by device name
+    pub firmware: Vec<(String, String)>,
+}
+
+/// Ask the provider `plugin` for its devices
+pub fn poll(plugin: &PluginCommand) -> Result<Poll, BackendError> {
+    poll_with(&SystemRunner, plugin)
+}
+
+/// Ask the provider `plugin`, started by `runner`, for its devices
+pub fn poll_with(
+    runner: &impl CommandRunner,
+    plugin: &PluginCommand,
+) -> Result<Poll, BackendError> {
+    let stdout = call(runner, plugin, &Request::Poll)?;
+    parse_poll(&stdout).map_err(|e| BackendError::parse(&plugin.command, e))
+}
+
+/// Parse a provider's answer to a poll
+pub fn parse_poll(stdout: &str) -> Result<Poll, ParseError> {
+    let response: PollResponse = serde_json::from_str(stdout)?;
+    let mut poll = Poll::default();
+    for d in response.devices {
+        let battery_status =
+            BatteryStatus::from_name(&d.status).ok_or_else(|| ParseError::UnknownStatus {
+                device: d.name.clone(),
+                status: d.status.clone(),
+            })?;
+        if let Some(firmware) = d.firmware {
+            poll.firmware.push((d.name.clone(), firmware));
+        }
+        poll.devices.push(Device {
+            name: d.name,
+            battery_status,
+            battery: d.level.and_then(|level| match d.max_level {
+                Some(max) => BatteryReading::level(level, max),
+                None => (level <= 100).then_some(BatteryReading::Percent(level)),
+            }),
+            last_notif_battery_level: None,
+        });
+    }
+    Ok(poll)
+}
+
+/// A sink plugin, receiving every notification sent
+pub struct PluginSink {
+    plugin: PluginCommand,
+}
+
+impl PluginSink {
+    /// Sink delivering notifications to `plugin`
+    pub fn new(plugin: PluginCommand) -> Self {
+        PluginSink { plugin }
+    }
+}
+
+impl NotificationSink for PluginSink {
+    fn send(
+        &mut self,
+        name: &str,
+        display_name: &str,
+        content: &str,
+        icon: &str,
+    ) -> Result<(), NotifyError> {
+        let request = Request::Notify {
+            device: name,
+            display_name,
+            message: content,
+            icon,
+        };
+        call(&SystemRunner, &self.plugin, &request)?;
+        Ok(())
+    }
+}
+
+/// Run `plugin` with `request` on its standard input, returning its standard output
+fn call(
+    runner: &impl CommandRunner,
+    plugin: &PluginCommand,
+    request: &Request,
+) -> Result<String, BackendError> {
+    let mut input = encode(request);
+    input.push(b'\n');
+    let args: Vec<&str> = plugin.args.iter().map(String::as_str).collect();
+    let timeout = Duration::from_secs(plugin.timeout);
+    let output = runner
+        .run_with_timeout(&plugin.command, &args, &input, timeout)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::TimedOut => BackendError::timeout(&plugin.command, timeout),
+            _ => BackendError::spawn(&plugin.command, e),
+        })?;
+    if !output.is_success() {
+        return Err(BackendError::exit(&plugin.command, output.code));
+    }
+    Ok(output.stdout)
+}
+
+/// `request` as JSON, with the protocol version added
+pub(crate) fn encode(request: &Request) -> Vec<u8> {
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        version: u32,
+        #[serde(flatten)]
+        request: &'a Request<'a>,
+    }
+
+    // Requests only hold strings and numbers, which always serialize
+    serde_json::to_vec(&Envelope {
+        version: PROTOCOL_VERSION,
+        request,
+    })
+    .expect("plugin requests serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use hsc_core::command::{CommandOutput, FakeRunner};
+
+    use super::*;
+
+    fn plugin() -> PluginCommand {
+        PluginCommand {
+            command: "mouse-battery".to_string(),
+            args: vec!["--json".to_string()],
+            ..PluginCommand::default()
+        }
+    }
+
+    #[test]
+    fn poll_sends_request_and_parses_devices() {
+        let runner = FakeRunner::with_output(CommandOutput::success(
+            r#"{"devices": [{"name": "Mouse", "status": "charging", "level": 40},
+                            {"name": "Pad", "status": "disconnected"}]}"#,
+        ));
+        let poll = poll_with(&runner, &plugin()).unwrap();
+
+        assert_eq!(runner.calls(), [["mouse-battery", "--json"]]);
+        assert_eq!(runner.inputs(), ["{\"version\":1,\"request\":\"poll\"}\n"]);
+        assert!(poll.firmware.is_empty());
+        let devices: Vec<_> = poll
+            .devices
+            .into_iter()
+            .map(|d| (d.name.clone(), d.battery_status, d.level()))
+            .collect();
+        assert_eq!(
+            devices,
+            [
+                ("Mouse".to_string(), BatteryStatus::Charging, Some(40)),
+                ("Pad".to_string(), BatteryStatus::Disconnected, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn poll_reads_coarse_levels() {
+        let devices = parse_poll(
+            r#"{"devices": [{"name": "Keyboard", "status": "discharging", "level": 1, "max_level": 4},
+                            {"name": "Pad", "status": "discharging", "level": 5, "max_level": 4}]}"#,
+        )
+        .unwrap()
+        .devices;
+        assert_eq!(
+            devices[0].battery,
+            Some(BatteryReading::Level { current: 1, max: 4 })
+        );
+        assert_eq!(devices[1].battery, None);
+    }
+
+    #[test]
+    fn poll_reads_firmware_versions() {
+        let poll = parse_poll(
+            r#"{"devices": [{"name": "Mouse", "status": "charging", "firmware": "1.04"},
+                            {"name": "Pad", "status": "disconnected"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(poll.devices.len(), 2);
+        assert_eq!(poll.firmware, [("Mouse".to_string(), "1.04".to_string())]);
+    }
+
+    #[test]
+    fn poll_rejects_unknown_status() {
+        let err = parse_poll(r#"{"devices": [{"name": "Mouse", "status": "full"}]}"#).unwrap_err();
+        assert!(matches!(err, ParseError::UnknownStatus { .. }));
+    }
+
+    #[test]
+    fn poll_reports_failure() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(2),
+            stdout: String::new(),
+        });
+        let err = poll_with(&runner, &plugin()).unwrap_err();
+        assert_eq!(err.to_string(), "mouse-battery exited with code 2");
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn hung_plugins_are_killed() {
+        let plugin = PluginCommand {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "sleep 30".to_string()],
+            timeout: 1,
+        };
+        let started = std::time::Instant::now();
+        let err = poll_with(&SystemRunner, &plugin).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(err.to_string(), "sh did not answer within 1s");
+        assert!(err.is_retryable());
+    }
+}
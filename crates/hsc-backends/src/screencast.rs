@@ -0,0 +1,114 @@
+//! Screen sharing sessions
+//!
+//! xdg-desktop-portal only tells the application that started a ScreenCast session about it, so
+//! other programs cannot ask the portal whether the screen is shared. Every portal session
+//! streams the screen through a PipeWire video source, though, and those are listed by
+//! `pw-dump`. Unlike cameras, screen streams are not backed by a device, and they are running
+//! while an application is reading from them.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use hsc_core::command::{CommandRunner, SystemRunner};
+
+use crate::error::{BackendError, ParseError};
+
+const PROGRAM: &str = "pw-dump";
+
+/// An object as listed by `pw-dump`
+#[derive(Deserialize)]
+struct PwObject {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    info: Option<PwInfo>,
+}
+
+/// Details of a PipeWire node
+#[derive(Deserialize)]
+struct PwInfo {
+    /// `running`, `idle`, `suspended`, `creating` or `error`
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    props: HashMap<String, serde_json::Value>,
+}
+
+/// Whether the screen is currently being shared
+pub fn is_sharing() -> Result<bool, BackendError> {
+    is_sharing_with(&SystemRunner)
+}
+
+/// Whether the screen is currently being shared, running pw-dump with `runner`
+pub fn is_sharing_with(runner: &impl CommandRunner) -> Result<bool, BackendError> {
+    let output = runner
+        .run(PROGRAM, &[])
+        .map_err(|e| BackendError::spawn(PROGRAM, e))?;
+    if !output.is_success() {
+        return Err(BackendError::exit(PROGRAM, output.code));
+    }
+    parse_sharing(&output.stdout).map_err(|e| BackendError::parse(PROGRAM, e))
+}
+
+/// Whether `pw-dump` output lists a running video source that no device backs
+pub fn parse_sharing(stdout: &str) -> Result<bool, ParseError> {
+    let objects: Vec<PwObject> = serde_json::from_str(stdout)?;
+    Ok(objects
+        .into_iter()
+        .filter(|o| o.kind == "PipeWire:Interface:Node")
+        .filter_map(|o| o.info)
+        .any(|info| {
+            info.state.as_deref() == Some("running")
+                && info.props.get("media.class").and_then(|c| c.as_str()) == Some("Video/Source")
+                && !info.props.contains_key("device.id")
+                && !info.props.contains_key("device.api")
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use hsc_core::command::{CommandOutput, FakeRunner};
+
+    use super::*;
+
+    /// pw-dump output with a camera and a portal screen stream in `state`
+    fn dump(state: &str) -> String {
+        format!(
+            r#"[
+                {{"id": 0, "type": "PipeWire:Interface:Core", "info": {{"name": "pipewire-0"}}}},
+                {{"id": 52, "type": "PipeWire:Interface:Node", "info": {{"state": "running",
+                  "props": {{"media.class": "Video/Source", "device.api": "v4l2",
+                             "device.id": 48, "node.name": "v4l2_input.usb-camera"}}}}}},
+                {{"id": 91, "type": "PipeWire:Interface:Node", "info": {{"state": "{}",
+                  "props": {{"media.class": "Video/Source",
+                             "node.name": "xdg-desktop-portal-wlr"}}}}}},
+                {{"id": 93, "type": "PipeWire:Interface:Link", "info": {{"state": "active"}}}}
+            ]"#,
+            state
+        )
+    }
+
+    #[test]
+    fn running_portal_stream_is_sharing() {
+        assert!(parse_sharing(&dump("running")).unwrap());
+    }
+
+    #[test]
+    fn idle_stream_and_camera_are_not_sharing() {
+        assert!(!parse_sharing(&dump("suspended")).unwrap());
+    }
+
+    #[test]
+    fn failing_pw_dump_is_an_error() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(1),
+            stdout: String::new(),
+        });
+        assert!(matches!(
+            is_sharing_with(&runner),
+            Err(BackendError::Exit { code: Some(1), .. })
+        ));
+        assert_eq!(runner.calls(), [["pw-dump"]]);
+    }
+}
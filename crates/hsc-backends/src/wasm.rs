@@ -0,0 +1,351 @@
+//! Sandboxed WebAssembly plugins
+//!
+//! A module can act as a provider, a sink and a policy at once, depending on which functions it
+//! exports. Data crosses the boundary as UTF-8 JSON in the module's linear memory, using the same
+//! messages as [stdio plugins](crate::plugin):
+//!
+//! - `memory` and `alloc(len: i32) -> i32`, returning a buffer the host writes input to, are
+//!   always required
+//! - `poll() -> i64` makes the module a provider; it answers a poll
+//! - `notify(ptr: i32, len: i32)` makes it a sink; it receives a notify request
+//! - `on_event(ptr: i32, len: i32) -> i64` makes it a policy; it receives an event request
+//!   (`"request": "event"`) with the device `status` and `level` and an `event` object, a
+//!   [`EventKind`](hsc_core::schema::EventKind), and answers
+//!   `{"notifications": [{"message": "...", "icon": "..."}]}`
+//!
+//! Results are returned as `ptr << 32 | len`, 0 meaning no output. Modules cannot import
+//! anything from the host, and every call is limited in the amount of work it may do.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use serde::Deserialize;
+use wasmi::{Config, Engine, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+use hsc_core::{
+    bus::Subscriber,
+    device::{Device, DeviceEvent},
+    error::NotifyError,
+    monitor::Monitor,
+    notify::NotificationSink,
+    schema::Status,
+};
+
+use crate::{
+    error::{BackendError, ParseError},
+    plugin::{encode, parse_poll, Poll, Request},
+};
+
+/// Fuel given to every call, bounding the work a module may do
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// A policy's answer to an event
+#[derive(Deserialize)]
+struct EventResponse {
+    #[serde(default)]
+    notifications: Vec<Notification>,
+}
+
+/// A notification raised by a policy
+#[derive(Deserialize)]
+struct Notification {
+    message: String,
+    #[serde(default = "default_icon")]
+    icon: String,
+}
+
+fn default_icon() -> String {
+    "battery".to_string()
+}
+
+/// A loaded WebAssembly module
+pub struct WasmPlugin {
+    path: PathBuf,
+    store: Store<()>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+impl WasmPlugin {
+    /// Load and instantiate the module at `path`
+    pub fn load(path: &Path) -> Result<Self, BackendError> {
+        let mut plugin = Self::from_bytes(&fs::read(path).map_err(other)?)?;
+        plugin.path = path.to_path_buf();
+        Ok(plugin)
+    }
+
+    /// Instantiate a module from its binary form
+    pub fn from_bytes(wasm: &[u8]) -> Result<Self, BackendError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(other)?;
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(FUEL_PER_CALL).map_err(other)?;
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(other)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| other("module does not export its memory"))?;
+        let alloc = instance.get_typed_func(&store, "alloc").map_err(other)?;
+        Ok(WasmPlugin {
+            path: PathBuf::new(),
+            store,
+            instance,
+            memory,
+            alloc,
+        })
+    }
+
+    /// Whether the module exports `name`
+    fn exports(&self, name: &str) -> bool {
+        self.instance.get_export(&self.store, name).is_some()
+    }
+
+    /// Whether the module provides devices
+    pub fn is_provider(&self) -> bool {
+        self.exports("poll")
+    }
+
+    /// Whether the module receives notifications
+    pub fn is_sink(&self) -> bool {
+        self.exports("notify")
+    }
+
+    /// Whether the module reacts to device events
+    pub fn is_policy(&self) -> bool {
+        self.exports("on_event")
+    }
+
+    /// Ask the module for its devices
+    pub fn poll(&mut self) -> Result<Poll, BackendError> {
+        self.refuel()?;
+        let poll = self
+            .instance
+            .get_typed_func::<(), i64>(&self.store, "poll")
+            .map_err(other)?;
+        let result = poll.call(&mut self.store, ()).map_err(other)?;
+        parse_poll(&self.read(result)?).map_err(|e| self.parse_error(e))
+    }
+
+    /// Deliver a notification to the module
+    fn notify(&mut self, request: &Request) -> Result<(), BackendError> {
+        self.refuel()?;
+        let (ptr, len) = self.write(&encode(request))?;
+        let notify = self
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&self.store, "notify")
+            .map_err(other)?;
+        notify.call(&mut self.store, (ptr, len)).map_err(other)
+    }
+
+    /// Hand an event to the module, returning the notifications it raises
+    fn on_event(&mut self, request: &Request) -> Result<Vec<Notification>, BackendError> {
+        self.refuel()?;
+        let (ptr, len) = self.write(&encode(request))?;
+        let on_event = self
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&self.store, "on_event")
+            .map_err(other)?;
+        let result = on_event.call(&mut self.store, (ptr, len)).map_err(other)?;
+        if result == 0 {
+            return Ok(Vec::new());
+        }
+        let response: EventResponse =
+            serde_json::from_str(&self.read(result)?).map_err(|e| self.parse_error(e))?;
+        Ok(response.notifications)
+    }
+
+    /// Reset the work budget before a call
+    fn refuel(&mut self) -> Result<(), BackendError> {
+        self.store.set_fuel(FUEL_PER_CALL).map_err(other)
+    }
+
+    /// Copy `data` into a buffer allocated by the module, returning its address and length
+    fn write(&mut self, data: &[u8]) -> Result<(i32, i32), BackendError> {
+        let len = i32::try_from(data.len()).map_err(other)?;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(other)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, data)
+            .map_err(other)?;
+        Ok((ptr, len))
+    }
+
+    /// Read the string at the `ptr << 32 | len` location returned by the module
+    fn read(&self, location: i64) -> Result<String, BackendError> {
+        let ptr = (location as u64 >> 32) as usize;
+        let len = (location as u64 & u32::MAX as u64) as usize;
+        let mut buffer = vec![0; len];
+        self.memory
+            .read(&self.store, ptr, &mut buffer)
+            .map_err(other)?;
+        String::from_utf8(buffer).map_err(other)
+    }
+
+    /// Error for output of the module that does not follow the protocol
+    fn parse_error(&self, e: impl Into<ParseError>) -> BackendError {
+        BackendError::parse(&self.path.display().to_string(), e)
+    }
+
+    /// File the module was loaded from, for messages
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// A module shared between the roles it plays
+pub type SharedPlugin = Arc<Mutex<WasmPlugin>>;
+
+/// Lock a shared module, recovering it if a previous call panicked
+pub fn lock(plugin: &SharedPlugin) -> MutexGuard<'_, WasmPlugin> {
+    plugin.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A module acting as a notification sink
+pub struct WasmSink(pub SharedPlugin);
+
+impl NotificationSink for WasmSink {
+    fn send(
+        &mut self,
+        name: &str,
+        display_name: &str,
+        content: &str,
+        icon: &str,
+    ) -> Result<(), NotifyError> {
+        lock(&self.0).notify(&Request::Notify {
+            device: name,
+            display_name,
+            message: content,
+            icon,
+        })?;
+        Ok(())
+    }
+}
+
+/// A module acting as a policy, raising notifications in response to events
+pub struct WasmPolicy(pub SharedPlugin);
+
+impl Subscriber for WasmPolicy {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        let name = event.device();
+        let device = monitor.devices.get(name);
+        let request = Request::Event {
+            device: name,
+            display_name: monitor.config.display_name(name),
+            status: device.map_or(Status::Disconnected, |d| d.battery_status.into()),
+            level: device.and_then(Device::level),
+            event: event.into(),
+        };
+        let mut plugin = lock(&self.0);
+        match plugin.on_event(&request) {
+            Ok(notifications) => {
+                drop(plugin);
+                for n in notifications {
+                    monitor.notify(name, &n.message, &n.icon);
+                }
+            }
+            Err(e) => eprintln!("wasm policy {} failed: {}", plugin.path().display(), e),
+        }
+    }
+}
+
+/// Wrap a wasmi, file or conversion error
+fn other(e: impl ToString) -> BackendError {
+    BackendError::Wasm(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use hsc_core::{device::BatteryStatus, schema::EventKind};
+
+    use super::*;
+
+    /// Module with `alloc` and `memory`, a constant string at address 16 and the given functions
+    fn module(data: &str, funcs: &str) -> WasmPlugin {
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "{}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                {})"#,
+            data.replace('"', "\\\""),
+            funcs
+        );
+        WasmPlugin::from_bytes(&wat::parse_str(wat).unwrap()).unwrap()
+    }
+
+    /// `ptr << 32 | len` for the constant string
+    fn location(data: &str) -> i64 {
+        (16 << 32) | data.len() as i64
+    }
+
+    #[test]
+    fn provider_reports_devices() {
+        let data = r#"{"devices": [{"name": "Mouse", "status": "discharging", "level": 42}]}"#;
+        let mut plugin = module(
+            data,
+            &format!(
+                r#"(func (export "poll") (result i64) (i64.const {}))"#,
+                location(data)
+            ),
+        );
+        assert!(plugin.is_provider());
+        assert!(!plugin.is_sink() && !plugin.is_policy());
+
+        let devices: Vec<_> = plugin
+            .poll()
+            .unwrap()
+            .devices
+            .into_iter()
+            .map(|d| (d.name.clone(), d.battery_status, d.level()))
+            .collect();
+        assert_eq!(
+            devices,
+            [("Mouse".to_string(), BatteryStatus::Discharging, Some(42))]
+        );
+    }
+
+    #[test]
+    fn policy_receives_event_and_raises_notifications() {
+        let data = r#"{"notifications": [{"message": "Bedtime"}]}"#;
+        // Answers only when the request was written where `alloc` said
+        let mut plugin = module(
+            data,
+            &format!(
+                r#"(func (export "on_event") (param i32 i32) (result i64)
+                    (if (result i64) (i32.eq (i32.load8_u (local.get 0)) (i32.const 123))
+                        (then (i64.const {}))
+                        (else (i64.const 0))))"#,
+                location(data)
+            ),
+        );
+        assert!(plugin.is_policy());
+
+        let request = Request::Event {
+            device: "Arctis 7",
+            display_name: "Arctis 7",
+            status: Status::Discharging,
+            level: Some(12),
+            event: EventKind::Full,
+        };
+        let notifications = plugin.on_event(&request).unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].message, "Bedtime");
+        assert_eq!(notifications[0].icon, "battery");
+    }
+
+    #[test]
+    fn runaway_module_is_stopped() {
+        let mut plugin = module(
+            "",
+            r#"(func (export "poll") (result i64) (loop (br 0)) (i64.const 0))"#,
+        );
+        assert!(plugin.poll().is_err());
+    }
+}
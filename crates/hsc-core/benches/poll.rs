@@ -0,0 +1,130 @@
+//! Cost of one poll: parsing headsetcontrol output, updating the monitor and delivering the
+//! notifications it raises, for growing numbers of devices
+//!
+//! Run with `cargo bench -p hsc-core`; criterion compares each run with the previous one.
+
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use hsc_core::{
+    clock::ManualClock, config::Config, device::parse_devices, error::NotifyError,
+    notify::NotificationSink, Monitor,
+};
+
+/// Numbers of devices each benchmark is run with
+const DEVICES: [usize; 3] = [1, 4, 16];
+
+/// `headsetcontrol -b` output for `devices` devices at `level`
+fn output(devices: usize, level: u8, status: &str) -> String {
+    (0..devices)
+        .map(|i| format!("Found Headset {i}!\n\nBattery:\n\tStatus: {status}\n\tLevel: {level}%\n"))
+        .collect()
+}
+
+/// Sink accepting every notification without doing anything
+struct NullSink;
+
+impl NotificationSink for NullSink {
+    fn send(
+        &mut self,
+        name: &str,
+        display_name: &str,
+        content: &str,
+        icon: &str,
+    ) -> Result<(), NotifyError> {
+        black_box((name, display_name, content, icon));
+        Ok(())
+    }
+}
+
+/// Monitor on a virtual clock delivering notifications to `sinks` null sinks only
+fn monitor(sinks: usize) -> Monitor {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    for _ in 0..sinks {
+        monitor.add_sink(NullSink);
+    }
+    monitor
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for devices in DEVICES {
+        let output = output(devices, 62, "BATTERY_AVAILABLE");
+        group.throughput(Throughput::Elements(devices as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(devices),
+            &output,
+            |b, output| b.iter(|| parse_devices(black_box(output))),
+        );
+    }
+    group.finish();
+}
+
+/// Readings that change nothing, the common case between two notifications
+fn update_unchanged(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_unchanged");
+    for devices in DEVICES {
+        let output = output(devices, 62, "BATTERY_AVAILABLE");
+        let mut monitor = monitor(1);
+        monitor.update(&output);
+        group.throughput(Throughput::Elements(devices as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(devices),
+            &output,
+            |b, output| b.iter(|| monitor.update(black_box(output))),
+        );
+    }
+    group.finish();
+}
+
+/// A full discharge from connection to empty, raising level changes, threshold crossings and
+/// their notifications
+fn update_discharge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_discharge");
+    for devices in DEVICES {
+        let outputs: Vec<String> = (0..=100)
+            .rev()
+            .map(|level| output(devices, level, "BATTERY_AVAILABLE"))
+            .collect();
+        group.throughput(Throughput::Elements((devices * outputs.len()) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(devices),
+            &outputs,
+            |b, outputs| {
+                b.iter_batched(
+                    || monitor(1),
+                    |mut monitor| {
+                        for output in outputs {
+                            black_box(monitor.update(output));
+                        }
+                        monitor
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Delivering one notification to growing numbers of sinks
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    for sinks in DEVICES {
+        let mut monitor = monitor(sinks);
+        group.throughput(Throughput::Elements(sinks as u64));
+        group.bench_function(BenchmarkId::from_parameter(sinks), |b| {
+            b.iter(|| monitor.notify("Headset 0", "Battery level low: 9%", "battery-low"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, update_unchanged, update_discharge, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! Sound server nodes and matching them to headsets
+
+use crate::config::Config;
+
+/// Whether a node plays or records audio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Sink,
+    Source,
+}
+
+/// An audio output or input known to the sound server
+#[derive(Clone, Debug)]
+pub struct AudioNode {
+    /// Sound server name of the node
+    pub name: String,
+    /// Human readable description shown by volume applets
+    pub description: String,
+    /// Sink or source
+    pub kind: NodeKind,
+    /// Whether audio is flowing through the node right now
+    pub running: bool,
+}
+
+/// Whether an audio node belongs to the given headset
+///
+/// A configured `audio_device` is matched against the node name and description; otherwise the
+/// description must contain the device name, with or without its leading vendor word.
+pub fn node_matches(config: &Config, node: &AudioNode, device_name: &str) -> bool {
+    let description = node.description.to_lowercase();
+    if let Some(pattern) = config
+        .rule(device_name)
+        .and_then(|r| r.audio_device.as_ref())
+    {
+        let pattern = pattern.to_lowercase();
+        return node.name.to_lowercase().contains(&pattern) || description.contains(&pattern);
+    }
+
+    let name = device_name.to_lowercase();
+    let without_vendor = name.split_once(' ').map_or(name.as_str(), |(_, rest)| rest);
+    description.contains(&name) || description.contains(without_vendor)
+}
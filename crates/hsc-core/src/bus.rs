@@ -0,0 +1,45 @@
+//! Event bus connecting the device state machine to its consumers
+//!
+//! [`Monitor::update`] turns every reading into [`DeviceEvent`]s and hands them to each
+//! [`Subscriber`] in turn. Notifications are raised by the [`Notifier`](crate::notify::Notifier)
+//! subscriber, and embedders can add their own loggers or exporters with
+//! [`Monitor::subscribe`].
+
+use crate::{device::DeviceEvent, monitor::Monitor};
+
+/// A consumer of device events
+pub trait Subscriber: Send {
+    /// Handle one event; `monitor` already holds the reading that produced it
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent);
+}
+
+impl<F: FnMut(&mut Monitor, &DeviceEvent) + Send> Subscriber for F {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        self(monitor, event)
+    }
+}
+
+/// Subscribers, in the order they receive events
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    /// Add a subscriber after the existing ones
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Hand `event` to every subscriber
+    pub fn publish(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        for subscriber in self.subscribers.iter_mut() {
+            subscriber.handle(monitor, event);
+        }
+    }
+
+    /// Append the subscribers of `other` after the existing ones
+    pub fn append(&mut self, other: &mut EventBus) {
+        self.subscribers.append(&mut other.subscribers);
+    }
+}
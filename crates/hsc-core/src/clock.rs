@@ -0,0 +1,97 @@
+//! Source of time for everything that depends on it
+//!
+//! Snoozes, rate estimates, notification timestamps and polling all read time through a
+//! [`Clock`], so tests can run against a [`ManualClock`] and advance virtual time instead of
+//! sleeping.
+
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+/// Monotonic and wall-clock time, and a way to wait
+pub trait Clock: Send + Sync {
+    /// Current monotonic time
+    fn now(&self) -> Instant;
+
+    /// Current local wall-clock time
+    fn local(&self) -> DateTime<Local>;
+
+    /// Block for `duration`
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real system clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Virtual time that only moves when advanced, for tests and replays
+#[derive(Debug)]
+pub struct ManualClock {
+    /// Monotonic time at creation
+    start: Instant,
+    /// Wall-clock time at creation
+    local_start: DateTime<Local>,
+    /// Time advanced since creation
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Clock starting at `local`
+    pub fn new(local: DateTime<Local>) -> Self {
+        ManualClock {
+            start: Instant::now(),
+            local_start: local,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move time forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+
+    /// Time advanced since creation
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ManualClock {
+    /// Clock starting at the current local time
+    fn default() -> Self {
+        ManualClock::new(Local::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn local(&self) -> DateTime<Local> {
+        self.local_start + self.elapsed()
+    }
+
+    /// Advances the clock instead of blocking
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
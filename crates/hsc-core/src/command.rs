@@ -0,0 +1,376 @@
+//! Running external programs
+//!
+//! Code that shells out takes a [`CommandRunner`] so it can be exercised with canned output from
+//! a `FakeRunner`, built with the `testing` feature, instead of touching the real system.
+
+#[cfg(any(test, feature = "testing"))]
+use std::{cell::RefCell, collections::VecDeque};
+use std::{
+    io::{self, Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What a finished program produced
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Exit code, `None` if the program was killed by a signal
+    pub code: Option<i32>,
+    /// Everything written to standard output
+    pub stdout: String,
+}
+
+impl CommandOutput {
+    /// Output of a program that exited successfully after printing `stdout`
+    pub fn success(stdout: &str) -> Self {
+        CommandOutput {
+            code: Some(0),
+            stdout: stdout.to_string(),
+        }
+    }
+
+    /// Whether the program exited with code 0
+    pub fn is_success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Runs a program to completion and captures its output
+pub trait CommandRunner {
+    /// Run `program` with `args`, failing only if it could not be started
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput>;
+
+    /// Run `program` with `args`, writing `input` to its standard input
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> io::Result<CommandOutput>;
+
+    /// Like [`run_with_input`](Self::run_with_input), but kill `program` if it is still running
+    /// after `timeout`, failing with [`io::ErrorKind::TimedOut`]
+    fn run_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        timeout: Duration,
+    ) -> io::Result<CommandOutput>;
+}
+
+/// Runs programs found in `PATH`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let output = Command::new(program).args(args).output()?;
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        })
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> io::Result<CommandOutput> {
+        let mut child = spawn(program, args)?;
+        if let Some(stdin) = child.stdin.take() {
+            write_input(stdin, input)?;
+        }
+        let output = child.wait_with_output()?;
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        })
+    }
+
+    fn run_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        timeout: Duration,
+    ) -> io::Result<CommandOutput> {
+        let mut child = spawn(program, args)?;
+        // Write and read on other threads, so that a program ignoring its input or filling the
+        // pipe is still killed once the deadline passes
+        let stdin = child.stdin.take();
+        let input = input.to_vec();
+        let writer = thread::spawn(move || match stdin {
+            Some(stdin) => write_input(stdin, &input),
+            None => Ok(()),
+        });
+        let mut stdout = child.stdout.take();
+        let reader = thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(stdout) = &mut stdout {
+                stdout.read_to_end(&mut buf)?;
+            }
+            Ok::<_, io::Error>(buf)
+        });
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                child.kill()?;
+                child.wait()?;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{program} did not finish within {}s", timeout.as_secs_f32()),
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        // A writer still blocked is left behind: the pipe is kept open by a program's children
+        if writer.is_finished() {
+            writer.join().expect("stdin writer does not panic")?;
+        }
+        let stdout = reader.join().expect("stdout reader does not panic")?;
+        Ok(CommandOutput {
+            code: status.code(),
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+        })
+    }
+}
+
+/// Start `program` with `args`, with pipes for its standard input and output
+fn spawn(program: &str, args: &[&str]) -> io::Result<Child> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+}
+
+/// Write `input` to the standard input of a program and close it
+fn write_input(mut stdin: ChildStdin, input: &[u8]) -> io::Result<()> {
+    // A program that exits without reading its input is not an error
+    match stdin.write_all(input) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Runs programs on another machine over SSH, starting `ssh` with its runner
+///
+/// `ssh` runs without prompting, so the destination must accept key authentication. Failing to
+/// connect is reported like failing to start the program, rather than as the program's exit code.
+#[derive(Clone, Debug)]
+pub struct SshRunner<R = SystemRunner> {
+    /// Where to connect, as `host` or `user@host`
+    pub destination: String,
+    pub runner: R,
+}
+
+impl SshRunner {
+    /// Runner for programs on `destination`
+    pub fn new(destination: &str) -> Self {
+        SshRunner {
+            destination: destination.to_string(),
+            runner: SystemRunner,
+        }
+    }
+}
+
+/// Exit code of `ssh` itself failing, as opposed to the remote program
+const SSH_FAILED: i32 = 255;
+
+impl<R: CommandRunner> SshRunner<R> {
+    /// Arguments of `ssh` running `program` with `args` on the destination
+    fn ssh_args(&self, program: &str, args: &[&str]) -> Vec<String> {
+        // The remote shell splits the command again, so every word is quoted
+        let command: Vec<String> = std::iter::once(program)
+            .chain(args.iter().copied())
+            .map(|word| format!("'{}'", word.replace('\'', r"'\''")))
+            .collect();
+        [
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            &self.destination,
+            "--",
+            &command.join(" "),
+        ]
+        .map(str::to_string)
+        .to_vec()
+    }
+
+    /// `output` of `ssh`, or an error if it could not connect
+    fn connected(&self, output: CommandOutput) -> io::Result<CommandOutput> {
+        if output.code == Some(SSH_FAILED) {
+            return Err(io::Error::other(format!(
+                "cannot connect to {} over SSH",
+                self.destination
+            )));
+        }
+        Ok(output)
+    }
+}
+
+impl<R: CommandRunner> CommandRunner for SshRunner<R> {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let ssh_args = self.ssh_args(program, args);
+        let ssh_args: Vec<&str> = ssh_args.iter().map(String::as_str).collect();
+        self.connected(self.runner.run("ssh", &ssh_args)?)
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> io::Result<CommandOutput> {
+        let ssh_args = self.ssh_args(program, args);
+        let ssh_args: Vec<&str> = ssh_args.iter().map(String::as_str).collect();
+        self.connected(self.runner.run_with_input("ssh", &ssh_args, input)?)
+    }
+
+    fn run_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        timeout: Duration,
+    ) -> io::Result<CommandOutput> {
+        let ssh_args = self.ssh_args(program, args);
+        let ssh_args: Vec<&str> = ssh_args.iter().map(String::as_str).collect();
+        self.connected(
+            self.runner
+                .run_with_timeout("ssh", &ssh_args, input, timeout)?,
+        )
+    }
+}
+
+/// Runs programs on this machine, or on a remote one over SSH
+#[derive(Clone, Debug)]
+pub enum HostRunner {
+    Local(SystemRunner),
+    Remote(SshRunner),
+}
+
+impl HostRunner {
+    /// Runner for programs on `remote` if given, on this machine otherwise
+    pub fn new(remote: Option<&str>) -> Self {
+        match remote {
+            Some(destination) => HostRunner::Remote(SshRunner::new(destination)),
+            None => HostRunner::Local(SystemRunner),
+        }
+    }
+}
+
+impl CommandRunner for HostRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        match self {
+            HostRunner::Local(runner) => runner.run(program, args),
+            HostRunner::Remote(runner) => runner.run(program, args),
+        }
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> io::Result<CommandOutput> {
+        match self {
+            HostRunner::Local(runner) => runner.run_with_input(program, args, input),
+            HostRunner::Remote(runner) => runner.run_with_input(program, args, input),
+        }
+    }
+
+    fn run_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        timeout: Duration,
+    ) -> io::Result<CommandOutput> {
+        match self {
+            HostRunner::Local(runner) => runner.run_with_timeout(program, args, input, timeout),
+            HostRunner::Remote(runner) => runner.run_with_timeout(program, args, input, timeout),
+        }
+    }
+}
+
+/// Answers with queued outputs and records every invocation, for tests
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Default)]
+pub struct FakeRunner {
+    outputs: RefCell<VecDeque<CommandOutput>>,
+    calls: RefCell<Vec<Vec<String>>>,
+    inputs: RefCell<Vec<String>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl FakeRunner {
+    /// Runner answering the next invocation with `output`
+    pub fn with_output(output: CommandOutput) -> Self {
+        let runner = FakeRunner::default();
+        runner.push(output);
+        runner
+    }
+
+    /// Queue `output` as the answer to a later invocation
+    pub fn push(&self, output: CommandOutput) {
+        self.outputs.borrow_mut().push_back(output);
+    }
+
+    /// Every invocation so far, each as the program followed by its arguments
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.borrow().clone()
+    }
+
+    /// Standard input given to every invocation so far, empty for those run without any
+    pub fn inputs(&self) -> Vec<String> {
+        self.inputs.borrow().clone()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl CommandRunner for FakeRunner {
+    /// Returns the next queued output, or a successful empty one once the queue is exhausted
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        self.run_with_input(program, args, &[])
+    }
+
+    fn run_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+    ) -> io::Result<CommandOutput> {
+        self.inputs
+            .borrow_mut()
+            .push(String::from_utf8_lossy(input).to_string());
+        let call = std::iter::once(program)
+            .chain(args.iter().copied())
+            .map(str::to_string)
+            .collect();
+        self.calls.borrow_mut().push(call);
+        Ok(self
+            .outputs
+            .borrow_mut()
+            .pop_front()
+            .unwrap_or_else(|| CommandOutput::success("")))
+    }
+
+    /// Answers like [`run_with_input`](Self::run_with_input), queued outputs never time out
+    fn run_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &[u8],
+        _timeout: Duration,
+    ) -> io::Result<CommandOutput> {
+        self.run_with_input(program, args, input)
+    }
+}
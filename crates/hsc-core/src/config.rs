@@ -0,0 +1,624 @@
+//! Runtime configuration, loaded from a TOML file
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    command::CommandRunner,
+    error::ConfigError,
+    notify::is_critical,
+    platform::{ColorScheme, Native, NotificationOptions, Platform},
+};
+
+/// Configuration for the battery notifier
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Config {
+    /// Interval between polls in milliseconds
+    pub polling_interval: u64,
+    /// Enable debug output
+    pub debug: bool,
+    /// Battery level threshold for low battery notifications
+    pub battery_threshold: u8,
+    /// Also warn when fewer than this many minutes of use are estimated to remain
+    pub battery_threshold_minutes: Option<u32>,
+    /// Warn when a device drains this many times faster than its usual rate; 0 disables
+    pub abnormal_discharge_factor: f64,
+    /// Warn when a device starts discharging with less charge than a typical day of use needs
+    pub usage_forecast: bool,
+    /// Notify every time the battery level crosses a multiple of this step
+    pub notification_step: u8,
+    /// Which level changes are announced
+    pub notification_mode: NotificationMode,
+    /// Round percentages down to multiples of this, for devices whose level jitters; 1 keeps
+    /// them exact
+    pub level_bucket: u8,
+    /// Notify when a device is plugged in or unplugged
+    pub notify_charging: bool,
+    /// Announce devices running low in the same poll with one notification listing them all
+    pub group_low_alerts: bool,
+    /// Hold notifications other than low battery alerts while the screen is being shared, and
+    /// send the latest one of each device once sharing ends
+    pub hold_while_screen_sharing: bool,
+    /// Hold notifications other than low battery alerts while the microphone of a connected
+    /// headset is in use, and send the latest one of each device once the call ends
+    pub hold_during_calls: bool,
+    /// Number of recent notifications kept in memory
+    pub event_log_len: usize,
+    /// Persistent history store
+    pub history: HistoryConfig,
+    /// Where notifications are delivered
+    pub sinks: Vec<Sink>,
+    /// Application name notifications are sent under, for notification daemon rules; the
+    /// notification program's default if unset
+    pub app_name: Option<String>,
+    /// How each class of notifications is filed, over the global and per-device settings
+    pub notifications: NotificationsConfig,
+    /// Run headsetcontrol on this SSH destination (`user@host`) instead of locally, to watch a
+    /// headset plugged into another machine
+    pub remote: Option<String>,
+    /// Device shown by panel indicators when it is connected
+    pub primary_device: Option<String>,
+    /// Sound server integration
+    pub audio: AudioConfig,
+    /// Readings besides the battery, such as chatmix
+    pub extras: ExtrasConfig,
+    /// Shorter auto-shutoff while the battery is low
+    pub power_saving: PowerSavingConfig,
+    /// Reminder to charge before the computer shuts down
+    pub shutdown_reminder: ShutdownReminderConfig,
+    /// On-screen bar flashed for low battery on Wayland
+    pub osd: OsdConfig,
+    /// Notifications about firmware updates
+    pub firmware: FirmwareConfig,
+    /// Light and dark icon variants for notifications and the tray
+    pub icons: IconsConfig,
+    /// CSV battery history log
+    pub csv: CsvConfig,
+    /// Periodic usage summary notifications
+    pub summary: SummaryConfig,
+    /// External programs providing devices or receiving notifications
+    pub plugins: PluginsConfig,
+    /// Rhai scripts run on every device event to raise custom notifications
+    pub rules: Vec<PathBuf>,
+    /// Per-device overrides, keyed by the device name reported by headsetcontrol
+    pub devices: BTreeMap<String, DeviceRule>,
+}
+
+/// Which battery level changes are announced
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationMode {
+    /// Every `notification_step` percent, besides the low threshold and full charge
+    #[default]
+    Steps,
+    /// Only the low threshold, connection changes and full charge
+    ThresholdsOnly,
+}
+
+/// A destination for notifications
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Sink {
+    /// Desktop notification through the system's notification service
+    Desktop,
+    /// A line on standard output
+    Stdout,
+    /// Low battery alerts broadcast to every terminal with `wall`, or written to the controlling
+    /// terminal if that fails, for machines nobody watches a desktop on
+    Wall,
+}
+
+/// Sound server integration settings
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Append the battery level to the description of the headset's sinks and sources, which
+    /// only PulseAudio allows
+    pub annotate_description: bool,
+    /// Only send routine level notifications for the device backing the default sink or source
+    pub only_active_output: bool,
+    /// Watch whether audio plays through the headset, to tell when it turned itself off while
+    /// idle rather than losing its connection
+    pub track_usage: bool,
+    /// Make the headset's sink the default output when it connects, and switch back to the
+    /// previous default when it disconnects
+    pub switch_default_output: bool,
+}
+
+/// Settings for readings besides the battery
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ExtrasConfig {
+    /// Also read chatmix and capabilities on every poll, for indicators and `status`
+    pub poll: bool,
+    /// Notify when the chatmix dial is moved
+    pub notify_chatmix: bool,
+}
+
+/// Built-in power-saving policy settings
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct PowerSavingConfig {
+    /// Shorten the inactive time of devices below the low battery threshold
+    pub enabled: bool,
+    /// Minutes without audio before a headset running low turns itself off
+    pub low_battery_inactive_time: u8,
+    /// Inactive time in minutes restored once the device has charged, 0 to never turn off
+    pub inactive_time: u8,
+}
+
+impl Default for PowerSavingConfig {
+    fn default() -> Self {
+        PowerSavingConfig {
+            enabled: false,
+            low_battery_inactive_time: 5,
+            inactive_time: 30,
+        }
+    }
+}
+
+/// Settings of the reminder sent when the computer shuts down
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ShutdownReminderConfig {
+    /// Delay shutdowns and reboots to remind about devices that need charging
+    pub enabled: bool,
+    /// Battery percentage below which a discharging device is mentioned
+    pub below: u8,
+}
+
+impl Default for ShutdownReminderConfig {
+    fn default() -> Self {
+        ShutdownReminderConfig {
+            enabled: false,
+            below: 50,
+        }
+    }
+}
+
+/// Settings of the on-screen bar shown when a device runs low, for fullscreen applications
+/// hiding notifications
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct OsdConfig {
+    /// Flash a bar across the top of the screen when a device drops below its low threshold
+    pub enabled: bool,
+    /// Seconds the bar stays on screen
+    pub duration: u64,
+    /// Height of the bar in pixels
+    pub height: u32,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        OsdConfig {
+            enabled: false,
+            duration: 3,
+            height: 12,
+        }
+    }
+}
+
+/// Firmware update checks against a table of known latest versions
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct FirmwareConfig {
+    /// Notify once when a device reports firmware older than its latest version
+    pub enabled: bool,
+    /// Latest firmware version per device name, e.g. from the vendor's release notes
+    pub latest: BTreeMap<String, String>,
+}
+
+/// Icon variants drawn for light and dark desktops
+///
+/// Monochrome symbolic icons are often not recolored by notification daemons and panels, so
+/// icon sets ship variants for each color scheme under names with a suffix.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct IconsConfig {
+    /// Color scheme to choose icons for, instead of detecting the desktop's
+    pub scheme: Option<ColorScheme>,
+    /// Appended to icon names on light desktops
+    pub light_suffix: String,
+    /// Appended to icon names on dark desktops
+    pub dark_suffix: String,
+}
+
+impl IconsConfig {
+    /// Whether icons differ between color schemes
+    pub fn has_variants(&self) -> bool {
+        self.light_suffix != self.dark_suffix
+    }
+
+    /// Color scheme to choose icons for: the configured one, or the one `platform` detects
+    /// with `runner`
+    pub fn color_scheme(
+        &self,
+        platform: &impl Platform,
+        runner: &impl CommandRunner,
+    ) -> Option<ColorScheme> {
+        self.scheme.or_else(|| platform.color_scheme(runner))
+    }
+
+    /// Suffix of icon names for `scheme`, treating an unknown scheme as light, unless it is empty
+    pub fn suffix(&self, scheme: Option<ColorScheme>) -> Option<&str> {
+        let suffix = match scheme {
+            Some(ColorScheme::Dark) => &self.dark_suffix,
+            Some(ColorScheme::Light) | None => &self.light_suffix,
+        };
+        Some(suffix.as_str()).filter(|suffix| !suffix.is_empty())
+    }
+}
+
+/// SQLite history store settings
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Record readings and notifications
+    pub enabled: bool,
+    /// Database file, defaults to `history.db` in the data directory
+    pub path: Option<PathBuf>,
+    /// Days raw readings are kept before being compacted into hourly aggregates; 0 keeps them
+    pub raw_retention_days: u32,
+    /// Days hourly aggregates and notifications are kept; 0 keeps them
+    pub aggregate_retention_days: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            enabled: true,
+            path: None,
+            raw_retention_days: 30,
+            aggregate_retention_days: 365,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// Location of the database file
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| Native.data_dir().join("history.db"))
+    }
+}
+
+/// CSV history logging settings
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct CsvConfig {
+    /// File readings are appended to; logging is disabled when unset
+    pub path: Option<PathBuf>,
+    /// Seconds between flushes to disk
+    pub flush_interval: u64,
+    /// Size in bytes after which the file is rotated to `<path>.1`; unlimited when unset
+    pub max_size: Option<u64>,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        CsvConfig {
+            path: None,
+            flush_interval: 60,
+            max_size: None,
+        }
+    }
+}
+
+/// How often a usage summary is sent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SummarySchedule {
+    /// Never
+    #[default]
+    Off,
+    /// Every morning, covering the previous day
+    Daily,
+    /// Every Monday morning, covering the previous week
+    Weekly,
+}
+
+/// Usage summary notification settings
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SummaryConfig {
+    /// When to send summaries
+    pub schedule: SummarySchedule,
+    /// Local hour from which the summary is sent
+    pub hour: u32,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        SummaryConfig {
+            schedule: SummarySchedule::Off,
+            hour: 9,
+        }
+    }
+}
+
+/// External plugin programs, speaking JSON over standard input and output
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Programs asked for device readings on every poll
+    pub providers: Vec<PluginCommand>,
+    /// Programs handed every notification that is sent
+    pub sinks: Vec<PluginCommand>,
+    /// WebAssembly modules, each acting as a provider, sink or policy depending on its exports
+    pub wasm: Vec<PathBuf>,
+}
+
+/// How to start a plugin
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct PluginCommand {
+    /// Program to run, looked up in `PATH` unless it is a path
+    pub command: String,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    /// Seconds the program may take to answer before it is killed
+    pub timeout: u64,
+}
+
+impl Default for PluginCommand {
+    fn default() -> Self {
+        PluginCommand {
+            command: String::new(),
+            args: Vec::new(),
+            timeout: 10,
+        }
+    }
+}
+
+/// Overrides applied to classes of notifications, whichever device they are about
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Low battery alerts, including the one listing several devices running low together
+    pub low: NotificationClass,
+}
+
+/// How notifications of one class are filed by the notification daemon
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct NotificationClass {
+    /// Application name these notifications are sent under
+    pub app_name: Option<String>,
+    /// Key these notifications are stacked under
+    pub group: Option<String>,
+}
+
+impl NotificationsConfig {
+    /// Overrides for notifications that are `critical` or not
+    fn class(&self, critical: bool) -> Option<&NotificationClass> {
+        critical.then_some(&self.low)
+    }
+}
+
+/// Overrides applied to a single device
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct DeviceRule {
+    /// Name used in notifications instead of the reported one
+    pub alias: Option<String>,
+    /// Low battery threshold for this device
+    pub battery_threshold: Option<u8>,
+    /// Remaining-time threshold for this device, in minutes
+    pub battery_threshold_minutes: Option<u32>,
+    /// Percentage bucket for this device
+    pub level_bucket: Option<u8>,
+    /// Never notify about this device
+    pub muted: bool,
+    /// Icon name or absolute path to an image used for every notification about this device
+    pub icon: Option<String>,
+    /// Application name notifications about this device are sent under
+    pub app_name: Option<String>,
+    /// Key notifications about this device are stacked under by the notification daemon
+    pub group: Option<String>,
+    /// Name or description of the device's sinks and sources, if they cannot be matched by name
+    pub audio_device: Option<String>,
+    /// Whether to make this device's sink the default output while it is connected
+    pub switch_default_output: Option<bool>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            polling_interval: 5000,
+            debug: true,
+            battery_threshold: 10,
+            battery_threshold_minutes: None,
+            abnormal_discharge_factor: 2.0,
+            usage_forecast: true,
+            notification_step: 5,
+            notification_mode: NotificationMode::default(),
+            level_bucket: 1,
+            notify_charging: true,
+            group_low_alerts: true,
+            hold_while_screen_sharing: false,
+            hold_during_calls: false,
+            event_log_len: 50,
+            sinks: vec![Sink::Desktop],
+            app_name: None,
+            notifications: NotificationsConfig::default(),
+            remote: None,
+            primary_device: None,
+            audio: AudioConfig::default(),
+            extras: ExtrasConfig::default(),
+            power_saving: PowerSavingConfig::default(),
+            shutdown_reminder: ShutdownReminderConfig::default(),
+            osd: OsdConfig::default(),
+            firmware: FirmwareConfig::default(),
+            icons: IconsConfig::default(),
+            csv: CsvConfig::default(),
+            summary: SummaryConfig::default(),
+            plugins: PluginsConfig::default(),
+            rules: Vec::new(),
+            history: HistoryConfig::default(),
+            devices: BTreeMap::new(),
+        }
+    }
+}
+
+impl Sink {
+    /// Every available sink
+    pub const ALL: [Sink; 3] = [Sink::Desktop, Sink::Stdout, Sink::Wall];
+}
+
+impl Config {
+    /// JSON Schema of the configuration file, for editor completion and external validation
+    #[cfg(feature = "json-schema")]
+    pub fn json_schema() -> schemars::Schema {
+        schemars::schema_for!(Config)
+    }
+
+    /// Default location of the configuration file
+    pub fn default_path() -> PathBuf {
+        Native.config_dir().join("config.toml")
+    }
+
+    /// Load the configuration from `path`, falling back to defaults if it does not exist
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(source) => Err(ConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Write the configuration to `path`, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let write_error = |source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?).map_err(write_error)
+    }
+
+    /// Rule for the named device, if one is configured
+    pub fn rule(&self, name: &str) -> Option<&DeviceRule> {
+        self.devices.get(name)
+    }
+
+    /// Low battery threshold for the named device
+    pub fn threshold_for(&self, name: &str) -> u8 {
+        self.rule(name)
+            .and_then(|r| r.battery_threshold)
+            .unwrap_or(self.battery_threshold)
+    }
+
+    /// Remaining-time threshold in minutes for the named device, if any
+    pub fn threshold_minutes_for(&self, name: &str) -> Option<u32> {
+        self.rule(name)
+            .and_then(|r| r.battery_threshold_minutes)
+            .or(self.battery_threshold_minutes)
+    }
+
+    /// Percentage bucket readings of the named device are rounded down to
+    pub fn level_bucket_for(&self, name: &str) -> u8 {
+        self.rule(name)
+            .and_then(|r| r.level_bucket)
+            .unwrap_or(self.level_bucket)
+    }
+
+    /// Whether the named device's sink becomes the default output while it is connected
+    pub fn switch_default_output_for(&self, name: &str) -> bool {
+        self.rule(name)
+            .and_then(|r| r.switch_default_output)
+            .unwrap_or(self.audio.switch_default_output)
+    }
+
+    /// Name to show in notifications for the named device
+    pub fn display_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.rule(name)
+            .and_then(|r| r.alias.as_deref())
+            .unwrap_or(name)
+    }
+
+    /// Icon for notifications about the named device, `icon` unless overridden
+    pub fn icon_for<'a>(&'a self, name: &str, icon: &'a str) -> &'a str {
+        self.rule(name)
+            .and_then(|r| r.icon.as_deref())
+            .unwrap_or(icon)
+    }
+
+    /// How notifications with `icon` about the named device are filed by the notification daemon
+    ///
+    /// `icon` is the one chosen for the event, before [`Config::icon_for`] replaces it, so that
+    /// device icons do not change which notifications are critical.
+    pub fn notification_options(&self, name: &str, icon: &str) -> NotificationOptions<'_> {
+        self.options(self.rule(name), icon)
+    }
+
+    /// How notifications with `icon` about several devices at once are filed
+    pub fn grouped_notification_options(&self, icon: &str) -> NotificationOptions<'_> {
+        self.options(None, icon)
+    }
+
+    /// Options of notifications with `icon`, the class overriding the device `rule`, which
+    /// overrides the global settings
+    fn options<'a>(&'a self, rule: Option<&'a DeviceRule>, icon: &str) -> NotificationOptions<'a> {
+        let critical = is_critical(icon);
+        let class = self.notifications.class(critical);
+        NotificationOptions {
+            app_name: class
+                .and_then(|c| c.app_name.as_deref())
+                .or(rule.and_then(|r| r.app_name.as_deref()))
+                .or(self.app_name.as_deref()),
+            group: class
+                .and_then(|c| c.group.as_deref())
+                .or(rule.and_then(|r| r.group.as_deref())),
+            icon_suffix: None,
+            critical,
+        }
+    }
+
+    /// Device name for a name or alias given by the user
+    pub fn resolve_device<'a>(&'a self, name_or_alias: &'a str) -> &'a str {
+        self.devices
+            .iter()
+            .find(|(_, rule)| rule.alias.as_deref() == Some(name_or_alias))
+            .map_or(name_or_alias, |(name, _)| name.as_str())
+    }
+
+    /// Whether notifications for the named device are muted
+    pub fn is_muted(&self, name: &str) -> bool {
+        self.rule(name).is_some_and(|r| r.muted)
+    }
+}
@@ -0,0 +1,219 @@
+//! Changing headset settings
+//!
+//! Every change goes through a [`Controller`] provided by a backend, and [`apply`] records what
+//! was applied per device in a [`SettingsStore`]. Command line tools, menu actions and automations
+//! all use this single path, so the store always reflects the last setting sent to a headset.
+//!
+//! [`PowerSaving`] is the built-in automation using it, shortening the auto-shutoff of headsets
+//! running low.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bus::Subscriber,
+    device::{DeviceEvent, Threshold},
+    error::{ConfigError, ControlError},
+    extras::CAPABILITY_INACTIVE_TIME,
+    monitor::Monitor,
+    platform::{Native, Platform},
+};
+
+/// Highest sidetone level
+pub const SIDETONE_MAX: u8 = 128;
+
+/// Longest inactive time in minutes
+pub const INACTIVE_TIME_MAX: u8 = 90;
+
+/// A setting that can be sent to a headset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    /// Lights switched on or off
+    Lights(bool),
+    /// How loud the microphone is played back, from 0 to [`SIDETONE_MAX`]
+    Sidetone(u8),
+    /// Minutes without audio before the headset turns itself off, 0 to never turn off
+    InactiveTime(u8),
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Setting::Lights(true) => write!(f, "lights on"),
+            Setting::Lights(false) => write!(f, "lights off"),
+            Setting::Sidetone(level) => write!(f, "sidetone {}", level),
+            Setting::InactiveTime(0) => write!(f, "inactive time off"),
+            Setting::InactiveTime(minutes) => write!(f, "inactive time {}m", minutes),
+        }
+    }
+}
+
+/// Something able to send settings to headsets, usually a backend
+pub trait Controller {
+    /// Send `setting` to the device named `device`
+    fn apply(&self, device: &str, setting: Setting) -> Result<(), ControlError>;
+}
+
+/// Settings last applied to a device, unset for those never changed through the notifier
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lights: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sidetone: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactive_time: Option<u8>,
+}
+
+impl AppliedSettings {
+    /// Remember that `setting` was applied
+    pub fn record(&mut self, setting: Setting) {
+        match setting {
+            Setting::Lights(on) => self.lights = Some(on),
+            Setting::Sidetone(level) => self.sidetone = Some(level),
+            Setting::InactiveTime(minutes) => self.inactive_time = Some(minutes),
+        }
+    }
+
+    /// Every recorded setting
+    pub fn settings(&self) -> Vec<Setting> {
+        let lights = self.lights.map(Setting::Lights);
+        let sidetone = self.sidetone.map(Setting::Sidetone);
+        let inactive_time = self.inactive_time.map(Setting::InactiveTime);
+        [lights, sidetone, inactive_time]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// Applied settings of every device, kept in a TOML file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SettingsStore {
+    devices: BTreeMap<String, AppliedSettings>,
+}
+
+impl SettingsStore {
+    /// Default location of the store
+    pub fn default_path() -> PathBuf {
+        Native.data_dir().join("settings.toml")
+    }
+
+    /// Load the store from `path`, empty if it does not exist
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SettingsStore::default()),
+            Err(source) => Err(ConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Write the store to `path`, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let write_error = |source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?).map_err(write_error)
+    }
+
+    /// Settings applied to `device`
+    pub fn get(&self, device: &str) -> AppliedSettings {
+        self.devices.get(device).copied().unwrap_or_default()
+    }
+
+    /// Remember that `setting` was applied to `device`
+    pub fn record(&mut self, device: &str, setting: Setting) {
+        self.devices
+            .entry(device.to_string())
+            .or_default()
+            .record(setting);
+    }
+}
+
+/// Send `setting` to `device` through `controller` and record it in the store at `path`
+///
+/// The store is read again before recording so changes made by other processes are kept; the
+/// updated store is returned.
+pub fn apply(
+    controller: &impl Controller,
+    path: &Path,
+    device: &str,
+    setting: Setting,
+) -> Result<SettingsStore, ControlError> {
+    controller.apply(device, setting)?;
+    let mut store = SettingsStore::load(path)?;
+    store.record(device, setting);
+    store.save(path)?;
+    Ok(store)
+}
+
+/// Subscriber shortening the inactive time of devices crossing the low battery threshold and
+/// restoring the configured one once they have charged, following
+/// [`PowerSavingConfig`](crate::config::PowerSavingConfig)
+///
+/// Only devices headsetcontrol lists with the inactive time capability are changed, so its
+/// [`Extras`](crate::extras::Extras) must be known. Whether a device's inactive time is shortened
+/// is read from [`Monitor::settings`], so a restart in between still restores it.
+pub struct PowerSaving<C> {
+    controller: C,
+    /// Settings store changes are recorded in
+    path: PathBuf,
+}
+
+impl<C: Controller> PowerSaving<C> {
+    /// Policy sending settings through `controller` and recording them in the store at `path`
+    pub fn new(controller: C, path: PathBuf) -> Self {
+        PowerSaving { controller, path }
+    }
+}
+
+impl<C: Controller + Send> Subscriber for PowerSaving<C> {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        let config = &monitor.config.power_saving;
+        if !config.enabled {
+            return;
+        }
+        let name = event.device();
+        let capable = monitor
+            .extras
+            .get(name)
+            .is_some_and(|e| e.capabilities.iter().any(|c| c == CAPABILITY_INACTIVE_TIME));
+        if !capable {
+            return;
+        }
+        let shortened = config.low_battery_inactive_time != config.inactive_time
+            && monitor.settings.get(name).inactive_time == Some(config.low_battery_inactive_time);
+        let setting = match event {
+            DeviceEvent::ThresholdCrossed {
+                threshold: Threshold::Low,
+                charging: false,
+                ..
+            } if !shortened => Setting::InactiveTime(config.low_battery_inactive_time),
+            DeviceEvent::ChargingStopped { .. } | DeviceEvent::Full { .. } if shortened => {
+                Setting::InactiveTime(config.inactive_time)
+            }
+            _ => return,
+        };
+        match apply(&self.controller, &self.path, name, setting) {
+            Ok(store) => monitor.settings = store,
+            Err(e) => eprintln!("cannot change the inactive time of {}: {}", name, e),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Device model and headsetcontrol output parsing
+
+use std::fmt::Display;
+
+/// Represents the current battery status of a device
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BatteryStatus {
+    Charging,
+    Discharging,
+    Disconnected,
+}
+
+impl BatteryStatus {
+    /// Stable lowercase name, used in exported data
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BatteryStatus::Charging => "charging",
+            BatteryStatus::Discharging => "discharging",
+            BatteryStatus::Disconnected => "disconnected",
+        }
+    }
+
+    /// Parse a name produced by [`BatteryStatus::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "charging" => Some(BatteryStatus::Charging),
+            "discharging" => Some(BatteryStatus::Discharging),
+            "disconnected" => Some(BatteryStatus::Disconnected),
+            _ => None,
+        }
+    }
+}
+
+/// Battery charge as reported by a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatteryReading {
+    /// Percentage from 0 to 100
+    Percent(u8),
+    /// `current` out of `max` discrete levels, for devices showing bars instead of percentages
+    Level { current: u8, max: u8 },
+}
+
+impl BatteryReading {
+    /// Coarse reading of `current` out of `max` levels, if `current` is within `1..=max` or 0
+    pub fn level(current: u8, max: u8) -> Option<Self> {
+        (max > 0 && current <= max).then_some(BatteryReading::Level { current, max })
+    }
+
+    /// Charge as a percentage, rounded to the nearest for coarse levels
+    pub fn percent(self) -> u8 {
+        match self {
+            BatteryReading::Percent(percent) => percent,
+            BatteryReading::Level { current, max } => {
+                ((current as u32 * 100 + max as u32 / 2) / max as u32) as u8
+            }
+        }
+    }
+
+    /// Whether the charge may be below `threshold` percent
+    ///
+    /// A coarse level covers the range down to the level below it, so it counts as low as soon
+    /// as that range reaches under the threshold.
+    pub fn is_below(self, threshold: u8) -> bool {
+        match self {
+            BatteryReading::Percent(percent) => percent < threshold,
+            BatteryReading::Level { current, max } => {
+                (current.saturating_sub(1) as u32 * 100) < threshold as u32 * max as u32
+            }
+        }
+    }
+
+    /// Percentage rounded down to a multiple of `bucket`, hiding small fluctuations
+    ///
+    /// A full battery stays at 100%, and coarse levels are left alone.
+    pub fn bucketed(self, bucket: u8) -> Self {
+        match self {
+            BatteryReading::Percent(percent) if bucket > 1 && percent < 100 => {
+                BatteryReading::Percent(percent - percent % bucket)
+            }
+            reading => reading,
+        }
+    }
+}
+
+impl Display for BatteryReading {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatteryReading::Percent(percent) => write!(f, "{}%", percent),
+            BatteryReading::Level { current, max } => write!(f, "{}/{}", current, max),
+        }
+    }
+}
+
+/// Represents a connected device
+#[derive(Clone, Debug)]
+pub struct Device {
+    /// Name of the device
+    pub name: String,
+    /// Current battery status
+    pub battery_status: BatteryStatus,
+    /// Current battery level (if available)
+    pub battery: Option<BatteryReading>,
+    /// Last battery level that triggered a notification
+    pub last_notif_battery_level: Option<u8>,
+}
+
+impl Device {
+    /// Current battery level as a percentage, if available
+    pub fn level(&self) -> Option<u8> {
+        self.battery.map(BatteryReading::percent)
+    }
+}
+
+impl Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Device: {} | Battery Status: {:?} | Battery: {:?} | Last Notif Battery Level: {:?}",
+            self.name,
+            self.battery_status,
+            self.level(),
+            self.last_notif_battery_level
+        )
+    }
+}
+
+/// A change in the state of a device between two readings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// The device appeared or reconnected
+    Connected {
+        name: String,
+        battery: Option<BatteryReading>,
+    },
+    /// The device stopped reporting a battery
+    Disconnected { name: String },
+    /// The device was plugged in
+    ChargingStarted { name: String },
+    /// The device was unplugged
+    ChargingStopped { name: String },
+    /// The reported battery level changed
+    LevelChanged {
+        name: String,
+        from: BatteryReading,
+        to: BatteryReading,
+    },
+    /// The battery level reached a threshold worth notifying about
+    ThresholdCrossed {
+        name: String,
+        battery: BatteryReading,
+        threshold: Threshold,
+        charging: bool,
+    },
+    /// A charging device reached 100% or its highest level
+    Full { name: String },
+}
+
+/// Kind of threshold reported by [`DeviceEvent::ThresholdCrossed`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Threshold {
+    /// Below the low battery percentage or remaining-time threshold
+    Low,
+    /// On a multiple of the notification step
+    Step,
+}
+
+impl DeviceEvent {
+    /// Name of the device the event concerns
+    pub fn device(&self) -> &str {
+        match self {
+            DeviceEvent::Connected { name, .. }
+            | DeviceEvent::Disconnected { name }
+            | DeviceEvent::ChargingStarted { name }
+            | DeviceEvent::ChargingStopped { name }
+            | DeviceEvent::LevelChanged { name, .. }
+            | DeviceEvent::ThresholdCrossed { name, .. }
+            | DeviceEvent::Full { name } => name,
+        }
+    }
+
+    /// Changes from `old`, the previous reading if any, to `new`
+    ///
+    /// Threshold crossings depend on the notification policy and are added by the monitor.
+    pub fn between(old: Option<&Device>, new: &Device) -> Vec<DeviceEvent> {
+        let name = new.name.clone();
+        let was_connected = old.is_some_and(|d| d.battery_status != BatteryStatus::Disconnected);
+        let is_connected = new.battery_status != BatteryStatus::Disconnected;
+
+        let mut events = Vec::new();
+        match (was_connected, is_connected) {
+            (false, true) => events.push(DeviceEvent::Connected {
+                name: name.clone(),
+                battery: new.battery,
+            }),
+            (true, false) => events.push(DeviceEvent::Disconnected { name: name.clone() }),
+            _ => {}
+        }
+        let Some(old) = old.filter(|_| was_connected && is_connected) else {
+            return events;
+        };
+
+        match (old.battery_status, new.battery_status) {
+            (BatteryStatus::Discharging, BatteryStatus::Charging) => {
+                events.push(DeviceEvent::ChargingStarted { name: name.clone() })
+            }
+            (BatteryStatus::Charging, BatteryStatus::Discharging) => {
+                events.push(DeviceEvent::ChargingStopped { name: name.clone() })
+            }
+            _ => {}
+        }
+        if let (Some(from), Some(to)) = (old.battery, new.battery) {
+            if from != to {
+                events.push(DeviceEvent::LevelChanged {
+                    name: name.clone(),
+                    from,
+                    to,
+                });
+            }
+            if new.battery_status == BatteryStatus::Charging
+                && to.percent() == 100
+                && from.percent() < 100
+            {
+                events.push(DeviceEvent::Full { name });
+            }
+        }
+        events
+    }
+}
+
+/// Parse every device found in a full headsetcontrol output
+pub fn parse_devices(hsc_output: &str) -> Vec<Device> {
+    hsc_output
+        .split("Found")
+        .filter(|l| !l.is_empty())
+        .filter_map(parse_device)
+        .collect()
+}
+
+/// Names of every device in `headsetcontrol -b` output, in order, including those without a
+/// battery reading that [`parse_devices`] leaves out
+pub fn parse_device_names(hsc_output: &str) -> Vec<String> {
+    hsc_output
+        .split("Found")
+        .filter_map(|block| {
+            block
+                .lines()
+                .find(|line| line.ends_with('!') && line.starts_with(' '))
+        })
+        .map(|line| device_name(line.trim().trim_end_matches('!')).to_string())
+        .collect()
+}
+
+/// Parse device information from a string
+pub fn parse_device(device_str: &str) -> Option<Device> {
+    let mut device = Device {
+        name: String::new(),
+        battery_status: BatteryStatus::Disconnected,
+        battery: None,
+        last_notif_battery_level: None,
+    };
+
+    for line in device_str.lines() {
+        if line.contains("Status: BATTERY_AVAILABLE") {
+            device.battery_status = BatteryStatus::Discharging;
+        } else if line.contains("Status: BATTERY_CHARGING") {
+            device.battery_status = BatteryStatus::Charging;
+        } else if line.ends_with("!") && line.starts_with(" ") {
+            device.name = line.trim().trim_end_matches('!').to_string();
+        } else if line.contains("Level: ") {
+            device.battery = line
+                .trim()
+                .replace("Level: ", "")
+                .replace('%', "")
+                .parse()
+                .ok()
+                .map(BatteryReading::Percent);
+        }
+    }
+
+    if device.name.is_empty()
+        || (device.battery_status == BatteryStatus::Disconnected && device.battery.is_none())
+    {
+        return None;
+    }
+
+    device.name = device_name(&device.name).to_string();
+    Some(device)
+}
+
+/// Name of a device as reported by headsetcontrol, without the edition or revision in brackets
+pub fn device_name(reported: &str) -> &str {
+    reported.split('(').next().unwrap_or("").trim()
+}
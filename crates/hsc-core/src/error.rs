@@ -0,0 +1,57 @@
+//! Errors returned by the core library
+
+use std::{error::Error, io, path::PathBuf};
+
+use thiserror::Error;
+
+/// Failure to load or save the configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid configuration in {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("cannot serialize the configuration: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("cannot write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+}
+
+/// Failure to deliver a notification
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    /// The notification program could not be started
+    #[error("cannot run {program}: {source}")]
+    Spawn { program: String, source: io::Error },
+    /// The notification program reported a failure
+    #[error("{program} {}", exit_description(*code))]
+    Exit { program: String, code: Option<i32> },
+    /// A [`NotificationSink`](crate::notify::NotificationSink) failed
+    #[error("notification sink failed: {0}")]
+    Sink(#[source] Box<dyn Error + Send + Sync>),
+}
+
+/// Failure to change a headset setting
+#[derive(Debug, Error)]
+pub enum ControlError {
+    /// The [`Controller`](crate::control::Controller) failed to send the setting
+    #[error("cannot apply setting: {0}")]
+    Backend(#[source] Box<dyn Error + Send + Sync>),
+    /// The backend does not report the device the setting is for
+    #[error("cannot apply setting: {0} is not connected")]
+    NotConnected(String),
+    /// The setting was sent but could not be recorded
+    #[error(transparent)]
+    Store(#[from] ConfigError),
+}
+
+/// How a program ended, given its exit code if it has one
+pub fn exit_description(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("exited with code {}", code),
+        None => "was killed by a signal".to_string(),
+    }
+}
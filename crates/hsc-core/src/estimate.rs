@@ -0,0 +1,120 @@
+//! Charge and discharge rate estimation
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use crate::device::BatteryStatus;
+
+/// How far back level changes are considered
+const WINDOW: Duration = Duration::from_secs(2 * 60 * 60);
+/// Minimum time between the first and last level change for a usable rate
+const MIN_SPAN: Duration = Duration::from_secs(60);
+
+/// Estimated time until a device is empty or full
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    /// Battery percentage gained or lost per hour
+    pub rate_per_hour: f64,
+    /// Time until empty when discharging, or until full when charging
+    pub remaining: Duration,
+    /// Whether the device is charging
+    pub charging: bool,
+}
+
+impl Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.charging {
+            write!(f, "≈{} to full", format_duration(self.remaining))
+        } else {
+            write!(f, "≈{} remaining", format_duration(self.remaining))
+        }
+    }
+}
+
+/// Tracks when the battery level of one device changed to derive a rolling rate
+#[derive(Default)]
+pub struct RateEstimator {
+    /// Status the samples were taken in
+    status: Option<BatteryStatus>,
+    /// Moments the level first reached each value, oldest first
+    samples: VecDeque<(Instant, u8)>,
+}
+
+impl RateEstimator {
+    /// Add a reading; samples from a different charging state are discarded
+    pub fn push(&mut self, now: Instant, level: Option<u8>, status: BatteryStatus) {
+        if self.status != Some(status) || status == BatteryStatus::Disconnected {
+            self.samples.clear();
+            self.status = Some(status);
+        }
+        let Some(level) = level else {
+            return;
+        };
+        if self.samples.back().is_some_and(|&(_, last)| last == level) {
+            return;
+        }
+
+        self.samples.push_back((now, level));
+        while self.samples.len() > 2
+            && self
+                .samples
+                .front()
+                .is_some_and(|&(t, _)| now.duration_since(t) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Time between the first and last level change considered
+    pub fn span(&self) -> Duration {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(first, _)), Some(&(last, _))) => last.duration_since(first),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Battery percentage gained or lost per hour, if enough changes were seen
+    pub fn rate_per_hour(&self) -> Option<f64> {
+        let (&(_, first_level), &(_, last_level)) = (self.samples.front()?, self.samples.back()?);
+        let span = self.span();
+        if span < MIN_SPAN {
+            return None;
+        }
+        let change = (last_level as f64 - first_level as f64).abs();
+        Some(change / (span.as_secs_f64() / 3600.0))
+    }
+
+    /// Time until empty or full at the current rate
+    pub fn estimate(&self) -> Option<Estimate> {
+        let charging = match self.status? {
+            BatteryStatus::Charging => true,
+            BatteryStatus::Discharging => false,
+            BatteryStatus::Disconnected => return None,
+        };
+        let &(_, level) = self.samples.back()?;
+        let rate = self.rate_per_hour().filter(|r| *r > 0.0)?;
+        let left = if charging {
+            100u8.saturating_sub(level)
+        } else {
+            level
+        };
+        Some(Estimate {
+            rate_per_hour: rate,
+            remaining: Duration::from_secs_f64(left as f64 / rate * 3600.0),
+            charging,
+        })
+    }
+}
+
+/// Format a duration as hours and minutes, e.g. `2h 10m` or `45m`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
@@ -0,0 +1,46 @@
+//! Readings besides the battery level
+
+use std::fmt::Display;
+
+/// Highest chatmix value, meaning chat audio only
+pub const CHATMIX_MAX: u8 = 128;
+
+/// Capability of headsets turning themselves off after a time without audio
+pub const CAPABILITY_INACTIVE_TIME: &str = "inactive time";
+
+/// Values a headset reports besides its battery
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Extras {
+    /// Balance between game and chat audio
+    pub chatmix: Option<Chatmix>,
+    /// Features the headset supports, as named by headsetcontrol (`sidetone`, `lights`, ...)
+    pub capabilities: Vec<String>,
+}
+
+/// Position of the chatmix dial, from 0 (game audio only) to [`CHATMIX_MAX`] (chat audio only)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chatmix(u8);
+
+impl Chatmix {
+    /// Dial position `value`, if it is within range
+    pub fn new(value: u8) -> Option<Self> {
+        (value <= CHATMIX_MAX).then_some(Chatmix(value))
+    }
+
+    /// Raw dial position as reported by headsetcontrol
+    pub fn value(self) -> u8 {
+        self.0
+    }
+
+    /// Share of chat audio in percent
+    pub fn chat_percent(self) -> u8 {
+        ((self.0 as u32 * 100 + CHATMIX_MAX as u32 / 2) / CHATMIX_MAX as u32) as u8
+    }
+}
+
+impl Display for Chatmix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let chat = self.chat_percent();
+        write!(f, "game {}% / chat {}%", 100 - chat, chat)
+    }
+}
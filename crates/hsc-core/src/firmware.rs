@@ -0,0 +1,36 @@
+//! Firmware versions
+//!
+//! Versions are compared as dot-separated numbers, so `1.10` is newer than `1.9`. Parts that are
+//! not numbers, like a `b` suffix in `2.0b`, are compared as text.
+
+use std::cmp::Ordering;
+
+/// Whether `latest` is a newer version than `installed`
+pub fn is_newer(latest: &str, installed: &str) -> bool {
+    compare(latest.trim(), installed.trim()) == Ordering::Greater
+}
+
+/// Order of two versions
+fn compare(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.trim_start_matches(['v', 'V']).split('.');
+    let mut b_parts = b.trim_start_matches(['v', 'V']).split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(a), None) => compare_part(a, "0"),
+            (None, Some(b)) => compare_part("0", b),
+            (Some(a), Some(b)) => compare_part(a, b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Order of two parts of a version, numerically if both are numbers
+fn compare_part(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
@@ -0,0 +1,25 @@
+//! Typical battery use for the rest of a day
+
+use std::fmt::Display;
+
+use chrono::NaiveTime;
+
+/// Typical battery use for the rest of the day, from the same time of day in the past
+#[derive(Clone, Copy, Debug)]
+pub struct UsageForecast {
+    /// Battery percentage usually used from now until the end of the day
+    pub expected_drop: f64,
+    /// Time of day use usually ends
+    pub until: NaiveTime,
+}
+
+impl Display for UsageForecast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "you usually use {:.0}% by {}",
+            self.expected_drop,
+            self.until.format("%H:%M")
+        )
+    }
+}
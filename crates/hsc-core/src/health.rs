@@ -0,0 +1,36 @@
+//! Long-term battery health figures
+
+use std::fmt::Display;
+
+/// Long-term battery figures for one device
+#[derive(Clone, Copy, Debug)]
+pub struct BatteryHealth {
+    /// Full-equivalent charge cycles
+    pub cycles: f64,
+    /// Hours of use per full charge in the first month of history
+    pub reference_runtime: Option<f64>,
+    /// Hours of use per full charge over the last two weeks
+    pub recent_runtime: Option<f64>,
+}
+
+impl BatteryHealth {
+    /// Recent runtime as a percentage of the reference runtime, capped at 100
+    pub fn percent(&self) -> Option<f64> {
+        match (self.reference_runtime, self.recent_runtime) {
+            (Some(reference), Some(recent)) if reference > 0.0 => {
+                Some((recent / reference * 100.0).min(100.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for BatteryHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} cycles", self.cycles)?;
+        if let Some(percent) = self.percent() {
+            write!(f, ", health ≈{:.0}%", percent)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+//! Core of the headset battery notifier
+//!
+//! Turns battery readings reported by [headsetcontrol](https://github.com/Sapd/HeadsetControl)
+//! into per-device state and decides when to notify about battery levels and connection status.
+//!
+//! [`Monitor`] parses raw `headsetcontrol -b` output, tracks every device and publishes
+//! [`DeviceEvent`]s describing what changed on an [`EventBus`]. The built-in
+//! [`Notifier`](notify::Notifier) subscriber raises notifications for thresholds chosen by the
+//! configured [`Policy`]. Running `headsetcontrol` and other external tools is left to the `hsc-backends`
+//! crate, so this crate only depends on small, pure-Rust libraries and can be embedded by bars,
+//! widgets and other programs.
+//!
+//! ```no_run
+//! use hsc_core::{config::Config, Monitor};
+//!
+//! # let output = String::new();
+//! let mut monitor = Monitor::new(Config::default());
+//! let update = monitor.update(&output);
+//! for event in update.events {
+//!     println!("{:?}", event);
+//! }
+//! ```
+
+pub mod audio;
+pub mod bus;
+pub mod clock;
+pub mod command;
+pub mod config;
+pub mod control;
+pub mod device;
+pub mod error;
+pub mod estimate;
+pub mod extras;
+pub mod firmware;
+pub mod forecast;
+pub mod health;
+pub mod monitor;
+pub mod notify;
+pub mod platform;
+pub mod policy;
+pub mod schema;
+pub mod session;
+pub mod snapshot;
+
+pub use bus::{EventBus, Subscriber};
+pub use device::{BatteryStatus, Device, DeviceEvent};
+pub use monitor::{Monitor, Update};
+pub use policy::Policy;
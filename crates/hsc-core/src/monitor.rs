@@ -0,0 +1,732 @@
+//! Device state tracking and notifications
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    audio::{node_matches, AudioNode, NodeKind},
+    bus::{EventBus, Subscriber},
+    clock::{Clock, SystemClock},
+    config::{Config, Sink},
+    control::SettingsStore,
+    device::{parse_devices, BatteryReading, BatteryStatus, Device, DeviceEvent, Threshold},
+    estimate::{Estimate, RateEstimator},
+    extras::{Extras, CAPABILITY_INACTIVE_TIME},
+    firmware,
+    forecast::UsageForecast,
+    health::BatteryHealth,
+    notify::{dispatch, is_critical, NotificationSink, Notifier},
+    platform::{ColorScheme, NotificationOptions},
+    policy::Policy,
+    session::ChargeSession,
+};
+
+/// Number of battery readings kept per device
+const HISTORY_LEN: usize = 120;
+/// How long a fast discharge must be observed before it is reported
+const ABNORMAL_DRAIN_MIN_SPAN: Duration = Duration::from_secs(20 * 60);
+/// Charge cycles between informational battery health notifications
+const HEALTH_CYCLE_MILESTONE: f64 = 50.0;
+/// Shortest inactive time headsets accept, assumed for those turning themselves off after an
+/// unknown time
+pub const MIN_INACTIVE_TIME: Duration = Duration::from_secs(60);
+
+/// Result of processing one headsetcontrol output
+pub struct Update {
+    /// Devices contained in the output
+    pub readings: Vec<Device>,
+    /// Changes detected since the previous output
+    pub events: Vec<DeviceEvent>,
+}
+
+/// A notification raised by the monitor
+#[derive(Clone)]
+pub struct Event {
+    /// Sequence number, increasing with every event
+    pub id: u64,
+    /// When the notification was raised
+    pub time: DateTime<Local>,
+    /// Name of the device it concerns
+    pub device: String,
+    /// Notification body
+    pub message: String,
+    /// Whether the notification was held back by a pause or snooze
+    pub suppressed: bool,
+}
+
+/// A low battery alert held back until the end of a poll
+struct LowAlert {
+    /// Name of the device running low
+    name: String,
+    battery: BatteryReading,
+    /// Notification body if it is sent on its own
+    message: String,
+}
+
+/// A notification held back while the screen is shared or a call is ongoing
+struct HeldNotification {
+    /// Name of the device it is about
+    name: String,
+    content: String,
+    icon: String,
+}
+
+/// Tracks connected devices and decides when to notify about them
+pub struct Monitor {
+    /// Active configuration
+    pub config: Config,
+    /// Last known state of every device, keyed by name
+    pub devices: HashMap<String, Device>,
+    /// Recent battery readings per device, oldest first
+    pub history: HashMap<String, VecDeque<u8>>,
+    /// Charge and discharge rate trackers per device
+    pub estimators: HashMap<String, RateEstimator>,
+    /// Typical discharge rate per device in %/h, from the history store
+    pub baselines: HashMap<String, f64>,
+    /// Typical remaining use today per device, from the history store
+    pub forecasts: HashMap<String, UsageForecast>,
+    /// Devices whose current discharge was already compared with their forecast
+    pub(crate) forecast_checked: HashSet<String>,
+    /// Usage since the last full charge per device
+    pub sessions: HashMap<String, ChargeSession>,
+    /// Charge cycles and estimated battery health per device, from the history store
+    pub health: HashMap<String, BatteryHealth>,
+    /// Readings besides the battery per device, when polled
+    pub extras: HashMap<String, Extras>,
+    /// Firmware version per device, for devices whose provider reports it
+    pub firmware: HashMap<String, String>,
+    /// Latest firmware version already announced per device
+    firmware_notified: HashMap<String, String>,
+    /// Devices already warned about draining abnormally fast in their current discharge
+    abnormal_drain_notified: HashSet<String>,
+    /// Recent notifications, oldest first
+    pub events: VecDeque<Event>,
+    /// Id given to the next event
+    next_event_id: u64,
+    /// Low battery alerts raised during the current update, sent together at its end
+    low_alerts: Vec<LowAlert>,
+    /// The screen is being shared, so routine notifications are held back
+    screen_sharing: bool,
+    /// The microphone of a connected headset is in use, so routine notifications are held back
+    in_call: bool,
+    /// Latest notification of each device held back while the screen is shared or a call is
+    /// ongoing, oldest first
+    held: Vec<HeldNotification>,
+    /// Notifications are suppressed until resumed
+    pub paused: bool,
+    /// Notifications are suppressed until this instant
+    pub snoozed_until: Option<Instant>,
+    /// Settings last applied to each device
+    pub settings: SettingsStore,
+    /// Default sink and source of the sound server, if known
+    pub active_audio: Option<Vec<AudioNode>>,
+    /// Color scheme of the desktop icons are chosen for, the configured one until detected
+    pub color_scheme: Option<ColorScheme>,
+    /// When each connected device was last seen in use
+    last_used: HashMap<String, Instant>,
+    /// Consumers of the events produced by [`Monitor::update`]
+    bus: EventBus,
+    /// Source of time
+    clock: Arc<dyn Clock>,
+    /// Destinations for notifications besides the configured sinks
+    extra_sinks: Vec<Box<dyn NotificationSink>>,
+}
+
+impl Monitor {
+    /// Monitor applying `config`, with the [`Notifier`] subscribed
+    pub fn new(config: Config) -> Self {
+        Monitor::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Monitor applying `config` and reading time from `clock`
+    pub fn with_clock(config: Config, clock: Arc<dyn Clock>) -> Self {
+        let mut bus = EventBus::default();
+        bus.subscribe(Notifier);
+        Monitor {
+            color_scheme: config.icons.scheme,
+            config,
+            devices: HashMap::new(),
+            history: HashMap::new(),
+            estimators: HashMap::new(),
+            baselines: HashMap::new(),
+            forecasts: HashMap::new(),
+            forecast_checked: HashSet::new(),
+            sessions: HashMap::new(),
+            health: HashMap::new(),
+            extras: HashMap::new(),
+            firmware: HashMap::new(),
+            firmware_notified: HashMap::new(),
+            abnormal_drain_notified: HashSet::new(),
+            events: VecDeque::new(),
+            next_event_id: 0,
+            low_alerts: Vec::new(),
+            screen_sharing: false,
+            in_call: false,
+            held: Vec::new(),
+            paused: false,
+            snoozed_until: None,
+            settings: SettingsStore::default(),
+            active_audio: None,
+            last_used: HashMap::new(),
+            bus,
+            clock,
+            extra_sinks: Vec::new(),
+        }
+    }
+
+    /// Source of time used by the monitor
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Update device status from headsetcontrol output, returning the devices it contained and
+    /// what changed about them
+    pub fn update(&mut self, hsc_output: &str) -> Update {
+        self.update_readings(parse_devices(hsc_output))
+    }
+
+    /// Update device status from readings gathered from any source, returning them along with
+    /// what changed
+    ///
+    /// Estimates use the exact levels, while the readings returned, the device state and the
+    /// notifications use levels rounded to the configured bucket.
+    pub fn update_readings(&mut self, mut readings: Vec<Device>) -> Update {
+        let mut events = Vec::new();
+        for device in readings.iter_mut() {
+            self.record_reading(device);
+            let bucket = self.config.level_bucket_for(&device.name);
+            device.battery = device.battery.map(|b| b.bucketed(bucket));
+            let old = self.devices.get(&device.name).cloned();
+            let mut changes = DeviceEvent::between(old.as_ref(), device);
+            if let Some(old) = &old {
+                device.last_notif_battery_level = old.last_notif_battery_level;
+                changes.extend(self.threshold_crossed(old, device));
+            }
+            self.devices.insert(device.name.clone(), device.clone());
+            self.track_usage(device, &changes);
+
+            self.publish(&changes);
+            if old.is_some() {
+                self.check_discharge_rate(device);
+                self.check_forecast(device);
+            }
+            events.extend(changes);
+        }
+        self.flush_low_alerts();
+        Update { readings, events }
+    }
+
+    /// Add a subscriber receiving every event after the existing ones
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.bus.subscribe(subscriber);
+    }
+
+    /// Deliver notifications to `sink` as well as the configured sinks
+    pub fn add_sink(&mut self, sink: impl NotificationSink + 'static) {
+        self.extra_sinks.push(Box::new(sink));
+    }
+
+    /// Whether notifications are delivered anywhere, rather than only kept in the recent events
+    pub fn has_sinks(&self) -> bool {
+        !self.config.sinks.is_empty() || !self.extra_sinks.is_empty()
+    }
+
+    /// Hand events to every subscriber
+    fn publish(&mut self, events: &[DeviceEvent]) {
+        let mut bus = std::mem::take(&mut self.bus);
+        for event in events {
+            bus.publish(self, event);
+        }
+        // Keep subscribers added while publishing
+        bus.append(&mut self.bus);
+        self.bus = bus;
+    }
+
+    /// Notification policy for the current configuration
+    pub fn policy(&self) -> Policy<'_> {
+        Policy::new(&self.config)
+    }
+
+    /// Events raised after the event with id `last_seen`, or all retained events if `None`
+    pub fn events_since(&self, last_seen: Option<u64>) -> impl Iterator<Item = &Event> {
+        self.events
+            .iter()
+            .filter(move |e| last_seen.is_none_or(|id| e.id > id))
+    }
+
+    /// Toggle the paused state
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Suppress notifications for the given duration, or clear the snooze if `None`
+    pub fn snooze(&mut self, duration: Option<Duration>) {
+        self.snoozed_until = duration.map(|d| self.clock.now() + d);
+    }
+
+    /// Remaining snooze time, if a snooze is active
+    pub fn snooze_remaining(&self) -> Option<Duration> {
+        self.snoozed_until
+            .and_then(|until| until.checked_duration_since(self.clock.now()))
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Whether notifications are currently being suppressed
+    pub fn is_muted(&self) -> bool {
+        self.paused || self.snooze_remaining().is_some()
+    }
+
+    /// Record whether the screen is being shared, sending the notifications held back meanwhile
+    /// once it no longer is
+    pub fn set_screen_sharing(&mut self, sharing: bool) {
+        self.screen_sharing = sharing;
+        self.release_held();
+    }
+
+    /// Whether the screen is being shared and notifications may be held back
+    pub fn is_screen_sharing(&self) -> bool {
+        self.screen_sharing
+    }
+
+    /// Record which audio sources are recording, telling whether the microphone of a connected
+    /// headset is in use, and send the notifications held back during the call once it ends
+    pub fn set_recording(&mut self, sources: &[AudioNode]) {
+        self.in_call = self
+            .devices
+            .values()
+            .filter(|d| d.battery_status != BatteryStatus::Disconnected)
+            .any(|d| {
+                sources.iter().any(|node| {
+                    node.kind == NodeKind::Source
+                        && node.running
+                        && node_matches(&self.config, node, &d.name)
+                })
+            });
+        self.release_held();
+    }
+
+    /// Whether the microphone of a connected headset is in use and notifications may be held
+    /// back
+    pub fn is_in_call(&self) -> bool {
+        self.in_call
+    }
+
+    /// Whether routine notifications are currently held back
+    fn is_holding(&self) -> bool {
+        (self.screen_sharing && self.config.hold_while_screen_sharing)
+            || (self.in_call && self.config.hold_during_calls)
+    }
+
+    /// Send the notifications held back once nothing holds them anymore
+    fn release_held(&mut self) {
+        if !self.is_holding() {
+            for held in std::mem::take(&mut self.held) {
+                self.notify(&held.name, &held.content, &held.icon);
+            }
+        }
+    }
+
+    /// Estimated time until the named device is empty or full
+    pub fn estimate(&self, name: &str) -> Option<Estimate> {
+        self.estimators.get(name).and_then(RateEstimator::estimate)
+    }
+
+    /// Append a battery reading to the device history
+    fn record_reading(&mut self, device: &Device) {
+        let now = self.clock.now();
+        self.estimators
+            .entry(device.name.clone())
+            .or_default()
+            .push(now, device.level(), device.battery_status);
+        self.sessions.entry(device.name.clone()).or_default().push(
+            now,
+            self.clock.local(),
+            device.level(),
+            device.battery_status,
+        );
+
+        let Some(battery) = device.level() else {
+            return;
+        };
+        let history = self.history.entry(device.name.clone()).or_default();
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(battery);
+    }
+
+    /// The threshold reached by a change of battery level, if any
+    fn threshold_crossed(&self, old: &Device, new: &Device) -> Option<DeviceEvent> {
+        // A reconnecting device is announced with its level instead
+        if old.battery_status == BatteryStatus::Disconnected {
+            return None;
+        }
+        let (Some(from), Some(to)) = (old.battery, new.battery) else {
+            return None;
+        };
+        let (from_percent, to_percent) = (from.percent(), to.percent());
+        let charging = match new.battery_status {
+            BatteryStatus::Discharging if to_percent < from_percent => false,
+            // Reaching 100% is reported as `Full`
+            BatteryStatus::Charging if to_percent > from_percent && to_percent < 100 => true,
+            _ => return None,
+        };
+        let threshold = if !charging && self.is_low(&new.name, to) {
+            Threshold::Low
+        } else if self.is_step(to) && self.is_in_use(&new.name) {
+            Threshold::Step
+        } else {
+            return None;
+        };
+        Some(DeviceEvent::ThresholdCrossed {
+            name: new.name.clone(),
+            battery: to,
+            threshold,
+            charging,
+        })
+    }
+
+    /// Update the readings besides the battery of a device, mentioning chatmix changes if
+    /// configured to
+    ///
+    /// Moving the chatmix dial counts as using the device.
+    pub fn set_extras(&mut self, name: &str, extras: Extras) {
+        let previous = self
+            .extras
+            .insert(name.to_string(), extras.clone())
+            .and_then(|e| e.chatmix);
+        if let (Some(previous), Some(chatmix)) = (previous, extras.chatmix) {
+            if chatmix != previous {
+                self.last_used.insert(name.to_string(), self.clock.now());
+            }
+            if self.config.extras.notify_chatmix && chatmix != previous {
+                self.notify(name, &format!("Chatmix: {}", chatmix), "dialog-information");
+            }
+        }
+    }
+
+    /// Record the firmware version a device reports, announcing once that an update is
+    /// available if [`FirmwareConfig`](crate::config::FirmwareConfig) lists a newer one
+    pub fn set_firmware(&mut self, name: &str, version: &str) {
+        self.firmware.insert(name.to_string(), version.to_string());
+        let config = &self.config.firmware;
+        let Some(latest) = config.latest.get(name).filter(|_| config.enabled) else {
+            return;
+        };
+        if !firmware::is_newer(latest, version) || self.firmware_notified.get(name) == Some(latest)
+        {
+            return;
+        }
+        let latest = latest.clone();
+        self.notify(
+            name,
+            &format!(
+                "Firmware update available: {} (installed {})",
+                latest, version
+            ),
+            "software-update-available",
+        );
+        self.firmware_notified.insert(name.to_string(), latest);
+    }
+
+    /// Update the battery health of a device, mentioning it whenever another
+    /// [`HEALTH_CYCLE_MILESTONE`] charge cycles have been completed
+    pub fn set_health(&mut self, name: &str, health: BatteryHealth) {
+        let milestone = |h: &BatteryHealth| (h.cycles / HEALTH_CYCLE_MILESTONE).floor() as u64;
+        let crossed = self
+            .health
+            .insert(name.to_string(), health)
+            .is_some_and(|previous| milestone(&health) > milestone(&previous));
+        if crossed {
+            let mut message = format!(
+                "Battery has been through {:.0} charge cycles",
+                health.cycles
+            );
+            if let Some(percent) = health.percent() {
+                message.push_str(&format!(", estimated health {:.0}%", percent));
+            }
+            self.notify(name, &message, "battery");
+        }
+    }
+
+    /// Warn once per discharge when a device drains much faster than its baseline
+    fn check_discharge_rate(&mut self, device: &Device) {
+        if device.battery_status != BatteryStatus::Discharging {
+            self.abnormal_drain_notified.remove(&device.name);
+            return;
+        }
+        let factor = self.config.abnormal_discharge_factor;
+        let (Some(&baseline), Some(estimator)) = (
+            self.baselines.get(&device.name),
+            self.estimators.get(&device.name),
+        ) else {
+            return;
+        };
+        let Some(rate) = estimator.rate_per_hour() else {
+            return;
+        };
+        if factor <= 0.0
+            || estimator.span() < ABNORMAL_DRAIN_MIN_SPAN
+            || rate <= baseline * factor
+            || self.abnormal_drain_notified.contains(&device.name)
+        {
+            return;
+        }
+
+        self.abnormal_drain_notified.insert(device.name.clone());
+        self.notify(
+            &device.name,
+            &format!(
+                "Draining faster than usual: {:.1}%/h (typically {:.1}%/h)",
+                rate, baseline
+            ),
+            "battery-caution",
+        );
+    }
+
+    /// Warn once per discharge when the charge left likely won't last the usual day of use
+    fn check_forecast(&mut self, device: &Device) {
+        if device.battery_status != BatteryStatus::Discharging {
+            self.forecast_checked.remove(&device.name);
+            return;
+        }
+        let (Some(battery), Some(forecast)) =
+            (device.level(), self.forecasts.get(&device.name).copied())
+        else {
+            return;
+        };
+        if !self.config.usage_forecast || !self.forecast_checked.insert(device.name.clone()) {
+            return;
+        }
+        if forecast.expected_drop >= battery as f64 {
+            self.notify(
+                &device.name,
+                &format!("{}% now, {}", battery, forecast),
+                "battery-caution",
+            );
+        }
+    }
+
+    /// Whether a discharging device is below its percentage or remaining-time threshold
+    fn is_low(&self, name: &str, battery: BatteryReading) -> bool {
+        self.policy()
+            .is_low(name, battery, self.estimate(name).as_ref())
+    }
+
+    /// Whether the battery level falls on a notification step
+    fn is_step(&self, battery: BatteryReading) -> bool {
+        self.policy().is_step(battery)
+    }
+
+    /// Note that a device is in use if it just connected, was plugged in or out, or audio is
+    /// playing through it
+    fn track_usage(&mut self, device: &Device, changes: &[DeviceEvent]) {
+        let handled = changes.iter().any(|event| {
+            matches!(
+                event,
+                DeviceEvent::Connected { .. }
+                    | DeviceEvent::ChargingStarted { .. }
+                    | DeviceEvent::ChargingStopped { .. }
+            )
+        });
+        let playing = self.active_audio.as_ref().is_some_and(|nodes| {
+            nodes
+                .iter()
+                .any(|node| node.running && node_matches(&self.config, node, &device.name))
+        });
+        if handled || playing {
+            self.last_used.insert(device.name.clone(), self.clock.now());
+        }
+    }
+
+    /// Whether a device that just disconnected most likely turned itself off after a while
+    /// without audio rather than losing its connection
+    ///
+    /// This needs the sound server state, and the device must be known to turn itself off: an
+    /// inactive time was applied to it, or headsetcontrol lists the capability, in which case it
+    /// must have been idle for at least [`MIN_INACTIVE_TIME`].
+    pub fn turned_off_idle(&self, name: &str) -> bool {
+        let capable = self
+            .extras
+            .get(name)
+            .is_some_and(|e| e.capabilities.iter().any(|c| c == CAPABILITY_INACTIVE_TIME));
+        let inactive_time = match self.settings.get(name).inactive_time {
+            Some(0) => return false,
+            Some(minutes) => Duration::from_secs(minutes as u64 * 60),
+            None if capable => MIN_INACTIVE_TIME,
+            None => return false,
+        };
+        if self.active_audio.is_none() {
+            return false;
+        }
+        // Usage is only sampled once per poll
+        let slack = Duration::from_millis(self.config.polling_interval);
+        self.last_used.get(name).is_some_and(|used| {
+            self.clock.now().saturating_duration_since(*used) + slack >= inactive_time
+        })
+    }
+
+    /// Remind to charge every discharging device below the configured
+    /// [`ShutdownReminderConfig::below`](crate::config::ShutdownReminderConfig::below) level,
+    /// returning the names of the devices mentioned
+    ///
+    /// Meant to run once just before the computer shuts down, so it is sent regardless of the
+    /// notification mode and step.
+    pub fn remind_to_charge(&mut self) -> Vec<String> {
+        let below = self.config.shutdown_reminder.below;
+        let mut due: Vec<(String, BatteryReading)> = self
+            .devices
+            .values()
+            .filter(|d| d.battery_status == BatteryStatus::Discharging)
+            .filter_map(|d| d.battery.map(|battery| (d.name.clone(), battery)))
+            .filter(|(_, battery)| battery.is_below(below))
+            .collect();
+        due.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, battery) in &due {
+            let content = format!("Charge your headset overnight, battery at {}", battery);
+            self.notify(name, &content, "battery-low");
+        }
+        due.into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Whether routine notifications should be sent for the named device
+    fn is_in_use(&self, name: &str) -> bool {
+        self.policy().is_in_use(name, self.active_audio.as_deref())
+    }
+
+    /// Record a notification and send it unless notifications are muted
+    ///
+    /// With [`Config::hold_while_screen_sharing`] or [`Config::hold_during_calls`] set,
+    /// notifications other than low battery alerts are held back while the screen is shared or
+    /// a call is ongoing, keeping only the latest of each device.
+    pub fn notify(&mut self, name: &str, content: &str, icon: &str) {
+        let suppressed = self.is_muted() || self.config.is_muted(name);
+        if !suppressed && self.is_holding() && !is_critical(icon) {
+            self.held.retain(|h| h.name != name);
+            self.held.push(HeldNotification {
+                name: name.to_string(),
+                content: content.to_string(),
+                icon: icon.to_string(),
+            });
+            return;
+        }
+        if !suppressed {
+            let display_name = self.config.display_name(name);
+            let options = NotificationOptions {
+                icon_suffix: self.config.icons.suffix(self.color_scheme),
+                ..self.config.notification_options(name, icon)
+            };
+            let icon = self.config.icon_for(name, icon);
+            deliver(
+                &self.config.sinks,
+                &mut self.extra_sinks,
+                name,
+                display_name,
+                content,
+                icon,
+                &options,
+            );
+        }
+        self.record_event(name, content, suppressed);
+    }
+
+    /// Announce that a device is running low, with `message` if it is the only one
+    ///
+    /// With [`Config::group_low_alerts`] set, alerts raised while updating are held back until
+    /// the update is done and sent as one notification if several devices ran low.
+    pub fn alert_low(&mut self, name: &str, battery: BatteryReading, message: &str) {
+        if !self.config.group_low_alerts {
+            self.notify(name, message, "battery-low");
+            return;
+        }
+        self.low_alerts.push(LowAlert {
+            name: name.to_string(),
+            battery,
+            message: message.to_string(),
+        });
+    }
+
+    /// Send the low battery alerts held back during an update, listing devices that are not
+    /// muted in a single notification if there are several
+    fn flush_low_alerts(&mut self) {
+        let alerts = std::mem::take(&mut self.low_alerts);
+        let (grouped, muted): (Vec<_>, Vec<_>) = alerts
+            .into_iter()
+            .partition(|alert| !self.config.is_muted(&alert.name));
+        if grouped.len() < 2 {
+            for alert in grouped.into_iter().chain(muted) {
+                self.notify(&alert.name, &alert.message, "battery-low");
+            }
+            return;
+        }
+        for alert in muted {
+            self.notify(&alert.name, &alert.message, "battery-low");
+        }
+
+        let devices: Vec<String> = grouped
+            .iter()
+            .map(|a| format!("{} ({})", self.config.display_name(&a.name), a.battery))
+            .collect();
+        let content = format!("{} devices low: {}", grouped.len(), devices.join(", "));
+        let suppressed = self.is_muted();
+        if !suppressed {
+            let names: Vec<&str> = grouped.iter().map(|a| a.name.as_str()).collect();
+            let options = NotificationOptions {
+                icon_suffix: self.config.icons.suffix(self.color_scheme),
+                ..self.config.grouped_notification_options("battery-low")
+            };
+            deliver(
+                &self.config.sinks,
+                &mut self.extra_sinks,
+                &names.join(", "),
+                "Low battery",
+                &content,
+                "battery-low",
+                &options,
+            );
+        }
+        for alert in &grouped {
+            self.record_event(&alert.name, &content, suppressed);
+        }
+    }
+
+    /// Keep a notification about the device `name` in the recent events
+    fn record_event(&mut self, name: &str, content: &str, suppressed: bool) {
+        while !self.events.is_empty() && self.events.len() >= self.config.event_log_len {
+            self.events.pop_front();
+        }
+        self.events.push_back(Event {
+            id: self.next_event_id,
+            time: self.clock.local(),
+            device: name.to_string(),
+            message: content.to_string(),
+            suppressed,
+        });
+        self.next_event_id += 1;
+    }
+}
+
+/// Send a notification to the configured sinks and `extra_sinks`, logging failures
+fn deliver(
+    sinks: &[Sink],
+    extra_sinks: &mut [Box<dyn NotificationSink>],
+    name: &str,
+    display_name: &str,
+    content: &str,
+    icon: &str,
+    options: &NotificationOptions,
+) {
+    for e in dispatch(sinks, display_name, content, icon, options) {
+        eprintln!("failed to deliver notification: {}", e);
+    }
+    for sink in extra_sinks.iter_mut() {
+        if let Err(e) = sink.send(name, display_name, content, icon) {
+            eprintln!("failed to deliver notification: {}", e);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Notification delivery
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    bus::Subscriber,
+    command::{CommandRunner, SystemRunner},
+    config::Sink,
+    device::{DeviceEvent, Threshold},
+    error::NotifyError,
+    monitor::Monitor,
+    platform::{Native, NotificationOptions, Platform},
+};
+
+/// A destination for notifications besides the built-in [`Sink`]s
+pub trait NotificationSink: Send {
+    /// Deliver a notification about the device `name`, shown to the user as `display_name`
+    fn send(
+        &mut self,
+        name: &str,
+        display_name: &str,
+        content: &str,
+        icon: &str,
+    ) -> Result<(), NotifyError>;
+}
+
+/// Deliver a notification to every configured sink, returning the failures
+#[cfg_attr(not(feature = "desktop"), allow(unused_variables, unused_mut))]
+pub fn dispatch(
+    sinks: &[Sink],
+    name: &str,
+    content: &str,
+    icon: &str,
+    options: &NotificationOptions,
+) -> Vec<NotifyError> {
+    let mut errors = Vec::new();
+    for sink in sinks {
+        match sink {
+            #[cfg(feature = "desktop")]
+            Sink::Desktop => {
+                let result =
+                    send_notification(&SystemRunner, &Native, name, content, icon, options);
+                if let Err(e) = result {
+                    errors.push(e);
+                }
+            }
+            // Without desktop support, fall back to printing rather than dropping notifications
+            #[cfg(not(feature = "desktop"))]
+            Sink::Desktop => println!("{}: {}", name, content),
+            Sink::Stdout => println!("{}: {}", name, content),
+            Sink::Wall if options.critical => {
+                if let Err(e) = broadcast(&SystemRunner, &Native, name, content) {
+                    errors.push(e);
+                }
+            }
+            Sink::Wall => {}
+        }
+    }
+    errors
+}
+
+/// Whether a notification shown with `icon` is a critical alert, which is sent even where
+/// routine notifications are not
+pub fn is_critical(icon: &str) -> bool {
+    icon == "battery-low"
+}
+
+/// Broadcast a notification to every terminal with `wall`, run by `runner`, writing it to the
+/// terminal of `platform` instead if `wall` is missing or fails
+pub fn broadcast(
+    runner: &impl CommandRunner,
+    platform: &impl Platform,
+    name: &str,
+    content: &str,
+) -> Result<(), NotifyError> {
+    let message = format!("{}: {}\n", name, content);
+    let error = match runner.run_with_input("wall", &[], message.as_bytes()) {
+        Ok(output) if output.is_success() => return Ok(()),
+        Ok(output) => NotifyError::Exit {
+            program: "wall".to_string(),
+            code: output.code,
+        },
+        Err(source) => NotifyError::Spawn {
+            program: "wall".to_string(),
+            source,
+        },
+    };
+    // Report why wall failed rather than the terminal, which daemons usually do not have
+    write_terminal(platform, &message).map_err(|_| error)
+}
+
+/// Write `message` to the terminal of `platform`
+fn write_terminal(platform: &impl Platform, message: &str) -> io::Result<()> {
+    let path = platform.terminal().ok_or(io::ErrorKind::NotFound)?;
+    let mut terminal = OpenOptions::new().write(true).open(path)?;
+    terminal.write_all(message.as_bytes())
+}
+
+/// Send a desktop notification the way `platform` shows them
+#[cfg(feature = "desktop")]
+fn send_notification(
+    runner: &impl CommandRunner,
+    platform: &impl Platform,
+    name: &str,
+    content: &str,
+    icon: &str,
+    options: &NotificationOptions,
+) -> Result<(), NotifyError> {
+    let command = platform.notification_with(name, content, icon, options);
+    let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+    let output = runner
+        .run(command.program, &args)
+        .map_err(|source| NotifyError::Spawn {
+            program: command.program.to_string(),
+            source,
+        })?;
+    if !output.is_success() {
+        return Err(NotifyError::Exit {
+            program: command.program.to_string(),
+            code: output.code,
+        });
+    }
+    Ok(())
+}
+
+/// Subscriber raising a notification for every event worth telling the user about
+#[derive(Default)]
+pub struct Notifier;
+
+impl Subscriber for Notifier {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        let name = event.device();
+        match *event {
+            DeviceEvent::Connected { battery, .. } => {
+                monitor.notify(name, "New device connected", "battery");
+                if let Some(battery) = battery {
+                    // Give the first notification time to show before the second one
+                    if !monitor.is_muted() && monitor.has_sinks() {
+                        monitor.clock().sleep(Duration::from_secs(1));
+                    }
+                    set_notified_level(monitor, name, Some(battery.percent()));
+                    monitor.notify(name, &format!("Battery level: {}", battery), "battery");
+                }
+            }
+            DeviceEvent::Disconnected { .. } => {
+                set_notified_level(monitor, name, None);
+                if monitor.turned_off_idle(name) {
+                    monitor.notify(name, "Headset turned off (idle)", "system-shutdown");
+                } else {
+                    monitor.notify(name, "Device disconnected", "battery-caution");
+                }
+            }
+            DeviceEvent::ThresholdCrossed {
+                battery,
+                threshold,
+                charging,
+                ..
+            } => {
+                let estimate = monitor
+                    .estimate(name)
+                    .map(|e| format!(" ({})", e))
+                    .unwrap_or_default();
+                set_notified_level(monitor, name, Some(battery.percent()));
+                let message = match (threshold, charging) {
+                    (Threshold::Low, _) => {
+                        let message = format!("Battery level low: {}{}", battery, estimate);
+                        monitor.alert_low(name, battery, &message);
+                        return;
+                    }
+                    (Threshold::Step, false) => format!("Battery level: {}", battery),
+                    (Threshold::Step, true) => format!("Charging {}", battery),
+                };
+                monitor.notify(name, &format!("{}{}", message, estimate), "battery");
+            }
+            DeviceEvent::Full { .. } => {
+                set_notified_level(monitor, name, Some(100));
+                monitor.notify(name, "Battery level full: 100%", "battery");
+            }
+            DeviceEvent::ChargingStarted { .. } if monitor.config.notify_charging => {
+                let message = with_level(monitor, name, "Charging started");
+                monitor.notify(name, &message, "battery-good-charging");
+            }
+            DeviceEvent::ChargingStopped { .. } if monitor.config.notify_charging => {
+                let message = with_level(monitor, name, "Charging stopped");
+                monitor.notify(name, &message, "battery");
+            }
+            DeviceEvent::ChargingStarted { .. }
+            | DeviceEvent::ChargingStopped { .. }
+            | DeviceEvent::LevelChanged { .. } => {}
+        }
+    }
+}
+
+/// `message` followed by the current battery reading of the device, if it has one
+fn with_level(monitor: &Monitor, name: &str, message: &str) -> String {
+    match monitor.devices.get(name).and_then(|d| d.battery) {
+        Some(battery) => format!("{} at {}", message, battery),
+        None => message.to_string(),
+    }
+}
+
+/// Remember the battery level a device was last notified about
+fn set_notified_level(monitor: &mut Monitor, name: &str, level: Option<u8>) {
+    if let Some(device) = monitor.devices.get_mut(name) {
+        device.last_notif_battery_level = level;
+    }
+}
+
+#[cfg(all(test, feature = "desktop"))]
+mod tests {
+    use super::*;
+    use crate::{
+        command::{CommandOutput, FakeRunner},
+        platform::{Freedesktop, MacOs},
+    };
+
+    #[test]
+    fn send_notification_runs_platform_command() {
+        let runner = FakeRunner::default();
+        send_notification(
+            &runner,
+            &Freedesktop,
+            "Arctis 7",
+            "Battery level: 50%",
+            "battery",
+            &NotificationOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            runner.calls(),
+            [[
+                "notify-send",
+                "Arctis 7",
+                "Battery level: 50%",
+                "--icon=battery"
+            ]]
+        );
+
+        let runner = FakeRunner::default();
+        send_notification(
+            &runner,
+            &MacOs,
+            "Arctis 7",
+            "Battery level: 50%",
+            "battery",
+            &NotificationOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(runner.calls()[0][0], "osascript");
+    }
+
+    #[test]
+    fn send_notification_reports_failure() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(1),
+            stdout: String::new(),
+        });
+        let err = send_notification(
+            &runner,
+            &Freedesktop,
+            "Arctis 7",
+            "Battery level: 50%",
+            "battery",
+            &NotificationOptions::default(),
+        );
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "notify-send exited with code 1"
+        );
+    }
+}
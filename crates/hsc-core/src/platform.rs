@@ -0,0 +1,103 @@
+//! Operating-system specific services
+//!
+//! Where files live and how desktop notifications are shown differ between systems. Each system
+//! implements [`Platform`] in its own module, and [`Native`] names the one this build targets, so
+//! the rest of the code never needs a `cfg` to find its way around. Every implementation is built
+//! on every system, which keeps them all tested wherever the tests run.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::command::CommandRunner;
+
+mod freedesktop;
+mod macos;
+mod windows;
+
+pub use freedesktop::Freedesktop;
+pub use macos::MacOs;
+pub use windows::Windows;
+
+/// The platform this build targets
+#[cfg(not(any(target_os = "macos", windows)))]
+pub use freedesktop::Freedesktop as Native;
+/// The platform this build targets
+#[cfg(target_os = "macos")]
+pub use macos::MacOs as Native;
+/// The platform this build targets
+#[cfg(windows)]
+pub use windows::Windows as Native;
+
+/// Name of this program's directories
+const APP_DIR: &str = "headsetcontrol-notify";
+
+/// Conventions of an operating system
+pub trait Platform {
+    /// This program's configuration directory
+    fn config_dir(&self) -> PathBuf;
+
+    /// This program's directory for the history database and other data
+    fn data_dir(&self) -> PathBuf;
+
+    /// Command showing a desktop notification with `title`, `body` and, where the system has
+    /// named icons, `icon`
+    fn notification(&self, title: &str, body: &str, icon: &str) -> PlatformCommand;
+
+    /// Command showing a notification like [`Platform::notification`], with `options` applied
+    /// where the system supports them
+    fn notification_with(
+        &self,
+        title: &str,
+        body: &str,
+        icon: &str,
+        options: &NotificationOptions,
+    ) -> PlatformCommand {
+        let _ = options;
+        self.notification(title, body, icon)
+    }
+
+    /// Terminal that alerts are written to when they cannot be broadcast, if the system has one
+    fn terminal(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether the desktop uses light or dark colors, asking the system with `runner`, if it
+    /// can be told
+    fn color_scheme(&self, runner: &impl CommandRunner) -> Option<ColorScheme> {
+        let _ = runner;
+        None
+    }
+}
+
+/// How a notification is shown and filed by the notification service
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NotificationOptions<'a> {
+    /// Application name the notification is sent under, instead of the notifier's default
+    pub app_name: Option<&'a str>,
+    /// Key grouping notifications, so those sharing it are stacked together
+    pub group: Option<&'a str>,
+    /// Appended to named icons, choosing their variant for the desktop's color scheme
+    pub icon_suffix: Option<&'a str>,
+    /// Whether this is a critical alert, decided before any per-device icon replaced the icon
+    /// that tells
+    pub critical: bool,
+}
+
+/// Colors of the desktop, which icons need to stand out against
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ColorScheme {
+    /// Dark text on light backgrounds
+    Light,
+    /// Light text on dark backgrounds
+    Dark,
+}
+
+/// A program to run with its arguments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlatformCommand {
+    pub program: &'static str,
+    pub args: Vec<String>,
+}
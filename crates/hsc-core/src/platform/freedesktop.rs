@@ -0,0 +1,255 @@
+//! Linux and the BSDs: XDG base directories and `notify-send`
+
+use std::{
+    borrow::Cow,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{ColorScheme, NotificationOptions, Platform, PlatformCommand, APP_DIR};
+use crate::command::CommandRunner;
+
+/// Desktops following the freedesktop.org specifications
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Freedesktop;
+
+impl Platform for Freedesktop {
+    fn config_dir(&self) -> PathBuf {
+        xdg_base_dir("XDG_CONFIG_HOME", ".config").join(APP_DIR)
+    }
+
+    fn data_dir(&self) -> PathBuf {
+        xdg_base_dir("XDG_DATA_HOME", ".local/share").join(APP_DIR)
+    }
+
+    /// The controlling terminal
+    fn terminal(&self) -> Option<PathBuf> {
+        Some(PathBuf::from("/dev/tty"))
+    }
+
+    /// Icons that are neither an icon name nor an absolute image path are replaced with
+    /// `dialog-information`
+    fn notification(&self, title: &str, body: &str, icon: &str) -> PlatformCommand {
+        let icon = if is_icon(icon) {
+            icon
+        } else {
+            "dialog-information"
+        };
+        PlatformCommand {
+            program: "notify-send",
+            args: vec![
+                title.to_string(),
+                body.to_string(),
+                format!("--icon={}", icon),
+            ],
+        }
+    }
+
+    /// The group is passed as the stack tag of dunst and the synchronous hint understood by most
+    /// other notification daemons, both of which replace a notification with the next one of the
+    /// same group. The icon suffix is only added to icon names, not to image paths.
+    fn notification_with(
+        &self,
+        title: &str,
+        body: &str,
+        icon: &str,
+        options: &NotificationOptions,
+    ) -> PlatformCommand {
+        let icon = match options.icon_suffix {
+            Some(suffix) if !Path::new(icon).is_absolute() => {
+                Cow::Owned(format!("{}{}", icon, suffix))
+            }
+            _ => Cow::Borrowed(icon),
+        };
+        let mut command = self.notification(title, body, &icon);
+        if let Some(app_name) = options.app_name {
+            command.args.push(format!("--app-name={}", app_name));
+        }
+        if let Some(group) = options.group {
+            command
+                .args
+                .push(format!("--hint=string:x-dunst-stack-tag:{}", group));
+            command.args.push(format!(
+                "--hint=string:x-canonical-private-synchronous:{}",
+                group
+            ));
+        }
+        command
+    }
+
+    /// The `color-scheme` GNOME setting, which GNOME and KDE Plasma both keep up to date, or
+    /// else KDE's own color scheme
+    fn color_scheme(&self, runner: &impl CommandRunner) -> Option<ColorScheme> {
+        let gsettings = runner
+            .run(
+                "gsettings",
+                &["get", "org.gnome.desktop.interface", "color-scheme"],
+            )
+            .ok()
+            .filter(|output| output.is_success())
+            .and_then(|output| parse_gsettings_scheme(&output.stdout));
+        gsettings.or_else(|| {
+            let kdeglobals = xdg_base_dir("XDG_CONFIG_HOME", ".config").join("kdeglobals");
+            parse_kdeglobals(&fs::read_to_string(kdeglobals).ok()?)
+        })
+    }
+}
+
+/// Color scheme from `gsettings get org.gnome.desktop.interface color-scheme`, unless the
+/// desktop left it at its default
+fn parse_gsettings_scheme(stdout: &str) -> Option<ColorScheme> {
+    match stdout.trim().trim_matches('\'') {
+        "prefer-dark" => Some(ColorScheme::Dark),
+        "prefer-light" => Some(ColorScheme::Light),
+        _ => None,
+    }
+}
+
+/// Color scheme named by the `ColorScheme` key in the `[General]` group of KDE's `kdeglobals`,
+/// such as `BreezeDark` or `BreezeLight`
+fn parse_kdeglobals(contents: &str) -> Option<ColorScheme> {
+    let mut general = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            general = line == "[General]";
+        } else if let Some(name) = line.strip_prefix("ColorScheme=").filter(|_| general) {
+            return Some(if name.to_lowercase().contains("dark") {
+                ColorScheme::Dark
+            } else {
+                ColorScheme::Light
+            });
+        }
+    }
+    None
+}
+
+/// Whether `icon` can be shown: a name as allowed by the freedesktop icon naming specification,
+/// or an absolute path to an image file
+fn is_icon(icon: &str) -> bool {
+    if Path::new(icon).is_absolute() {
+        return true;
+    }
+    !icon.is_empty()
+        && icon
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-_.".contains(c))
+}
+
+/// An XDG base directory, given its variable and fallback below `$HOME`
+fn xdg_base_dir(var: &str, home_fallback: &str) -> PathBuf {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(home_fallback)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::{CommandOutput, FakeRunner};
+
+    use super::*;
+
+    #[test]
+    fn notification_passes_title_body_and_icon() {
+        let command = Freedesktop.notification("Arctis 7", "Battery level: 50%", "battery");
+        assert_eq!(command.program, "notify-send");
+        assert_eq!(
+            command.args,
+            ["Arctis 7", "Battery level: 50%", "--icon=battery"]
+        );
+    }
+
+    #[test]
+    fn notification_passes_app_name_and_group() {
+        let options = NotificationOptions {
+            app_name: Some("headset"),
+            group: Some("arctis"),
+            ..NotificationOptions::default()
+        };
+        let command = Freedesktop.notification_with("Arctis 7", "Charged", "battery", &options);
+        assert_eq!(
+            command.args[3..],
+            [
+                "--app-name=headset",
+                "--hint=string:x-dunst-stack-tag:arctis",
+                "--hint=string:x-canonical-private-synchronous:arctis"
+            ]
+        );
+
+        let command = Freedesktop.notification_with(
+            "Arctis 7",
+            "Charged",
+            "battery",
+            &NotificationOptions::default(),
+        );
+        assert_eq!(
+            command,
+            Freedesktop.notification("Arctis 7", "Charged", "battery")
+        );
+    }
+
+    #[test]
+    fn notification_replaces_invalid_icon() {
+        for icon in ["", "No Such Icon", "icons/mouse.png"] {
+            let command = Freedesktop.notification("Arctis 7", "Device disconnected", icon);
+            assert_eq!(command.args[2], "--icon=dialog-information");
+        }
+    }
+
+    #[test]
+    fn notification_accepts_icon_names_and_image_paths() {
+        for icon in [
+            "input-mouse",
+            "audio-headset",
+            "/usr/share/pixmaps/headset.png",
+        ] {
+            let command = Freedesktop.notification("Arctis 7", "Battery level: 50%", icon);
+            assert_eq!(command.args[2], format!("--icon={}", icon));
+        }
+    }
+
+    #[test]
+    fn icon_suffix_is_added_to_icon_names_only() {
+        let options = NotificationOptions {
+            icon_suffix: Some("-dark"),
+            ..NotificationOptions::default()
+        };
+        let command = Freedesktop.notification_with("Arctis 7", "Low", "battery-low", &options);
+        assert_eq!(command.args[2], "--icon=battery-low-dark");
+        let path = "/usr/share/pixmaps/headset.png";
+        let command = Freedesktop.notification_with("Arctis 7", "Low", path, &options);
+        assert_eq!(command.args[2], format!("--icon={}", path));
+    }
+
+    #[test]
+    fn color_scheme_is_read_from_gsettings() {
+        let runner = FakeRunner::with_output(CommandOutput {
+            code: Some(0),
+            stdout: "'prefer-dark'\n".to_string(),
+        });
+        assert_eq!(Freedesktop.color_scheme(&runner), Some(ColorScheme::Dark));
+        assert_eq!(
+            runner.calls(),
+            [[
+                "gsettings",
+                "get",
+                "org.gnome.desktop.interface",
+                "color-scheme"
+            ]]
+        );
+        assert_eq!(parse_gsettings_scheme("'default'\n"), None);
+    }
+
+    #[test]
+    fn color_scheme_is_read_from_kdeglobals() {
+        let kdeglobals =
+            "[Colors:Window]\nColorScheme=Ignored\n\n[General]\nColorScheme=BreezeDark\n";
+        assert_eq!(parse_kdeglobals(kdeglobals), Some(ColorScheme::Dark));
+        assert_eq!(
+            parse_kdeglobals("[General]\nColorScheme=BreezeLight\n"),
+            Some(ColorScheme::Light)
+        );
+        assert_eq!(parse_kdeglobals("[KDE]\nSingleClick=false\n"), None);
+    }
+}
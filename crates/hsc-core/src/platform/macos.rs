@@ -0,0 +1,67 @@
+//! macOS: `~/Library/Application Support` and Notification Center through `osascript`
+
+use std::{env, path::PathBuf};
+
+use super::{Platform, PlatformCommand, APP_DIR};
+
+/// macOS
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MacOs;
+
+impl Platform for MacOs {
+    fn config_dir(&self) -> PathBuf {
+        env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join("Library/Application Support")
+            .join(APP_DIR)
+    }
+
+    /// The same directory as the configuration, as is usual on macOS
+    fn data_dir(&self) -> PathBuf {
+        self.config_dir()
+    }
+
+    /// The controlling terminal
+    fn terminal(&self) -> Option<PathBuf> {
+        Some(PathBuf::from("/dev/tty"))
+    }
+
+    /// Notification Center has no named icons, so `icon` is ignored
+    fn notification(&self, title: &str, body: &str, _icon: &str) -> PlatformCommand {
+        PlatformCommand {
+            program: "osascript",
+            args: vec![
+                "-e".to_string(),
+                format!(
+                    "display notification {} with title {}",
+                    applescript_string(body),
+                    applescript_string(title)
+                ),
+            ],
+        }
+    }
+}
+
+/// `s` as an AppleScript string literal
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_quotes_title_and_body() {
+        let command = MacOs.notification("My \"Arctis\"", "Battery level: 5%", "battery-low");
+        assert_eq!(command.program, "osascript");
+        assert_eq!(
+            command.args,
+            [
+                "-e",
+                r#"display notification "Battery level: 5%" with title "My \"Arctis\"""#
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Windows: `%APPDATA%` and `%LOCALAPPDATA%`, and toast notifications through PowerShell
+
+use std::{env, path::PathBuf};
+
+use super::{Platform, PlatformCommand, APP_DIR};
+
+/// Application ID toasts are shown under; PowerShell's is registered on every installation
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// Windows 10 and later
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Windows;
+
+impl Platform for Windows {
+    /// `%APPDATA%`, which roams with the user's profile
+    fn config_dir(&self) -> PathBuf {
+        known_folder("APPDATA")
+    }
+
+    /// `%LOCALAPPDATA%`, which stays on this machine
+    fn data_dir(&self) -> PathBuf {
+        known_folder("LOCALAPPDATA")
+    }
+
+    /// The console of the process
+    fn terminal(&self) -> Option<PathBuf> {
+        Some(PathBuf::from("CONOUT$"))
+    }
+
+    /// Toasts have no named icons, so `icon` is ignored
+    fn notification(&self, title: &str, body: &str, _icon: &str) -> PlatformCommand {
+        let script = format!(
+            "$ErrorActionPreference = 'Stop'; \
+             $m = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+             $xml = $m::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $text = $xml.GetElementsByTagName('text'); \
+             [void]$text.Item(0).AppendChild($xml.CreateTextNode({})); \
+             [void]$text.Item(1).AppendChild($xml.CreateTextNode({})); \
+             $m::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+            powershell_string(title),
+            powershell_string(body),
+            powershell_string(APP_ID)
+        );
+        PlatformCommand {
+            program: "powershell",
+            args: vec![
+                "-NoProfile".to_string(),
+                "-NonInteractive".to_string(),
+                "-Command".to_string(),
+                script,
+            ],
+        }
+    }
+}
+
+/// This program's directory under the folder named by the environment variable `var`
+fn known_folder(var: &str) -> PathBuf {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(APP_DIR)
+}
+
+/// `s` as a single-quoted PowerShell string, which expands nothing
+fn powershell_string(s: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in s.chars() {
+        // PowerShell also ends single-quoted strings at typographic single quotes
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_quotes_title_and_body() {
+        let command = Windows.notification("Bob's Arctis", "Battery level: $5", "battery");
+        assert_eq!(command.program, "powershell");
+        let script = command.args.last().unwrap();
+        assert!(script.contains("CreateTextNode('Bob''s Arctis')"));
+        assert!(script.contains("CreateTextNode('Battery level: $5')"));
+    }
+
+    #[test]
+    fn typographic_quotes_are_doubled() {
+        assert_eq!(powershell_string("Bob\u{2019}s"), "'Bob\u{2019}\u{2019}s'");
+    }
+}
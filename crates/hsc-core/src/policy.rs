@@ -0,0 +1,67 @@
+//! Notification policy: which battery readings deserve a notification
+
+use std::time::Duration;
+
+use crate::{
+    audio::{self, AudioNode},
+    config::{Config, NotificationMode},
+    device::BatteryReading,
+    estimate::Estimate,
+};
+
+/// Thresholds and filters deciding when to notify, derived from the configuration
+#[derive(Clone, Copy)]
+pub struct Policy<'a> {
+    config: &'a Config,
+}
+
+impl<'a> Policy<'a> {
+    /// Policy applying the given configuration
+    pub fn new(config: &'a Config) -> Self {
+        Policy { config }
+    }
+
+    /// Whether a discharging device is below its percentage or remaining-time threshold
+    pub fn is_low(&self, name: &str, battery: BatteryReading, estimate: Option<&Estimate>) -> bool {
+        if battery.is_below(self.config.threshold_for(name)) {
+            return true;
+        }
+        match (self.config.threshold_minutes_for(name), estimate) {
+            (Some(minutes), Some(estimate)) => {
+                estimate.remaining < Duration::from_secs(minutes as u64 * 60)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the battery level falls on a notification step
+    ///
+    /// Every change of a coarse level is a step, since there are only a few of them. Nothing is
+    /// a step in [`NotificationMode::ThresholdsOnly`].
+    pub fn is_step(&self, battery: BatteryReading) -> bool {
+        if self.config.notification_mode == NotificationMode::ThresholdsOnly {
+            return false;
+        }
+        match battery {
+            BatteryReading::Percent(percent) => {
+                percent.is_multiple_of(self.config.notification_step.max(1))
+            }
+            BatteryReading::Level { .. } => true,
+        }
+    }
+
+    /// Whether routine notifications should be sent for the named device
+    ///
+    /// With `audio.only_active_output` set, only devices backing one of `active_audio` qualify.
+    /// Everything qualifies while the sound server state is unknown.
+    pub fn is_in_use(&self, name: &str, active_audio: Option<&[AudioNode]>) -> bool {
+        if !self.config.audio.only_active_output {
+            return true;
+        }
+        active_audio.is_none_or(|nodes| {
+            nodes
+                .iter()
+                .any(|node| audio::node_matches(self.config, node, name))
+        })
+    }
+}
@@ -0,0 +1,209 @@
+//! Stable JSON schema for everything the notifier emits
+//!
+//! Internal types such as [`Device`] and [`DeviceEvent`] change freely; the types here are what
+//! exports, `status --json` and plugins see, and only ever grow new optional fields within a
+//! [`SCHEMA_VERSION`]. Removing or renaming a field, or changing its meaning, bumps the version.
+//! Every top-level document carries a `schema_version` field so consumers can tell them apart.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent, Threshold},
+    extras::{Chatmix, Extras},
+};
+
+/// Version of the documents defined here
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Battery status of a device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Charging,
+    Discharging,
+    Disconnected,
+}
+
+impl Status {
+    /// Name used in the JSON documents, also written to CSV exports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Charging => "charging",
+            Status::Discharging => "discharging",
+            Status::Disconnected => "disconnected",
+        }
+    }
+}
+
+impl From<BatteryStatus> for Status {
+    fn from(status: BatteryStatus) -> Self {
+        match status {
+            BatteryStatus::Charging => Status::Charging,
+            BatteryStatus::Discharging => Status::Discharging,
+            BatteryStatus::Disconnected => Status::Disconnected,
+        }
+    }
+}
+
+/// Current state of a device
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceState {
+    /// Name reported by the device
+    pub name: String,
+    /// Name used in notifications
+    pub display_name: String,
+    pub status: Status,
+    /// Battery percentage, if reported
+    pub level: Option<u8>,
+    /// Level reported by devices with a few discrete levels, from which `level` is derived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coarse_level: Option<CoarseLevel>,
+    /// Chatmix dial position from 0 (game audio only) to 128 (chat audio only), when read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chatmix: Option<u8>,
+    /// Features the headset supports, when read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+}
+
+/// `current` out of `max` discrete battery levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoarseLevel {
+    pub current: u8,
+    pub max: u8,
+}
+
+impl DeviceState {
+    /// State of `device`, named as `config` says
+    pub fn new(config: &Config, device: &Device) -> Self {
+        DeviceState {
+            name: device.name.clone(),
+            display_name: config.display_name(&device.name).to_string(),
+            status: device.battery_status.into(),
+            level: device.level(),
+            coarse_level: match device.battery {
+                Some(BatteryReading::Level { current, max }) => Some(CoarseLevel { current, max }),
+                _ => None,
+            },
+            chatmix: None,
+            capabilities: Vec::new(),
+        }
+    }
+
+    /// Add the readings besides the battery
+    pub fn with_extras(mut self, extras: &Extras) -> Self {
+        self.chatmix = extras.chatmix.map(Chatmix::value);
+        self.capabilities = extras.capabilities.clone();
+        self
+    }
+}
+
+/// State of every device, as printed by `status --json`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub schema_version: u32,
+    pub devices: Vec<DeviceState>,
+}
+
+impl StatusReport {
+    /// Report of the current version
+    pub fn new(devices: Vec<DeviceState>) -> Self {
+        StatusReport {
+            schema_version: SCHEMA_VERSION,
+            devices,
+        }
+    }
+}
+
+/// Kind of a threshold crossing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdKind {
+    /// Below the low battery percentage or remaining-time threshold
+    Low,
+    /// On a multiple of the notification step
+    Step,
+}
+
+/// What happened to a device, tagged by `kind`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    Connected {
+        level: Option<u8>,
+    },
+    Disconnected,
+    ChargingStarted,
+    ChargingStopped,
+    LevelChanged {
+        from: u8,
+        to: u8,
+    },
+    ThresholdCrossed {
+        level: u8,
+        threshold: ThresholdKind,
+        charging: bool,
+    },
+    Full,
+}
+
+impl From<&DeviceEvent> for EventKind {
+    fn from(event: &DeviceEvent) -> Self {
+        match *event {
+            DeviceEvent::Connected { battery, .. } => EventKind::Connected {
+                level: battery.map(BatteryReading::percent),
+            },
+            DeviceEvent::Disconnected { .. } => EventKind::Disconnected,
+            DeviceEvent::ChargingStarted { .. } => EventKind::ChargingStarted,
+            DeviceEvent::ChargingStopped { .. } => EventKind::ChargingStopped,
+            DeviceEvent::LevelChanged { from, to, .. } => EventKind::LevelChanged {
+                from: from.percent(),
+                to: to.percent(),
+            },
+            DeviceEvent::ThresholdCrossed {
+                battery,
+                threshold,
+                charging,
+                ..
+            } => EventKind::ThresholdCrossed {
+                level: battery.percent(),
+                threshold: match threshold {
+                    Threshold::Low => ThresholdKind::Low,
+                    Threshold::Step => ThresholdKind::Step,
+                },
+                charging,
+            },
+            DeviceEvent::Full { .. } => EventKind::Full,
+        }
+    }
+}
+
+/// A recorded battery reading
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadingRecord {
+    /// RFC 3339 time of the reading
+    pub timestamp: String,
+    pub device: String,
+    pub level: Option<u8>,
+    pub status: Status,
+}
+
+/// A recorded notification
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    /// RFC 3339 time the notification was raised
+    pub timestamp: String,
+    pub device: String,
+    pub message: String,
+}
+
+/// Exported history
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Export {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readings: Option<Vec<ReadingRecord>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<Vec<NotificationRecord>>,
+}
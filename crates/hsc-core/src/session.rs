@@ -0,0 +1,70 @@
+//! Usage tracking between full charges
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use crate::{device::BatteryStatus, estimate::format_duration};
+
+/// Longest gap between readings still counted as continuous use
+const MAX_GAP: Duration = Duration::from_secs(10 * 60);
+
+/// Time a device has been used since it was last fully charged
+#[derive(Clone, Debug, Default)]
+pub struct ChargeSession {
+    /// When the device last reached 100% while charging, if known
+    pub full_at: Option<DateTime<Local>>,
+    /// Time spent discharging since then
+    pub usage: Duration,
+    /// When the previous reading was taken
+    last_reading: Option<Instant>,
+}
+
+impl ChargeSession {
+    /// Account for a reading taken at `now`, or `local` in wall-clock time, starting a new session
+    /// when the device is full
+    pub fn push(
+        &mut self,
+        now: Instant,
+        local: DateTime<Local>,
+        level: Option<u8>,
+        status: BatteryStatus,
+    ) {
+        if status == BatteryStatus::Charging && level == Some(100) {
+            self.full_at = Some(local);
+            self.usage = Duration::ZERO;
+        } else if status == BatteryStatus::Discharging {
+            if let Some(elapsed) = self.last_reading.map(|t| now.duration_since(t)) {
+                if elapsed <= MAX_GAP {
+                    self.usage += elapsed;
+                }
+            }
+        }
+        self.last_reading = Some(now);
+    }
+
+    /// The usage as display text, with the full charge dated relative to `now`
+    pub fn describe(&self, now: DateTime<Local>) -> String {
+        match self.full_at {
+            Some(full_at) => {
+                let ago = (now - full_at).to_std().unwrap_or_default();
+                format!(
+                    "used {} since full charge {} ago",
+                    format_duration(self.usage),
+                    format_age(ago)
+                )
+            }
+            None => format!("used {} this session", format_duration(self.usage)),
+        }
+    }
+}
+
+/// Format a long duration coarsely, e.g. `45m`, `20h` or `3d`
+fn format_age(age: Duration) -> String {
+    let hours = age.as_secs() / 3600;
+    match hours {
+        0 => format_duration(age),
+        1..48 => format!("{}h", hours),
+        _ => format!("{}d", hours / 24),
+    }
+}
@@ -0,0 +1,129 @@
+//! Device state kept between runs
+//!
+//! A [`Monitor`] only notices changes between readings it has seen itself. Programs polling once
+//! per run, like a command started by a timer, save a [`Snapshot`] after polling and restore it
+//! into the next run's monitor, so changes since the previous run are notified as if the monitor
+//! had kept running.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    device::{BatteryReading, BatteryStatus, Device},
+    error::ConfigError,
+    monitor::Monitor,
+    platform::{Native, Platform},
+};
+
+/// Last known state of a device
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedDevice {
+    /// Name given by [`BatteryStatus::as_str`]
+    pub status: String,
+    /// Percentage, or current level of devices with `max_level`
+    pub level: Option<u8>,
+    /// Number of discrete levels, for devices not reporting percentages
+    pub max_level: Option<u8>,
+    /// Level the last notification was about
+    pub last_notified: Option<u8>,
+    /// The current discharge was already compared with the usage forecast
+    pub forecast_checked: bool,
+}
+
+/// State of every device the monitor knew about
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    /// Keyed by device name
+    pub devices: BTreeMap<String, SavedDevice>,
+}
+
+impl Snapshot {
+    /// Default location of the snapshot
+    pub fn default_path() -> PathBuf {
+        Native.data_dir().join("state.toml")
+    }
+
+    /// Load the snapshot from `path`, empty if it does not exist
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Snapshot::default()),
+            Err(source) => Err(ConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Write the snapshot to `path`, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let write_error = |source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?).map_err(write_error)
+    }
+
+    /// State of every device known to `monitor`
+    pub fn of(monitor: &Monitor) -> Self {
+        let devices = monitor
+            .devices
+            .values()
+            .map(|device| {
+                let (level, max_level) = match device.battery {
+                    Some(BatteryReading::Percent(percent)) => (Some(percent), None),
+                    Some(BatteryReading::Level { current, max }) => (Some(current), Some(max)),
+                    None => (None, None),
+                };
+                let saved = SavedDevice {
+                    status: device.battery_status.as_str().to_string(),
+                    level,
+                    max_level,
+                    last_notified: device.last_notif_battery_level,
+                    forecast_checked: monitor.forecast_checked.contains(&device.name),
+                };
+                (device.name.clone(), saved)
+            })
+            .collect();
+        Snapshot { devices }
+    }
+
+    /// Make `monitor` know the saved devices, skipping any whose state cannot be understood
+    pub fn restore(&self, monitor: &mut Monitor) {
+        for (name, saved) in &self.devices {
+            let Some(battery_status) = BatteryStatus::from_name(&saved.status) else {
+                continue;
+            };
+            let battery = match (saved.level, saved.max_level) {
+                (Some(current), Some(max)) => BatteryReading::level(current, max),
+                (Some(percent), None) => Some(BatteryReading::Percent(percent.min(100))),
+                (None, _) => None,
+            };
+            monitor.devices.insert(
+                name.clone(),
+                Device {
+                    name: name.clone(),
+                    battery_status,
+                    battery,
+                    last_notif_battery_level: saved.last_notified,
+                },
+            );
+            if saved.forecast_checked {
+                monitor.forecast_checked.insert(name.clone());
+            }
+        }
+    }
+}
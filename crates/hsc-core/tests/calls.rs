@@ -0,0 +1,79 @@
+//! Notifications held back while a headset's microphone is in use
+
+mod common;
+
+use std::sync::Arc;
+
+use hsc_core::{
+    audio::{AudioNode, NodeKind},
+    clock::ManualClock,
+    config::Config,
+    Monitor,
+};
+
+use common::{reading, Recorder};
+
+/// A source described as `description`
+fn source(description: &str, running: bool) -> AudioNode {
+    AudioNode {
+        name: description.replace(' ', "_"),
+        description: description.to_string(),
+        kind: NodeKind::Source,
+        running,
+    }
+}
+
+/// Monitor that already saw the Arctis 7 at 50%
+fn monitor() -> (Monitor, Recorder) {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        hold_during_calls: true,
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let recorder = Recorder::default();
+    monitor.add_sink(recorder.clone());
+    monitor.update_readings(vec![reading("Arctis 7", 50)]);
+    recorder.take();
+    (monitor, recorder)
+}
+
+#[test]
+fn notifications_are_held_until_the_call_ends() {
+    let (mut monitor, recorder) = monitor();
+    monitor.set_recording(&[source("Arctis 7 Chat", true)]);
+    assert!(monitor.is_in_call());
+    monitor.update_readings(vec![reading("Arctis 7", 45)]);
+    monitor.update_readings(vec![reading("Arctis 7", 40)]);
+    assert!(recorder.take().is_empty());
+
+    monitor.set_recording(&[source("Arctis 7 Chat", false)]);
+    assert!(!monitor.is_in_call());
+    assert_eq!(
+        recorder.take(),
+        [("Arctis 7".to_string(), "Battery level: 40%".to_string())]
+    );
+}
+
+#[test]
+fn low_battery_alerts_go_through_during_calls() {
+    let (mut monitor, recorder) = monitor();
+    monitor.set_recording(&[source("Arctis 7 Chat", true)]);
+    monitor.update_readings(vec![reading("Arctis 7", 8)]);
+    let sent = recorder.take();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].1.starts_with("Battery level low: 8%"));
+}
+
+#[test]
+fn other_microphones_are_not_calls() {
+    let (mut monitor, recorder) = monitor();
+    monitor.set_recording(&[source("Built-in Audio Analog Stereo", true)]);
+    assert!(!monitor.is_in_call());
+    monitor.update_readings(vec![reading("Arctis 7", 45)]);
+    assert_eq!(
+        recorder.take(),
+        [("Arctis 7".to_string(), "Battery level: 45%".to_string())]
+    );
+}
@@ -0,0 +1,102 @@
+//! Time-dependent monitor behaviour, driven by a virtual clock
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hsc_core::{clock::ManualClock, config::Config, Monitor};
+
+use common::Recorder;
+
+/// `headsetcontrol -b` output for one discharging device
+fn discharging(level: u8) -> String {
+    format!("Found Arctis 7!\n\nBattery:\n\tStatus: BATTERY_AVAILABLE\n\tLevel: {level}%\n")
+}
+
+/// Monitor without notification sinks, running on a virtual clock
+fn monitor() -> (Monitor, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::default());
+    let config = Config {
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    (Monitor::with_clock(config, clock.clone()), clock)
+}
+
+#[test]
+fn snooze_expires_when_time_advances() {
+    let (mut monitor, clock) = monitor();
+    monitor.snooze(Some(Duration::from_secs(30 * 60)));
+    assert!(monitor.is_muted());
+
+    clock.advance(Duration::from_secs(29 * 60));
+    assert_eq!(monitor.snooze_remaining(), Some(Duration::from_secs(60)));
+
+    clock.advance(Duration::from_secs(60));
+    assert!(!monitor.is_muted());
+}
+
+#[test]
+fn estimate_follows_virtual_time() {
+    let (mut monitor, clock) = monitor();
+    for level in (70..=80).rev() {
+        monitor.update(&discharging(level));
+        clock.advance(Duration::from_secs(6 * 60));
+    }
+    // 10%/h, give or take the second spent announcing the device
+    let estimate = monitor.estimate("Arctis 7").unwrap();
+    assert!((estimate.rate_per_hour - 10.0).abs() < 0.01);
+    assert!(
+        estimate
+            .remaining
+            .abs_diff(Duration::from_secs(7 * 60 * 60))
+            < Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn connect_notification_does_not_wait_for_real() {
+    let (mut monitor, _clock) = monitor();
+    monitor.add_sink(Recorder::default());
+    let local = monitor.clock().local();
+    let start = Instant::now();
+    monitor.update(&discharging(50));
+
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert_eq!(
+        monitor.clock().local() - local,
+        chrono::Duration::seconds(1)
+    );
+    let times: Vec<_> = monitor.events.iter().map(|e| e.time - local).collect();
+    assert_eq!(
+        times,
+        [chrono::Duration::zero(), chrono::Duration::seconds(1)]
+    );
+}
+
+#[test]
+fn connect_notifications_nobody_receives_are_not_spaced_out() {
+    let (mut monitor, _clock) = monitor();
+    let local = monitor.clock().local();
+    monitor.update(&discharging(50));
+    assert_eq!(monitor.clock().local(), local);
+    assert_eq!(monitor.events.len(), 2);
+}
+
+#[test]
+fn session_age_follows_virtual_time() {
+    let (mut monitor, clock) = monitor();
+    monitor.update("Found Arctis 7!\n\nBattery:\n\tStatus: BATTERY_CHARGING\n\tLevel: 100%\n");
+    for level in [99, 98, 97] {
+        clock.advance(Duration::from_secs(5 * 60));
+        monitor.update(&discharging(level));
+    }
+    clock.advance(Duration::from_secs(3 * 60 * 60));
+    assert_eq!(
+        monitor.sessions["Arctis 7"].describe(monitor.clock().local()),
+        "used 15m since full charge 3h ago"
+    );
+}
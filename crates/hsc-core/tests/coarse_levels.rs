@@ -0,0 +1,76 @@
+//! Devices reporting a few discrete battery levels instead of percentages
+
+use std::sync::Arc;
+
+use hsc_core::{
+    clock::ManualClock,
+    config::Config,
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent, Threshold},
+    Monitor,
+};
+
+/// A discharging keyboard showing `current` of 4 bars
+fn keyboard(current: u8) -> Device {
+    Device {
+        name: "Keyboard".to_string(),
+        battery_status: BatteryStatus::Discharging,
+        battery: BatteryReading::level(current, 4),
+        last_notif_battery_level: None,
+    }
+}
+
+#[test]
+fn levels_convert_to_percentages() {
+    let percents: Vec<u8> = (0..=4)
+        .map(|current| BatteryReading::level(current, 4).unwrap().percent())
+        .collect();
+    assert_eq!(percents, [0, 25, 50, 75, 100]);
+    assert_eq!(BatteryReading::level(2, 3).unwrap().percent(), 67);
+    assert_eq!(BatteryReading::level(5, 4), None);
+    assert_eq!(BatteryReading::level(0, 0), None);
+}
+
+#[test]
+fn level_is_low_once_it_may_be_below_the_threshold() {
+    let below = |current, threshold| {
+        BatteryReading::level(current, 4)
+            .unwrap()
+            .is_below(threshold)
+    };
+    // The lowest bar covers everything up to 25%
+    assert!(below(1, 10));
+    assert!(!below(2, 10));
+    assert!(below(2, 30));
+    assert!(!below(1, 0));
+}
+
+#[test]
+fn every_bar_lost_is_notified() {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let mut crossings = Vec::new();
+    for current in (1..=4).rev() {
+        for event in monitor.update_readings(vec![keyboard(current)]).events {
+            if let DeviceEvent::ThresholdCrossed {
+                battery, threshold, ..
+            } = event
+            {
+                crossings.push((battery.to_string(), threshold));
+            }
+        }
+    }
+    assert_eq!(
+        crossings,
+        [
+            ("3/4".to_string(), Threshold::Step),
+            ("2/4".to_string(), Threshold::Step),
+            ("1/4".to_string(), Threshold::Low),
+        ]
+    );
+    let messages: Vec<&str> = monitor.events.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages.last(), Some(&"Battery level low: 1/4"));
+}
@@ -0,0 +1,32 @@
+//! Running local programs with a deadline
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use hsc_core::command::{CommandRunner, SystemRunner};
+
+/// More than a pipe holds, so writing it blocks until the program reads
+const LARGE_INPUT: usize = 1 << 20;
+
+#[test]
+fn input_is_passed_and_output_read_in_time() {
+    let input = vec![b'x'; LARGE_INPUT];
+    let output = SystemRunner
+        .run_with_timeout("cat", &[], &input, Duration::from_secs(10))
+        .unwrap();
+    assert!(output.is_success());
+    assert_eq!(output.stdout.len(), LARGE_INPUT);
+}
+
+#[test]
+fn programs_ignoring_their_input_are_killed_in_time() {
+    let input = vec![b'x'; LARGE_INPUT];
+    let started = Instant::now();
+    let err = SystemRunner
+        .run_with_timeout("sleep", &["30"], &input, Duration::from_secs(1))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
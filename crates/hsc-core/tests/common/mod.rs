@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests
+// Each test crate only uses some of them
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use hsc_core::{
+    device::{BatteryReading, BatteryStatus, Device},
+    error::NotifyError,
+    notify::NotificationSink,
+};
+
+/// Sink keeping (name, display name, message) of every notification delivered
+#[derive(Clone, Default)]
+pub struct Recorder(Arc<Mutex<Vec<(String, String, String)>>>);
+
+impl NotificationSink for Recorder {
+    fn send(
+        &mut self,
+        name: &str,
+        display_name: &str,
+        content: &str,
+        _icon: &str,
+    ) -> Result<(), NotifyError> {
+        self.0.lock().unwrap().push((
+            name.to_string(),
+            display_name.to_string(),
+            content.to_string(),
+        ));
+        Ok(())
+    }
+}
+
+impl Recorder {
+    /// (name, display name, message) of the notifications delivered since the last call
+    pub fn take_all(&self) -> Vec<(String, String, String)> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+
+    /// (name, message) of the notifications delivered since the last call
+    pub fn take(&self) -> Vec<(String, String)> {
+        self.take_all()
+            .into_iter()
+            .map(|(name, _, message)| (name, message))
+            .collect()
+    }
+
+    /// Messages delivered since the last call
+    pub fn take_messages(&self) -> Vec<String> {
+        self.take_all()
+            .into_iter()
+            .map(|(_, _, message)| message)
+            .collect()
+    }
+}
+
+/// A discharging reading of `name`
+pub fn reading(name: &str, percent: u8) -> Device {
+    Device {
+        name: name.to_string(),
+        battery_status: BatteryStatus::Discharging,
+        battery: Some(BatteryReading::Percent(percent)),
+        last_notif_battery_level: None,
+    }
+}
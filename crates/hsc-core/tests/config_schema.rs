@@ -0,0 +1,49 @@
+//! The JSON Schema of the configuration file describes every key it accepts
+#![cfg(feature = "json-schema")]
+
+use hsc_core::config::Config;
+use serde_json::Value;
+
+/// Properties of the object schema `schema`, following a `$ref` into `root` if there is one
+fn properties<'a>(root: &'a Value, schema: &'a Value) -> &'a serde_json::Map<String, Value> {
+    let schema = match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => {
+            let name = reference.trim_start_matches("#/$defs/");
+            &root["$defs"][name]
+        }
+        None => schema,
+    };
+    schema["properties"]
+        .as_object()
+        .unwrap_or_else(|| panic!("no properties in {}", schema))
+}
+
+/// Check that every key of `value` is described by `schema`, recursing into tables
+fn assert_described(root: &Value, schema: &Value, value: &Value, path: &str) {
+    let properties = properties(root, schema);
+    for (key, value) in value.as_object().unwrap() {
+        let key_path = format!("{}.{}", path, key);
+        let property = properties
+            .get(key)
+            .unwrap_or_else(|| panic!("{} is missing from the schema", key_path));
+        let is_table = value.as_object().is_some_and(|table| !table.is_empty());
+        if is_table && property.get("additionalProperties").is_none() {
+            assert_described(root, property, value, &key_path);
+        }
+    }
+}
+
+#[test]
+fn schema_describes_default_config() {
+    let schema = serde_json::to_value(Config::json_schema()).unwrap();
+    let config = serde_json::to_value(Config::default()).unwrap();
+    assert_described(&schema, &schema, &config, "config");
+}
+
+#[test]
+fn schema_declares_defaults() {
+    let schema = serde_json::to_value(Config::json_schema()).unwrap();
+    let properties = properties(&schema, &schema);
+    assert_eq!(properties["battery_threshold"]["default"], 10);
+    assert_eq!(properties["polling_interval"]["default"], 5000);
+}
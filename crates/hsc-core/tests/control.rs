@@ -0,0 +1,213 @@
+//! Sending settings to headsets and recording what was applied
+
+use std::sync::{Arc, Mutex};
+
+use tempfile::TempDir;
+
+use hsc_core::{
+    clock::ManualClock,
+    config::Config,
+    control::{self, AppliedSettings, Controller, PowerSaving, Setting, SettingsStore},
+    device::{BatteryReading, BatteryStatus, Device},
+    error::ControlError,
+    extras::{Extras, CAPABILITY_INACTIVE_TIME},
+    Monitor,
+};
+
+/// Controller remembering every setting it was asked to send, failing if `fail` is set
+#[derive(Clone, Default)]
+struct FakeController {
+    sent: Arc<Mutex<Vec<(String, Setting)>>>,
+    fail: bool,
+}
+
+impl FakeController {
+    fn sent(&self) -> Vec<(String, Setting)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Controller for FakeController {
+    fn apply(&self, device: &str, setting: Setting) -> Result<(), ControlError> {
+        if self.fail {
+            return Err(ControlError::Backend("headset unplugged".into()));
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((device.to_string(), setting));
+        Ok(())
+    }
+}
+
+/// A reading of the Arctis 7
+fn arctis(status: BatteryStatus, level: u8) -> Device {
+    Device {
+        name: "Arctis 7".to_string(),
+        battery_status: status,
+        battery: Some(BatteryReading::Percent(level)),
+        last_notif_battery_level: None,
+    }
+}
+
+/// Monitor with power saving enabled whose settings are stored at `path`, knowing the Arctis 7
+/// can turn itself off
+fn power_saving(controller: &FakeController, path: &std::path::Path) -> Monitor {
+    let mut config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    config.power_saving.enabled = true;
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    monitor.settings = SettingsStore::load(path).unwrap();
+    monitor.set_extras(
+        "Arctis 7",
+        Extras {
+            capabilities: vec![CAPABILITY_INACTIVE_TIME.to_string()],
+            ..Extras::default()
+        },
+    );
+    monitor.subscribe(PowerSaving::new(controller.clone(), path.to_path_buf()));
+    monitor
+}
+
+#[test]
+fn applied_settings_are_recorded_per_device() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("state").join("settings.toml");
+    let controller = FakeController::default();
+
+    control::apply(&controller, &path, "Arctis 7", Setting::Lights(false)).unwrap();
+    control::apply(&controller, &path, "Arctis 7", Setting::Sidetone(64)).unwrap();
+    let store = control::apply(&controller, &path, "G435", Setting::InactiveTime(10)).unwrap();
+
+    assert_eq!(
+        controller.sent(),
+        [
+            ("Arctis 7".to_string(), Setting::Lights(false)),
+            ("Arctis 7".to_string(), Setting::Sidetone(64)),
+            ("G435".to_string(), Setting::InactiveTime(10)),
+        ]
+    );
+    assert_eq!(
+        store.get("Arctis 7"),
+        AppliedSettings {
+            lights: Some(false),
+            sidetone: Some(64),
+            inactive_time: None,
+        }
+    );
+    assert_eq!(store.get("G435").inactive_time, Some(10));
+    assert_eq!(SettingsStore::load(&path).unwrap(), store);
+}
+
+#[test]
+fn failed_settings_are_not_recorded() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("settings.toml");
+    let controller = FakeController {
+        fail: true,
+        ..FakeController::default()
+    };
+
+    let err = control::apply(&controller, &path, "Arctis 7", Setting::Lights(true)).unwrap_err();
+    assert_eq!(err.to_string(), "cannot apply setting: headset unplugged");
+    assert!(!path.exists());
+}
+
+#[test]
+fn settings_are_described_for_people() {
+    let mut applied = AppliedSettings::default();
+    applied.record(Setting::Lights(true));
+    applied.record(Setting::InactiveTime(0));
+    let described: Vec<String> = applied.settings().iter().map(ToString::to_string).collect();
+    assert_eq!(described, ["lights on", "inactive time off"]);
+    assert_eq!(Setting::InactiveTime(15).to_string(), "inactive time 15m");
+}
+
+#[test]
+fn inactive_time_is_shortened_while_low_and_restored_once_charged() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("settings.toml");
+    let controller = FakeController::default();
+    let mut monitor = power_saving(&controller, &path);
+
+    for (status, level) in [
+        (BatteryStatus::Discharging, 50),
+        (BatteryStatus::Discharging, 9),
+        (BatteryStatus::Discharging, 8),
+        (BatteryStatus::Charging, 60),
+        (BatteryStatus::Charging, 100),
+    ] {
+        monitor.update_readings(vec![arctis(status, level)]);
+        if level == 8 {
+            assert_eq!(monitor.settings.get("Arctis 7").inactive_time, Some(5));
+        }
+    }
+
+    assert_eq!(
+        controller.sent(),
+        [
+            ("Arctis 7".to_string(), Setting::InactiveTime(5)),
+            ("Arctis 7".to_string(), Setting::InactiveTime(30)),
+        ]
+    );
+    assert_eq!(monitor.settings.get("Arctis 7").inactive_time, Some(30));
+    assert_eq!(SettingsStore::load(&path).unwrap(), monitor.settings);
+}
+
+#[test]
+fn inactive_time_is_restored_after_a_restart() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("settings.toml");
+    let controller = FakeController::default();
+    let mut monitor = power_saving(&controller, &path);
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging, 50)]);
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging, 8)]);
+
+    let mut monitor = power_saving(&controller, &path);
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging, 8)]);
+    monitor.update_readings(vec![arctis(BatteryStatus::Charging, 100)]);
+    assert_eq!(
+        controller.sent(),
+        [
+            ("Arctis 7".to_string(), Setting::InactiveTime(5)),
+            ("Arctis 7".to_string(), Setting::InactiveTime(30)),
+        ]
+    );
+}
+
+#[test]
+fn devices_without_an_inactive_time_are_left_alone() {
+    let dir = TempDir::new().unwrap();
+    let controller = FakeController::default();
+    let mut monitor = power_saving(&controller, &dir.path().join("settings.toml"));
+    let mouse = |level| Device {
+        name: "G502".to_string(),
+        ..arctis(BatteryStatus::Discharging, level)
+    };
+    monitor.update_readings(vec![mouse(50)]);
+    monitor.update_readings(vec![mouse(5)]);
+    assert!(controller.sent().is_empty());
+}
+
+#[test]
+fn power_saving_is_off_by_default() {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let controller = FakeController::default();
+    monitor.subscribe(PowerSaving::new(
+        controller.clone(),
+        dir.path().join("settings.toml"),
+    ));
+
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging, 50)]);
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging, 5)]);
+    assert!(controller.sent().is_empty());
+}
@@ -0,0 +1,68 @@
+//! Firmware update notifications
+
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use hsc_core::{
+    clock::ManualClock,
+    config::{Config, FirmwareConfig},
+    firmware::is_newer,
+    Monitor,
+};
+
+use common::Recorder;
+
+/// Monitor knowing 1.10 as the latest firmware of the G502, checking it if `enabled`
+fn monitor(enabled: bool) -> (Monitor, Recorder) {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        firmware: FirmwareConfig {
+            enabled,
+            latest: BTreeMap::from([("G502".to_string(), "1.10".to_string())]),
+        },
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let recorder = Recorder::default();
+    monitor.add_sink(recorder.clone());
+    (monitor, recorder)
+}
+
+#[test]
+fn versions_compare_by_number() {
+    assert!(is_newer("1.10", "1.9"));
+    assert!(is_newer("v2.0", "1.99.3"));
+    assert!(is_newer("1.0.1", "1.0"));
+    assert!(!is_newer("1.0", "1.0.0"));
+    assert!(!is_newer("1.9", "1.10"));
+    assert!(is_newer("2.0b", "2.0a"));
+}
+
+#[test]
+fn update_is_announced_once() {
+    let (mut monitor, recorder) = monitor(true);
+    monitor.set_firmware("G502", "1.9");
+    monitor.set_firmware("G502", "1.9");
+    assert_eq!(
+        recorder.take_messages(),
+        ["Firmware update available: 1.10 (installed 1.9)"]
+    );
+    assert_eq!(monitor.firmware["G502"], "1.9");
+}
+
+#[test]
+fn current_or_unlisted_firmware_is_not_announced() {
+    let (mut monitor, recorder) = monitor(true);
+    monitor.set_firmware("G502", "1.10");
+    monitor.set_firmware("Arctis 7", "0.1");
+    assert!(recorder.take_messages().is_empty());
+}
+
+#[test]
+fn checks_are_opt_in() {
+    let (mut monitor, recorder) = monitor(false);
+    monitor.set_firmware("G502", "1.9");
+    assert!(recorder.take_messages().is_empty());
+}
@@ -0,0 +1,131 @@
+//! Devices running low in the same poll announced with one notification
+
+mod common;
+
+use std::sync::Arc;
+
+use hsc_core::{
+    clock::ManualClock,
+    config::{Config, DeviceRule, NotificationClass, NotificationsConfig},
+    Monitor,
+};
+
+use common::{reading, Recorder};
+
+/// Messages delivered after the Arctis 7 and G502 both drop under the low threshold in one poll
+fn run(config: Config) -> (Monitor, Vec<(String, String, String)>) {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        notification_step: 50,
+        ..config
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let recorder = Recorder::default();
+    monitor.add_sink(recorder.clone());
+    monitor.update_readings(vec![reading("Arctis 7", 11), reading("G502", 14)]);
+    recorder.take_all();
+    monitor.update_readings(vec![reading("Arctis 7", 9), reading("G502", 8)]);
+    let sent = recorder.take_all();
+    (monitor, sent)
+}
+
+#[test]
+fn devices_low_together_share_one_notification() {
+    let mut config = Config::default();
+    config.devices.insert(
+        "G502".to_string(),
+        DeviceRule {
+            alias: Some("Mouse".to_string()),
+            ..DeviceRule::default()
+        },
+    );
+    let (monitor, sent) = run(config);
+    let message = "2 devices low: Arctis 7 (9%), Mouse (8%)";
+    assert_eq!(
+        sent,
+        [(
+            "Arctis 7, G502".to_string(),
+            "Low battery".to_string(),
+            message.to_string()
+        )]
+    );
+    // Each device still has the alert in its recent events
+    let recent: Vec<_> = monitor
+        .events
+        .iter()
+        .rev()
+        .take(2)
+        .map(|e| (e.device.as_str(), e.message.as_str()))
+        .collect();
+    assert_eq!(recent, [("G502", message), ("Arctis 7", message)]);
+}
+
+#[test]
+fn grouping_can_be_turned_off() {
+    let (_, sent) = run(Config {
+        group_low_alerts: false,
+        ..Config::default()
+    });
+    let messages: Vec<_> = sent.iter().map(|(_, _, m)| m.as_str()).collect();
+    assert_eq!(messages, ["Battery level low: 9%", "Battery level low: 8%"]);
+}
+
+#[test]
+fn muted_devices_are_left_out_of_the_group() {
+    let mut config = Config::default();
+    config.devices.insert(
+        "G502".to_string(),
+        DeviceRule {
+            muted: true,
+            ..DeviceRule::default()
+        },
+    );
+    let (_, sent) = run(config);
+    assert_eq!(
+        sent,
+        [(
+            "Arctis 7".to_string(),
+            "Arctis 7".to_string(),
+            "Battery level low: 9%".to_string()
+        )]
+    );
+}
+
+#[test]
+fn low_alerts_are_filed_under_their_class() {
+    let mut config = Config {
+        app_name: Some("Headset".to_string()),
+        notifications: NotificationsConfig {
+            low: NotificationClass {
+                app_name: None,
+                group: Some("low-battery".to_string()),
+            },
+        },
+        ..Config::default()
+    };
+    config.devices.insert(
+        "G502".to_string(),
+        DeviceRule {
+            app_name: Some("Mouse".to_string()),
+            group: Some("mouse".to_string()),
+            ..DeviceRule::default()
+        },
+    );
+
+    let grouped = config.grouped_notification_options("battery-low");
+    assert_eq!(
+        (grouped.app_name, grouped.group),
+        (Some("Headset"), Some("low-battery"))
+    );
+    let low = config.notification_options("G502", "battery-low");
+    assert_eq!(
+        (low.app_name, low.group),
+        (Some("Mouse"), Some("low-battery"))
+    );
+    let routine = config.notification_options("G502", "battery");
+    assert_eq!(
+        (routine.app_name, routine.group),
+        (Some("Mouse"), Some("mouse"))
+    );
+}
@@ -0,0 +1,98 @@
+//! Telling headsets that turned themselves off while idle from those losing their connection
+
+use std::{sync::Arc, time::Duration};
+
+use hsc_core::{
+    audio::{AudioNode, NodeKind},
+    clock::ManualClock,
+    config::Config,
+    control::Setting,
+    device::{BatteryReading, BatteryStatus, Device},
+    extras::{Extras, CAPABILITY_INACTIVE_TIME},
+    Monitor,
+};
+
+/// A reading of the Arctis 7
+fn arctis(status: BatteryStatus) -> Device {
+    Device {
+        name: "Arctis 7".to_string(),
+        battery_status: status,
+        battery: Some(BatteryReading::Percent(60)),
+        last_notif_battery_level: None,
+    }
+}
+
+/// The Arctis 7 as the default sink, `running` if audio is playing
+fn sink(running: bool) -> Vec<AudioNode> {
+    vec![AudioNode {
+        name: "alsa_output.usb-SteelSeries_Arctis_7".to_string(),
+        description: "Arctis 7 Game".to_string(),
+        kind: NodeKind::Sink,
+        running,
+    }]
+}
+
+/// Monitor on a virtual clock with the Arctis 7 connected and known to turn itself off
+fn monitor() -> (Monitor, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::default());
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, clock.clone());
+    monitor.set_extras(
+        "Arctis 7",
+        Extras {
+            chatmix: None,
+            capabilities: vec![CAPABILITY_INACTIVE_TIME.to_string()],
+        },
+    );
+    monitor.active_audio = Some(sink(false));
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging)]);
+    (monitor, clock)
+}
+
+/// Message of the notification sent when the Arctis 7 disconnects
+fn disconnect(monitor: &mut Monitor) -> String {
+    monitor.update_readings(vec![arctis(BatteryStatus::Disconnected)]);
+    monitor.events.back().unwrap().message.clone()
+}
+
+#[test]
+fn idle_headset_turning_off_is_reported_as_such() {
+    let (mut monitor, clock) = monitor();
+    clock.advance(Duration::from_secs(10 * 60));
+    assert_eq!(disconnect(&mut monitor), "Headset turned off (idle)");
+}
+
+#[test]
+fn headset_playing_audio_lost_its_connection() {
+    let (mut monitor, clock) = monitor();
+    clock.advance(Duration::from_secs(10 * 60));
+    monitor.active_audio = Some(sink(true));
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging)]);
+    assert_eq!(disconnect(&mut monitor), "Device disconnected");
+}
+
+#[test]
+fn applied_inactive_time_must_have_passed() {
+    let (mut monitor, clock) = monitor();
+    monitor
+        .settings
+        .record("Arctis 7", Setting::InactiveTime(30));
+    clock.advance(Duration::from_secs(10 * 60));
+    assert_eq!(disconnect(&mut monitor), "Device disconnected");
+
+    monitor.update_readings(vec![arctis(BatteryStatus::Discharging)]);
+    clock.advance(Duration::from_secs(30 * 60));
+    assert_eq!(disconnect(&mut monitor), "Headset turned off (idle)");
+}
+
+#[test]
+fn unknown_usage_is_a_plain_disconnect() {
+    let (mut monitor, clock) = monitor();
+    monitor.active_audio = None;
+    clock.advance(Duration::from_secs(10 * 60));
+    assert_eq!(disconnect(&mut monitor), "Device disconnected");
+}
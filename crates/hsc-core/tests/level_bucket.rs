@@ -0,0 +1,90 @@
+//! Rounding battery levels into buckets to hide jitter
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use hsc_core::{
+    clock::ManualClock,
+    config::{Config, DeviceRule},
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent},
+    Monitor,
+};
+
+/// A discharging Arctis 7 at `level` percent
+fn arctis(level: u8) -> Device {
+    Device {
+        name: "Arctis 7".to_string(),
+        battery_status: BatteryStatus::Discharging,
+        battery: Some(BatteryReading::Percent(level)),
+        last_notif_battery_level: None,
+    }
+}
+
+#[test]
+fn percentages_round_down_to_their_bucket() {
+    let bucketed = |percent, bucket| BatteryReading::Percent(percent).bucketed(bucket);
+    assert_eq!(bucketed(47, 5), BatteryReading::Percent(45));
+    assert_eq!(bucketed(45, 5), BatteryReading::Percent(45));
+    assert_eq!(bucketed(99, 10), BatteryReading::Percent(90));
+    assert_eq!(bucketed(100, 3), BatteryReading::Percent(100));
+    assert_eq!(bucketed(47, 1), BatteryReading::Percent(47));
+    assert_eq!(bucketed(47, 0), BatteryReading::Percent(47));
+
+    let level = BatteryReading::level(3, 4).unwrap();
+    assert_eq!(level.bucketed(5), level);
+}
+
+#[test]
+fn jitter_within_a_bucket_changes_nothing() {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        level_bucket: 5,
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    monitor.update_readings(vec![arctis(48)]);
+
+    let mut changes = Vec::new();
+    for level in [47, 48, 46, 49, 45] {
+        changes.extend(monitor.update_readings(vec![arctis(level)]).events);
+    }
+    assert!(changes.is_empty(), "{:?}", changes);
+
+    let update = monitor.update_readings(vec![arctis(44)]);
+    assert!(matches!(
+        update.events[..],
+        [
+            DeviceEvent::LevelChanged {
+                to: BatteryReading::Percent(40),
+                ..
+            },
+            ..
+        ]
+    ));
+    let messages: Vec<&str> = monitor.events.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "New device connected",
+            "Battery level: 45%",
+            "Battery level: 40%"
+        ]
+    );
+}
+
+#[test]
+fn device_rules_override_the_bucket() {
+    let config = Config {
+        level_bucket: 5,
+        devices: BTreeMap::from([(
+            "Arctis 7".to_string(),
+            DeviceRule {
+                level_bucket: Some(10),
+                ..DeviceRule::default()
+            },
+        )]),
+        ..Config::default()
+    };
+    assert_eq!(config.level_bucket_for("Arctis 7"), 10);
+    assert_eq!(config.level_bucket_for("G435"), 5);
+}
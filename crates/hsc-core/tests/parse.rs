@@ -0,0 +1,140 @@
+//! Parsing of real-world `headsetcontrol -b` outputs kept in `fixtures/`
+
+use hsc_core::device::{parse_device, parse_device_names, parse_devices, BatteryStatus};
+
+/// Name, status and level of a parsed device
+type Expected = (&'static str, BatteryStatus, Option<u8>);
+
+/// Fixture file name and the devices it should yield
+const FIXTURES: &[(&str, &str, &[Expected])] = &[
+    (
+        "discharging.txt",
+        include_str!("fixtures/discharging.txt"),
+        &[("SteelSeries Arctis 7", BatteryStatus::Discharging, Some(85))],
+    ),
+    (
+        "charging.txt",
+        include_str!("fixtures/charging.txt"),
+        &[(
+            "Logitech G533 Wireless Headset",
+            BatteryStatus::Charging,
+            Some(40),
+        )],
+    ),
+    (
+        "charging_no_level.txt",
+        include_str!("fixtures/charging_no_level.txt"),
+        &[(
+            "Logitech G533 Wireless Headset",
+            BatteryStatus::Charging,
+            None,
+        )],
+    ),
+    (
+        "full.txt",
+        include_str!("fixtures/full.txt"),
+        &[(
+            "SteelSeries Arctis Nova 7",
+            BatteryStatus::Discharging,
+            Some(100),
+        )],
+    ),
+    (
+        "unavailable.txt",
+        include_str!("fixtures/unavailable.txt"),
+        &[],
+    ),
+    (
+        "multiple.txt",
+        include_str!("fixtures/multiple.txt"),
+        &[
+            ("SteelSeries Arctis 7", BatteryStatus::Discharging, Some(62)),
+            (
+                "Corsair VOID Elite Wireless",
+                BatteryStatus::Charging,
+                Some(15),
+            ),
+        ],
+    ),
+    (
+        "multiple_one_unavailable.txt",
+        include_str!("fixtures/multiple_one_unavailable.txt"),
+        &[("SteelSeries Arctis 7", BatteryStatus::Discharging, Some(62))],
+    ),
+    ("no_device.txt", include_str!("fixtures/no_device.txt"), &[]),
+    ("hid_error.txt", include_str!("fixtures/hid_error.txt"), &[]),
+    ("empty.txt", include_str!("fixtures/empty.txt"), &[]),
+    (
+        "garbled_level.txt",
+        include_str!("fixtures/garbled_level.txt"),
+        &[(
+            "Logitech G PRO X Wireless",
+            BatteryStatus::Discharging,
+            None,
+        )],
+    ),
+    (
+        "crlf.txt",
+        include_str!("fixtures/crlf.txt"),
+        &[("SteelSeries Arctis 7", BatteryStatus::Discharging, Some(70))],
+    ),
+];
+
+#[test]
+fn fixtures() {
+    for (file, output, expected) in FIXTURES {
+        let parsed: Vec<_> = parse_devices(output)
+            .into_iter()
+            .map(|d| (d.name.clone(), d.battery_status, d.level()))
+            .collect();
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(name, status, level)| (name.to_string(), status, level))
+            .collect();
+        assert_eq!(parsed, expected, "{}", file);
+    }
+}
+
+#[test]
+fn device_blocks() {
+    // Blocks as left by splitting an output on "Found"
+    let cases: &[(&str, Option<Expected>)] = &[
+        (
+            " Arctis 7!\nBattery:\n\tStatus: BATTERY_AVAILABLE\n\tLevel: 5%\n",
+            Some(("Arctis 7", BatteryStatus::Discharging, Some(5))),
+        ),
+        (
+            " Arctis 7 (2019 Edition)!\n\tStatus: BATTERY_CHARGING\n\tLevel: 0%\n",
+            Some(("Arctis 7", BatteryStatus::Charging, Some(0))),
+        ),
+        (
+            " Arctis 7!\n\tLevel: 50%\n",
+            Some(("Arctis 7", BatteryStatus::Disconnected, Some(50))),
+        ),
+        (
+            " Arctis 7!\n\tStatus: BATTERY_AVAILABLE\n\tLevel: 300%\n",
+            Some(("Arctis 7", BatteryStatus::Discharging, None)),
+        ),
+        (" Arctis 7!\n\tStatus: BATTERY_UNAVAILABLE\n", None),
+        ("\n\tStatus: BATTERY_AVAILABLE\n\tLevel: 50%\n", None),
+        (
+            "Arctis 7!\n\tStatus: BATTERY_AVAILABLE\n\tLevel: 50%\n",
+            None,
+        ),
+        ("", None),
+    ];
+    for (block, expected) in cases {
+        let parsed = parse_device(block).map(|d| (d.name.clone(), d.battery_status, d.level()));
+        let expected = expected.map(|(name, status, level)| (name.to_string(), status, level));
+        assert_eq!(parsed, expected, "{:?}", block);
+    }
+}
+
+#[test]
+fn device_names_include_headsets_without_a_battery_reading() {
+    assert_eq!(
+        parse_device_names(include_str!("fixtures/multiple_one_unavailable.txt")),
+        ["SteelSeries Arctis 7", "HyperX Cloud Flight"]
+    );
+    assert!(parse_device_names(include_str!("fixtures/no_device.txt")).is_empty());
+}
@@ -0,0 +1,148 @@
+//! Compatibility of the emitted JSON with the documents kept in `fixtures/schema/`
+//!
+//! A failure here means consumers would see a different document. Adding an optional field is
+//! fine within a schema version: update the fixture. Anything else needs a new `SCHEMA_VERSION`.
+
+use hsc_core::{
+    config::Config,
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent, Threshold},
+    schema::{
+        DeviceState, EventKind, Export, NotificationRecord, ReadingRecord, Status, StatusReport,
+        SCHEMA_VERSION,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Check that `value` serializes to `fixture` and that the fixture reads back as `value`
+fn assert_matches<T>(value: &T, fixture: &str)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let expected: Value = serde_json::from_str(fixture).unwrap();
+    assert_eq!(serde_json::to_value(value).unwrap(), expected);
+    assert_eq!(&serde_json::from_str::<T>(fixture).unwrap(), value);
+}
+
+fn device(name: &str, battery_status: BatteryStatus, battery: Option<BatteryReading>) -> Device {
+    Device {
+        name: name.to_string(),
+        battery_status,
+        battery,
+        last_notif_battery_level: None,
+    }
+}
+
+#[test]
+fn version_is_one() {
+    // Bump only together with the fixtures and a note for consumers
+    assert_eq!(SCHEMA_VERSION, 1);
+}
+
+#[test]
+fn status_report() {
+    let mut config = Config::default();
+    config
+        .devices
+        .entry("SteelSeries Arctis 7".to_string())
+        .or_default()
+        .alias = Some("Arctis".to_string());
+    let report = StatusReport::new(vec![
+        DeviceState::new(
+            &config,
+            &device(
+                "SteelSeries Arctis 7",
+                BatteryStatus::Discharging,
+                Some(BatteryReading::Percent(62)),
+            ),
+        ),
+        DeviceState::new(
+            &config,
+            &device("Corsair VOID Elite Wireless", BatteryStatus::Charging, None),
+        ),
+        DeviceState::new(
+            &config,
+            &device(
+                "Logitech G435",
+                BatteryStatus::Discharging,
+                BatteryReading::level(3, 4),
+            ),
+        ),
+    ]);
+    assert_matches(&report, include_str!("fixtures/schema/status_report.json"));
+}
+
+#[test]
+fn event_kinds() {
+    let name = || "Arctis".to_string();
+    let events: Vec<EventKind> = [
+        DeviceEvent::Connected {
+            name: name(),
+            battery: Some(BatteryReading::Percent(80)),
+        },
+        DeviceEvent::Disconnected { name: name() },
+        DeviceEvent::ChargingStarted { name: name() },
+        DeviceEvent::ChargingStopped { name: name() },
+        DeviceEvent::LevelChanged {
+            name: name(),
+            from: BatteryReading::Percent(80),
+            to: BatteryReading::Percent(79),
+        },
+        DeviceEvent::ThresholdCrossed {
+            name: name(),
+            battery: BatteryReading::Percent(9),
+            threshold: Threshold::Low,
+            charging: false,
+        },
+        DeviceEvent::Full { name: name() },
+    ]
+    .iter()
+    .map(EventKind::from)
+    .collect();
+    assert_matches(&events, include_str!("fixtures/schema/events.json"));
+}
+
+#[test]
+fn exports() {
+    let readings = Export {
+        schema_version: SCHEMA_VERSION,
+        readings: Some(vec![ReadingRecord {
+            timestamp: "2024-03-01T09:30:00+01:00".to_string(),
+            device: "SteelSeries Arctis 7".to_string(),
+            level: Some(62),
+            status: Status::Discharging,
+        }]),
+        notifications: None,
+    };
+    assert_matches(
+        &readings,
+        include_str!("fixtures/schema/export_readings.json"),
+    );
+
+    let notifications = Export {
+        schema_version: SCHEMA_VERSION,
+        readings: None,
+        notifications: Some(vec![NotificationRecord {
+            timestamp: "2024-03-01T09:30:00+01:00".to_string(),
+            device: "SteelSeries Arctis 7".to_string(),
+            message: "Battery level low: 9%".to_string(),
+        }]),
+    };
+    assert_matches(
+        &notifications,
+        include_str!("fixtures/schema/export_notifications.json"),
+    );
+}
+
+#[test]
+fn unknown_fields_are_ignored() {
+    // Documents from a newer release of the same version may carry extra optional fields
+    let report: StatusReport = serde_json::from_str(
+        r#"{"schema_version": 1, "uptime": 12, "devices": [
+            {"name": "Mouse", "display_name": "Mouse", "status": "charging", "level": 5,
+             "health": 0.9}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(report.devices[0].status, Status::Charging);
+}
@@ -0,0 +1,65 @@
+//! Notifications held back while the screen is shared
+
+mod common;
+
+use std::sync::Arc;
+
+use hsc_core::{clock::ManualClock, config::Config, Monitor};
+
+use common::{reading, Recorder};
+
+/// Monitor that already saw the Arctis 7 at 50% and the screen being shared
+fn sharing(hold: bool) -> (Monitor, Recorder) {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        hold_while_screen_sharing: hold,
+        ..Config::default()
+    };
+    let mut monitor = Monitor::with_clock(config, Arc::new(ManualClock::default()));
+    let recorder = Recorder::default();
+    monitor.add_sink(recorder.clone());
+    monitor.update_readings(vec![reading("Arctis 7", 50)]);
+    recorder.take();
+    monitor.set_screen_sharing(true);
+    (monitor, recorder)
+}
+
+#[test]
+fn latest_notification_is_sent_when_sharing_ends() {
+    let (mut monitor, recorder) = sharing(true);
+    monitor.update_readings(vec![reading("Arctis 7", 45)]);
+    monitor.update_readings(vec![reading("Arctis 7", 40)]);
+    assert!(recorder.take().is_empty());
+    assert!(monitor
+        .events
+        .iter()
+        .all(|e| e.message != "Battery level: 40%"));
+
+    monitor.set_screen_sharing(false);
+    assert_eq!(
+        recorder.take(),
+        [("Arctis 7".to_string(), "Battery level: 40%".to_string())]
+    );
+    monitor.set_screen_sharing(false);
+    assert!(recorder.take().is_empty());
+}
+
+#[test]
+fn low_battery_alerts_are_not_held() {
+    let (mut monitor, recorder) = sharing(true);
+    monitor.update_readings(vec![reading("Arctis 7", 8)]);
+    let sent = recorder.take();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].1.starts_with("Battery level low: 8%"));
+}
+
+#[test]
+fn notifications_are_not_held_unless_enabled() {
+    let (mut monitor, recorder) = sharing(false);
+    monitor.update_readings(vec![reading("Arctis 7", 45)]);
+    assert_eq!(
+        recorder.take(),
+        [("Arctis 7".to_string(), "Battery level: 45%".to_string())]
+    );
+}
@@ -0,0 +1,76 @@
+//! Reminding to charge devices running low when the computer shuts down
+
+use std::sync::Arc;
+
+use hsc_core::{
+    clock::ManualClock,
+    config::{Config, DeviceRule},
+    device::{BatteryReading, BatteryStatus, Device},
+    Monitor,
+};
+
+/// A reading of `name`
+fn reading(name: &str, status: BatteryStatus, percent: u8) -> Device {
+    Device {
+        name: name.to_string(),
+        battery_status: status,
+        battery: Some(BatteryReading::Percent(percent)),
+        last_notif_battery_level: None,
+    }
+}
+
+/// Monitor with notifications only recorded, reminding below 50%
+fn monitor(config: Config) -> Monitor {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..config
+    };
+    Monitor::with_clock(config, Arc::new(ManualClock::default()))
+}
+
+#[test]
+fn only_discharging_devices_below_the_level_are_reminded() {
+    let mut monitor = monitor(Config::default());
+    monitor.update_readings(vec![
+        reading("Arctis 7", BatteryStatus::Discharging, 35),
+        reading("G435", BatteryStatus::Discharging, 80),
+        reading("G PRO", BatteryStatus::Charging, 20),
+        reading("Cloud II", BatteryStatus::Disconnected, 10),
+    ]);
+    let before = monitor.events.len();
+
+    assert_eq!(monitor.remind_to_charge(), ["Arctis 7"]);
+    let reminders: Vec<_> = monitor.events.iter().skip(before).collect();
+    assert_eq!(reminders.len(), 1);
+    assert_eq!(reminders[0].device, "Arctis 7");
+    assert_eq!(
+        reminders[0].message,
+        "Charge your headset overnight, battery at 35%"
+    );
+}
+
+#[test]
+fn reminder_level_is_configurable() {
+    let mut config = Config::default();
+    config.shutdown_reminder.below = 20;
+    let mut monitor = monitor(config);
+    monitor.update_readings(vec![reading("Arctis 7", BatteryStatus::Discharging, 35)]);
+    assert!(monitor.remind_to_charge().is_empty());
+}
+
+#[test]
+fn muted_devices_are_reminded_silently() {
+    let mut config = Config::default();
+    config.devices.insert(
+        "Arctis 7".to_string(),
+        DeviceRule {
+            muted: true,
+            ..DeviceRule::default()
+        },
+    );
+    let mut monitor = monitor(config);
+    monitor.update_readings(vec![reading("Arctis 7", BatteryStatus::Discharging, 35)]);
+    monitor.remind_to_charge();
+    assert!(monitor.events.back().unwrap().suppressed);
+}
@@ -0,0 +1,80 @@
+//! Device state saved by one run and restored by the next
+
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use hsc_core::{
+    clock::ManualClock,
+    config::Config,
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent},
+    snapshot::Snapshot,
+    Monitor,
+};
+
+/// Monitor that does not send notifications anywhere
+fn monitor() -> Monitor {
+    let config = Config {
+        debug: false,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    Monitor::with_clock(config, Arc::new(ManualClock::default()))
+}
+
+/// A discharging reading of `name`
+fn reading(name: &str, battery: BatteryReading) -> Device {
+    Device {
+        name: name.to_string(),
+        battery_status: BatteryStatus::Discharging,
+        battery: Some(battery),
+        last_notif_battery_level: None,
+    }
+}
+
+#[test]
+fn restored_monitor_continues_where_the_saved_one_stopped() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("state.toml");
+    let mut first = monitor();
+    first.update_readings(vec![
+        reading("Arctis 7", BatteryReading::Percent(50)),
+        reading("G502", BatteryReading::Level { current: 3, max: 4 }),
+    ]);
+    Snapshot::of(&first).save(&path).unwrap();
+
+    let snapshot = Snapshot::load(&path).unwrap();
+    assert_eq!(snapshot, Snapshot::of(&first));
+    let mut second = monitor();
+    snapshot.restore(&mut second);
+    assert_eq!(
+        second.devices["G502"].battery,
+        Some(BatteryReading::Level { current: 3, max: 4 })
+    );
+    assert_eq!(
+        second.devices["Arctis 7"].last_notif_battery_level,
+        Some(50)
+    );
+
+    let update = second.update_readings(vec![
+        reading("Arctis 7", BatteryReading::Percent(50)),
+        reading("G502", BatteryReading::Level { current: 3, max: 4 }),
+    ]);
+    assert!(update.events.is_empty(), "{:?}", update.events);
+    let update = second.update_readings(vec![reading("Arctis 7", BatteryReading::Percent(45))]);
+    assert!(
+        update
+            .events
+            .iter()
+            .any(|e| matches!(e, DeviceEvent::LevelChanged { .. })),
+        "{:?}",
+        update.events
+    );
+}
+
+#[test]
+fn missing_snapshot_is_empty() {
+    let dir = TempDir::new().unwrap();
+    let snapshot = Snapshot::load(&dir.path().join("state.toml")).unwrap();
+    assert!(snapshot.devices.is_empty());
+}
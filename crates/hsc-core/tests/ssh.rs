@@ -0,0 +1,56 @@
+//! Running programs on another machine over SSH
+
+use std::io;
+
+use hsc_core::command::{CommandOutput, CommandRunner, FakeRunner, SshRunner};
+
+/// Runner sending commands to `htpc` through a fake `ssh`
+fn ssh(runner: FakeRunner) -> SshRunner<FakeRunner> {
+    SshRunner {
+        destination: "me@htpc".to_string(),
+        runner,
+    }
+}
+
+#[test]
+fn command_is_quoted_for_the_remote_shell() {
+    let remote = ssh(FakeRunner::with_output(CommandOutput::success(
+        "Found Arctis 7!\n",
+    )));
+    let output = remote.run("headsetcontrol", &["-b", "it's"]).unwrap();
+    assert_eq!(output.stdout, "Found Arctis 7!\n");
+    assert_eq!(
+        remote.runner.calls(),
+        [[
+            "ssh",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            "me@htpc",
+            "--",
+            r"'headsetcontrol' '-b' 'it'\''s'",
+        ]]
+    );
+}
+
+#[test]
+fn remote_exit_code_is_passed_on() {
+    let remote = ssh(FakeRunner::with_output(CommandOutput {
+        code: Some(1),
+        stdout: "No supported device found\n".to_string(),
+    }));
+    let output = remote.run("headsetcontrol", &["-b"]).unwrap();
+    assert_eq!(output.code, Some(1));
+}
+
+#[test]
+fn connection_failure_is_an_error() {
+    let remote = ssh(FakeRunner::with_output(CommandOutput {
+        code: Some(255),
+        stdout: String::new(),
+    }));
+    let error = remote.run("headsetcontrol", &["-b"]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Other);
+    assert_eq!(error.to_string(), "cannot connect to me@htpc over SSH");
+}
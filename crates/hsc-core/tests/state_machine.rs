@@ -0,0 +1,187 @@
+//! Invariants of the monitor's event stream over random sequences of battery readings
+
+use std::sync::{Arc, Mutex};
+
+use proptest::prelude::*;
+
+use hsc_core::{
+    config::Config,
+    device::{DeviceEvent, Threshold},
+    Monitor,
+};
+
+/// Name of the simulated device
+const DEVICE: &str = "Arctis 7";
+
+/// What `headsetcontrol -b` reports in one poll
+#[derive(Clone, Copy, Debug)]
+enum Reading {
+    Charging(u8),
+    Discharging(u8),
+    /// Reported, but without a usable battery
+    Unavailable(u8),
+    /// Not reported at all
+    Missing,
+}
+
+impl Reading {
+    /// The reading as `headsetcontrol -b` output
+    fn output(self) -> String {
+        let (status, level) = match self {
+            Reading::Charging(level) => ("BATTERY_CHARGING", level),
+            Reading::Discharging(level) => ("BATTERY_AVAILABLE", level),
+            Reading::Unavailable(level) => ("BATTERY_UNAVAILABLE", level),
+            Reading::Missing => return String::new(),
+        };
+        format!(
+            "Found {}!\n\nBattery:\n\tStatus: {}\n\tLevel: {}%\n",
+            DEVICE, status, level
+        )
+    }
+}
+
+/// Readings following a random walk of the battery level
+fn readings() -> impl Strategy<Value = Vec<Reading>> {
+    (0..=100u8, prop::collection::vec((0..4u8, -4..=4i8), 1..80)).prop_map(|(start, steps)| {
+        let mut level = start;
+        steps
+            .into_iter()
+            .map(|(kind, delta)| {
+                level = level.saturating_add_signed(delta).min(100);
+                match kind {
+                    0 => Reading::Charging(level),
+                    1 => Reading::Discharging(level),
+                    2 => Reading::Unavailable(level),
+                    _ => Reading::Missing,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Feed `readings` to a monitor, returning every event published and the monitor
+fn run(readings: &[Reading], step: u8) -> (Vec<DeviceEvent>, Monitor) {
+    let config = Config {
+        notification_step: step,
+        event_log_len: usize::MAX,
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::new(config);
+    // Paused notifications are still logged, without waiting between them
+    monitor.paused = true;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let log = events.clone();
+    monitor.subscribe(move |_: &mut Monitor, event: &DeviceEvent| {
+        log.lock().unwrap().push(event.clone());
+    });
+    for reading in readings {
+        monitor.update(&reading.output());
+    }
+    let events = events.lock().unwrap().clone();
+    (events, monitor)
+}
+
+proptest! {
+    #[test]
+    fn connects_and_disconnects_alternate(readings in readings(), step in 1..=25u8) {
+        let (events, _) = run(&readings, step);
+        let mut connected = false;
+        for event in &events {
+            match event {
+                DeviceEvent::Connected { .. } => {
+                    prop_assert!(!connected, "connected twice: {:?}", events);
+                    connected = true;
+                }
+                DeviceEvent::Disconnected { .. } => {
+                    prop_assert!(connected, "disconnected while not connected: {:?}", events);
+                    connected = false;
+                }
+                _ => prop_assert!(connected, "{:?} while disconnected", event),
+            }
+        }
+    }
+
+    #[test]
+    fn thresholds_are_not_repeated_without_recovery(readings in readings(), step in 1..=25u8) {
+        let (events, _) = run(&readings, step);
+        let mut last: Option<(u8, Threshold, bool)> = None;
+        for event in &events {
+            match *event {
+                DeviceEvent::ThresholdCrossed { battery, threshold, charging, .. } => {
+                    let crossing = (battery.percent(), threshold, charging);
+                    prop_assert_ne!(last, Some(crossing), "repeated in {:?}", events);
+                    last = Some(crossing);
+                }
+                // Moving back past the last crossing re-arms it
+                DeviceEvent::LevelChanged { to, .. }
+                    if last.is_some_and(|(battery, _, charging)| {
+                        if charging { to.percent() < battery } else { to.percent() > battery }
+                    }) =>
+                {
+                    last = None
+                }
+                DeviceEvent::Connected { .. } | DeviceEvent::Disconnected { .. } => last = None,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn crossings_follow_level_changes(readings in readings(), step in 1..=25u8) {
+        let (events, _) = run(&readings, step);
+        for pair in events.windows(2) {
+            match (&pair[0], &pair[1]) {
+                (
+                    DeviceEvent::LevelChanged { from, to, .. },
+                    DeviceEvent::ThresholdCrossed { battery, charging, .. },
+                ) => {
+                    prop_assert_eq!(to, battery);
+                    prop_assert_eq!(*charging, to.percent() > from.percent());
+                }
+                (DeviceEvent::LevelChanged { from, to, .. }, DeviceEvent::Full { .. }) => {
+                    prop_assert_eq!(to.percent(), 100);
+                    prop_assert!(from.percent() < to.percent());
+                }
+                (_, DeviceEvent::ThresholdCrossed { .. } | DeviceEvent::Full { .. }) => {
+                    prop_assert!(false, "crossing without a level change: {:?}", pair);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn one_notification_per_event(readings in readings(), step in 1..=25u8) {
+        let (events, monitor) = run(&readings, step);
+        let expected: usize = events
+            .iter()
+            .map(|event| match event {
+                DeviceEvent::Connected { battery: Some(_), .. } => 2,
+                DeviceEvent::Connected { battery: None, .. }
+                | DeviceEvent::Disconnected { .. }
+                | DeviceEvent::ChargingStarted { .. }
+                | DeviceEvent::ChargingStopped { .. }
+                | DeviceEvent::ThresholdCrossed { .. }
+                | DeviceEvent::Full { .. } => 1,
+                _ => 0,
+            })
+            .sum();
+        prop_assert_eq!(monitor.events.len(), expected);
+        prop_assert!(monitor.events.iter().all(|e| e.suppressed && e.device == DEVICE));
+    }
+
+    #[test]
+    fn low_crossings_match_threshold(readings in readings()) {
+        let (events, monitor) = run(&readings, 1);
+        let threshold = monitor.config.battery_threshold;
+        for event in &events {
+            if let DeviceEvent::ThresholdCrossed { battery, threshold: kind, charging: false, .. } =
+                *event
+            {
+                prop_assert_eq!(kind == Threshold::Low, battery.percent() < threshold);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Broadcasting alerts to terminals
+
+use std::path::PathBuf;
+
+use hsc_core::{
+    command::{CommandOutput, FakeRunner},
+    config::{Config, DeviceRule},
+    error::NotifyError,
+    notify::{broadcast, is_critical},
+    platform::{Platform, PlatformCommand},
+};
+
+/// A system without a terminal to fall back to
+struct Headless;
+
+impl Platform for Headless {
+    fn config_dir(&self) -> PathBuf {
+        PathBuf::new()
+    }
+
+    fn data_dir(&self) -> PathBuf {
+        PathBuf::new()
+    }
+
+    fn notification(&self, _title: &str, _body: &str, _icon: &str) -> PlatformCommand {
+        unreachable!("alerts are not shown on the desktop")
+    }
+}
+
+#[test]
+fn alert_is_piped_to_wall() {
+    let runner = FakeRunner::default();
+    broadcast(&runner, &Headless, "Arctis 7", "Battery level low: 9%").unwrap();
+    assert_eq!(runner.calls(), [["wall"]]);
+    assert_eq!(runner.inputs(), ["Arctis 7: Battery level low: 9%\n"]);
+}
+
+#[test]
+fn wall_failure_is_reported_without_a_terminal() {
+    let runner = FakeRunner::with_output(CommandOutput {
+        code: Some(1),
+        stdout: String::new(),
+    });
+    assert!(matches!(
+        broadcast(&runner, &Headless, "Arctis 7", "Battery level low: 9%"),
+        Err(NotifyError::Exit { code: Some(1), .. })
+    ));
+}
+
+#[test]
+fn only_low_battery_alerts_are_critical() {
+    assert!(is_critical("battery-low"));
+    assert!(!is_critical("battery"));
+    assert!(!is_critical("battery-caution"));
+}
+
+#[test]
+fn device_icons_do_not_change_which_alerts_are_critical() {
+    let mut config = Config::default();
+    for (name, icon) in [("Arctis 7", "audio-headset"), ("G502", "battery-low")] {
+        config.devices.insert(
+            name.to_string(),
+            DeviceRule {
+                icon: Some(icon.to_string()),
+                ..DeviceRule::default()
+            },
+        );
+    }
+    assert!(
+        config
+            .notification_options("Arctis 7", "battery-low")
+            .critical
+    );
+    assert!(!config.notification_options("G502", "battery").critical);
+}
@@ -0,0 +1,80 @@
+/* C interface to the headsetcontrol-notify battery monitor, see crates/hsc-ffi/src/lib.rs */
+
+#ifndef HSC_H
+#define HSC_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum hsc_status {
+    HSC_STATUS_CHARGING,
+    HSC_STATUS_DISCHARGING,
+    HSC_STATUS_DISCONNECTED,
+} hsc_status;
+
+typedef enum hsc_event_kind {
+    HSC_EVENT_CONNECTED,
+    HSC_EVENT_DISCONNECTED,
+    HSC_EVENT_CHARGING_STARTED,
+    HSC_EVENT_CHARGING_STOPPED,
+    HSC_EVENT_LEVEL_CHANGED,
+    HSC_EVENT_THRESHOLD_CROSSED,
+    HSC_EVENT_FULL,
+} hsc_event_kind;
+
+typedef enum hsc_threshold {
+    HSC_THRESHOLD_NONE,
+    HSC_THRESHOLD_LOW,
+    HSC_THRESHOLD_STEP,
+} hsc_threshold;
+
+typedef struct hsc_device {
+    const char *name;         /* name reported by the device */
+    const char *display_name; /* name used in notifications */
+    hsc_status status;
+    int level;                /* battery percentage, -1 if unknown */
+} hsc_device;
+
+/* Valid only for the duration of the callback */
+typedef struct hsc_event {
+    hsc_event_kind kind;
+    const char *device;
+    int from;                 /* previous level of a level change, otherwise -1 */
+    int to;                   /* new level of a connection, level change or threshold crossing */
+    hsc_threshold threshold;
+    bool charging;            /* charging when the threshold was crossed */
+} hsc_event;
+
+typedef struct hsc_monitor hsc_monitor;
+
+typedef void (*hsc_event_callback)(const hsc_event *event, void *user_data);
+
+/* Create a monitor from a configuration file, or the default one if config_path is NULL.
+ * Returns NULL if the configuration cannot be read. */
+hsc_monitor *hsc_monitor_new(const char *config_path);
+
+void hsc_monitor_free(hsc_monitor *monitor);
+
+/* Run headsetcontrol and update the monitor. Returns the number of events, or -1 if the
+ * monitor is NULL or headsetcontrol cannot be run. */
+int hsc_monitor_poll(hsc_monitor *monitor);
+
+/* Update the monitor with headsetcontrol output. Returns the number of events, -1 on error. */
+int hsc_monitor_update(hsc_monitor *monitor, const char *output);
+
+/* Call callback with user_data for every later event. Returns 0, or -1 on error. */
+int hsc_monitor_subscribe(hsc_monitor *monitor, hsc_event_callback callback, void *user_data);
+
+/* Copy up to capacity devices, sorted by name, into out and return the number known.
+ * Strings remain valid until the next call on the same monitor. */
+size_t hsc_monitor_devices(hsc_monitor *monitor, hsc_device *out, size_t capacity);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,317 @@
+//! C bindings for the battery monitor
+//!
+//! Builds `libhsc` as a shared and a static library so bars and widgets written in C, C++ or any
+//! language with a C FFI can embed the [`Monitor`]. The declarations are in `include/hsc.h`.
+//!
+//! A monitor is created from a configuration file and fed readings either by running
+//! headsetcontrol ([`hsc_monitor_poll`]) or from output the caller obtained itself
+//! ([`hsc_monitor_update`]). Events are delivered synchronously to callbacks registered with
+//! [`hsc_monitor_subscribe`], and [`hsc_monitor_devices`] copies out the current device states.
+//! Notifications are sent as the configuration says, exactly as the daemon would.
+//!
+//! A monitor must only be used from one thread at a time.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    path::Path,
+    ptr, slice,
+};
+
+use hsc_backends::headsetcontrol::get_headsetcontrol_output;
+use hsc_core::{
+    bus::Subscriber,
+    config::Config,
+    device::{BatteryReading, BatteryStatus, Device, DeviceEvent, Threshold},
+    monitor::Monitor,
+};
+
+/// Battery status of a device
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HscStatus {
+    Charging,
+    Discharging,
+    Disconnected,
+}
+
+/// Kind of an [`HscEvent`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HscEventKind {
+    Connected,
+    Disconnected,
+    ChargingStarted,
+    ChargingStopped,
+    LevelChanged,
+    ThresholdCrossed,
+    Full,
+}
+
+/// Threshold reported by a threshold crossing
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HscThreshold {
+    /// The event is not a threshold crossing
+    None,
+    Low,
+    Step,
+}
+
+/// Snapshot of a device, filled in by [`hsc_monitor_devices`]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct HscDevice {
+    /// Name reported by the device
+    pub name: *const c_char,
+    /// Name used in notifications
+    pub display_name: *const c_char,
+    pub status: HscStatus,
+    /// Battery percentage, or -1 if unknown
+    pub level: c_int,
+}
+
+/// A device event, valid only for the duration of the callback
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct HscEvent {
+    pub kind: HscEventKind,
+    /// Name of the device the event concerns
+    pub device: *const c_char,
+    /// Previous level of a level change, otherwise -1
+    pub from: c_int,
+    /// New level of a connection, level change or threshold crossing, -1 if unknown
+    pub to: c_int,
+    pub threshold: HscThreshold,
+    /// Whether the device was charging when it crossed the threshold
+    pub charging: bool,
+}
+
+/// Function called with every device event
+pub type HscEventCallback =
+    Option<unsafe extern "C" fn(event: *const HscEvent, user_data: *mut c_void)>;
+
+/// Opaque monitor handle
+pub struct HscMonitor {
+    monitor: Monitor,
+    /// Names handed out by the last [`hsc_monitor_devices`] call
+    names: Vec<(CString, CString)>,
+}
+
+/// A C callback subscribed to the monitor
+struct Callback {
+    callback: unsafe extern "C" fn(*const HscEvent, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// The caller promises to use a monitor from one thread at a time, so its callbacks are only ever
+// called from that thread.
+unsafe impl Send for Callback {}
+
+impl Subscriber for Callback {
+    fn handle(&mut self, _monitor: &mut Monitor, event: &DeviceEvent) {
+        let device = c_string(event.device());
+        let mut raw = HscEvent {
+            kind: HscEventKind::Connected,
+            device: device.as_ptr(),
+            from: -1,
+            to: -1,
+            threshold: HscThreshold::None,
+            charging: false,
+        };
+        match *event {
+            DeviceEvent::Connected { battery, .. } => {
+                raw.to = level(battery.map(BatteryReading::percent))
+            }
+            DeviceEvent::Disconnected { .. } => raw.kind = HscEventKind::Disconnected,
+            DeviceEvent::ChargingStarted { .. } => raw.kind = HscEventKind::ChargingStarted,
+            DeviceEvent::ChargingStopped { .. } => raw.kind = HscEventKind::ChargingStopped,
+            DeviceEvent::LevelChanged { from, to, .. } => {
+                raw.kind = HscEventKind::LevelChanged;
+                raw.from = from.percent().into();
+                raw.to = to.percent().into();
+            }
+            DeviceEvent::ThresholdCrossed {
+                battery,
+                threshold,
+                charging,
+                ..
+            } => {
+                raw.kind = HscEventKind::ThresholdCrossed;
+                raw.to = battery.percent().into();
+                raw.threshold = match threshold {
+                    Threshold::Low => HscThreshold::Low,
+                    Threshold::Step => HscThreshold::Step,
+                };
+                raw.charging = charging;
+            }
+            DeviceEvent::Full { .. } => raw.kind = HscEventKind::Full,
+        }
+        // SAFETY: the caller registered a valid function along with the data it expects
+        unsafe { (self.callback)(&raw, self.user_data) }
+    }
+}
+
+/// Create a monitor configured from the file at `config_path`, or from the default configuration
+/// file if it is NULL. Returns NULL if the configuration cannot be read.
+///
+/// # Safety
+///
+/// `config_path` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_new(config_path: *const c_char) -> *mut HscMonitor {
+    let path = if config_path.is_null() {
+        Config::default_path()
+    } else {
+        match CStr::from_ptr(config_path).to_str() {
+            Ok(path) => Path::new(path).to_path_buf(),
+            Err(_) => return ptr::null_mut(),
+        }
+    };
+    match Config::load(&path) {
+        Ok(config) => Box::into_raw(Box::new(HscMonitor {
+            monitor: Monitor::new(config),
+            names: Vec::new(),
+        })),
+        Err(e) => {
+            eprintln!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Destroy a monitor. Does nothing if `monitor` is NULL.
+///
+/// # Safety
+///
+/// `monitor` must be NULL or returned by [`hsc_monitor_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_free(monitor: *mut HscMonitor) {
+    if !monitor.is_null() {
+        drop(Box::from_raw(monitor));
+    }
+}
+
+/// Run headsetcontrol and update the monitor with its output. Returns the number of events
+/// raised, or -1 if `monitor` is NULL or headsetcontrol cannot be run.
+///
+/// # Safety
+///
+/// `monitor` must be NULL or a live monitor.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_poll(monitor: *mut HscMonitor) -> c_int {
+    let Some(monitor) = monitor.as_mut() else {
+        return -1;
+    };
+    match get_headsetcontrol_output() {
+        Ok(output) => count(monitor.monitor.update(&output).events.len()),
+        Err(e) => {
+            eprintln!("{}", e);
+            -1
+        }
+    }
+}
+
+/// Update the monitor with headsetcontrol output obtained by the caller. Returns the number of
+/// events raised, or -1 if an argument is NULL.
+///
+/// # Safety
+///
+/// `monitor` must be NULL or a live monitor, and `output` NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_update(
+    monitor: *mut HscMonitor,
+    output: *const c_char,
+) -> c_int {
+    let Some(monitor) = monitor.as_mut() else {
+        return -1;
+    };
+    if output.is_null() {
+        return -1;
+    }
+    let output = CStr::from_ptr(output).to_string_lossy();
+    count(monitor.monitor.update(&output).events.len())
+}
+
+/// Call `callback` with `user_data` for every event raised by later updates, after the
+/// subscribers already registered. Returns 0, or -1 if an argument is NULL.
+///
+/// # Safety
+///
+/// `monitor` must be NULL or a live monitor, and `callback` must remain safe to call with
+/// `user_data` for as long as the monitor lives.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_subscribe(
+    monitor: *mut HscMonitor,
+    callback: HscEventCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    let (Some(monitor), Some(callback)) = (monitor.as_mut(), callback) else {
+        return -1;
+    };
+    monitor.monitor.subscribe(Callback {
+        callback,
+        user_data,
+    });
+    0
+}
+
+/// Copy up to `capacity` devices, sorted by name, into `out` and return the number of devices
+/// known. Strings remain valid until the next call on the same monitor.
+///
+/// # Safety
+///
+/// `monitor` must be NULL or a live monitor, and `out` must point to `capacity` writable
+/// devices unless `capacity` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn hsc_monitor_devices(
+    monitor: *mut HscMonitor,
+    out: *mut HscDevice,
+    capacity: usize,
+) -> usize {
+    let Some(monitor) = monitor.as_mut() else {
+        return 0;
+    };
+    let mut devices: Vec<&Device> = monitor.monitor.devices.values().collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    monitor.names = devices
+        .iter()
+        .map(|d| {
+            let display_name = monitor.monitor.config.display_name(&d.name);
+            (c_string(&d.name), c_string(display_name))
+        })
+        .collect();
+    if capacity > 0 && !out.is_null() {
+        let out = slice::from_raw_parts_mut(out, capacity);
+        for ((slot, device), (name, display_name)) in
+            out.iter_mut().zip(&devices).zip(&monitor.names)
+        {
+            *slot = HscDevice {
+                name: name.as_ptr(),
+                display_name: display_name.as_ptr(),
+                status: match device.battery_status {
+                    BatteryStatus::Charging => HscStatus::Charging,
+                    BatteryStatus::Discharging => HscStatus::Discharging,
+                    BatteryStatus::Disconnected => HscStatus::Disconnected,
+                },
+                level: level(device.level()),
+            };
+        }
+    }
+    devices.len()
+}
+
+/// Convert a name, dropping anything after an interior NUL
+fn c_string(s: &str) -> CString {
+    let end = s.find('\0').unwrap_or(s.len());
+    CString::new(&s[..end]).unwrap_or_default()
+}
+
+/// Battery level, -1 meaning unknown
+fn level(battery: Option<u8>) -> c_int {
+    battery.map_or(-1, c_int::from)
+}
+
+/// Event count, saturating
+fn count(events: usize) -> c_int {
+    c_int::try_from(events).unwrap_or(c_int::MAX)
+}
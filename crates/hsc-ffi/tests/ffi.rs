@@ -0,0 +1,108 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    fs, ptr,
+};
+
+use hsc::*;
+
+/// Monitor printing its notifications instead of showing them
+fn monitor(dir: &tempfile::TempDir) -> *mut HscMonitor {
+    let path = dir.path().join("config.toml");
+    fs::write(&path, "sinks = [\"stdout\"]\n").unwrap();
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+    let monitor = unsafe { hsc_monitor_new(path.as_ptr()) };
+    assert!(!monitor.is_null());
+    monitor
+}
+
+unsafe extern "C" fn record(event: *const HscEvent, user_data: *mut c_void) {
+    let events = &mut *(user_data as *mut Vec<(HscEventKind, String, i32, i32)>);
+    let event = &*event;
+    let device = CStr::from_ptr(event.device).to_str().unwrap().to_string();
+    events.push((event.kind, device, event.from, event.to));
+}
+
+#[test]
+fn callbacks_receive_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let monitor = monitor(&dir);
+    let mut events: Vec<(HscEventKind, String, i32, i32)> = Vec::new();
+    let output = |level: u8| {
+        CString::new(format!(
+            "Found Logitech G533 Wireless Headset!\n\nBattery:\n\tStatus: BATTERY_AVAILABLE\n\tLevel: {}%\n",
+            level
+        ))
+        .unwrap()
+    };
+
+    unsafe {
+        assert_eq!(
+            hsc_monitor_subscribe(monitor, Some(record), &mut events as *mut _ as *mut c_void),
+            0
+        );
+        hsc_monitor_update(monitor, output(60).as_ptr());
+        assert_eq!(hsc_monitor_update(monitor, output(59).as_ptr()), 1);
+        hsc_monitor_free(monitor);
+    }
+
+    let name = "Logitech G533 Wireless Headset".to_string();
+    assert_eq!(
+        events,
+        [
+            (HscEventKind::Connected, name.clone(), -1, 60),
+            (HscEventKind::LevelChanged, name, 60, 59),
+        ]
+    );
+}
+
+#[test]
+fn devices_are_copied_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let monitor = monitor(&dir);
+    let output =
+        CString::new(fs::read_to_string("../hsc-core/tests/fixtures/multiple.txt").unwrap())
+            .unwrap();
+
+    unsafe {
+        hsc_monitor_update(monitor, output.as_ptr());
+        let total = hsc_monitor_devices(monitor, ptr::null_mut(), 0);
+        assert!(total >= 2);
+
+        let mut devices = vec![
+            HscDevice {
+                name: ptr::null(),
+                display_name: ptr::null(),
+                status: HscStatus::Disconnected,
+                level: -1,
+            };
+            total
+        ];
+        assert_eq!(
+            hsc_monitor_devices(monitor, devices.as_mut_ptr(), total),
+            total
+        );
+        let names: Vec<&str> = devices
+            .iter()
+            .map(|d| CStr::from_ptr(d.name).to_str().unwrap())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert!(devices.iter().all(|d| d.status != HscStatus::Disconnected));
+        hsc_monitor_free(monitor);
+    }
+}
+
+#[test]
+fn null_arguments_are_rejected() {
+    unsafe {
+        assert_eq!(hsc_monitor_poll(ptr::null_mut()), -1);
+        assert_eq!(hsc_monitor_update(ptr::null_mut(), ptr::null()), -1);
+        assert_eq!(
+            hsc_monitor_subscribe(ptr::null_mut(), None, ptr::null_mut()),
+            -1
+        );
+        assert_eq!(hsc_monitor_devices(ptr::null_mut(), ptr::null_mut(), 0), 0);
+        hsc_monitor_free(ptr::null_mut());
+    }
+}
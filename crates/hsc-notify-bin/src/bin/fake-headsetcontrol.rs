@@ -0,0 +1,70 @@
+//! Stand-in for `headsetcontrol` and `notify-send` used by the integration tests
+//!
+//! Invoked as `headsetcontrol -b`, prints the next block of the script named by
+//! `FAKE_HSC_SCRIPT`, where blocks are separated by lines containing only `---`. Once the script
+//! is exhausted the last block is repeated. The number of battery queries so far is kept in a
+//! `.count` file next to the script. Invoked as `headsetcontrol -o json`, does the same with the
+//! script named by `FAKE_HSC_JSON_SCRIPT`, printing nothing if it is unset. Other `headsetcontrol`
+//! options succeed without output.
+//!
+//! Invoked as `notify-send`, appends the notification to the file named by `FAKE_NOTIFY_LOG`,
+//! one line per notification with its arguments separated by tabs.
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+fn main() -> ExitCode {
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+    let args: Vec<String> = args.collect();
+    let name = Path::new(&program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+
+    let result = match name {
+        "notify-send" => notify_send(&args),
+        _ if args.first().is_some_and(|a| a == "-b") => scripted("FAKE_HSC_SCRIPT"),
+        _ if args == ["-o", "json"] => match env::var_os("FAKE_HSC_JSON_SCRIPT") {
+            Some(_) => scripted("FAKE_HSC_JSON_SCRIPT"),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print the next block of the script named by the environment variable `var`
+fn scripted(var: &str) -> io::Result<()> {
+    let script_path = env::var(var).map_err(io::Error::other)?;
+    let script = fs::read_to_string(&script_path)?;
+    let blocks: Vec<&str> = script.split("---\n").collect();
+
+    let count_path = format!("{}.count", script_path);
+    let count: usize = fs::read_to_string(&count_path)
+        .ok()
+        .and_then(|c| c.trim().parse().ok())
+        .unwrap_or(0);
+    print!("{}", blocks[count.min(blocks.len() - 1)]);
+    fs::write(&count_path, (count + 1).to_string())
+}
+
+/// Record a notification
+fn notify_send(args: &[String]) -> io::Result<()> {
+    let log_path = env::var("FAKE_NOTIFY_LOG").map_err(io::Error::other)?;
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    writeln!(log, "{}", args.join("\t"))
+}
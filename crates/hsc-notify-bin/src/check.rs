@@ -0,0 +1,156 @@
+//! Monitoring plugin for Nagios, Icinga and compatible systems
+//!
+//! `check` polls devices once and prints a single line in the monitoring plugin format, the
+//! overall state followed by a summary and performance data for every device:
+//!
+//! ```text
+//! HEADSET WARNING - Arctis 7 18% discharging, G502 80% charging | 'Arctis 7'=18%;20:;10:;0;100 'G502'=80%;20:;10:;0;100
+//! ```
+//!
+//! The exit code tells the state: 0 OK, 1 WARNING, 2 CRITICAL and 3 UNKNOWN. Charging devices
+//! are always OK, and disconnected ones have no level to compare so they are UNKNOWN, as is a
+//! critical threshold above the warning one, which would skip the warning state entirely.
+
+use hsc_backends::headsetcontrol::headsetcontrol_output;
+use hsc_core::{
+    command::HostRunner,
+    config::Config,
+    device::{parse_devices, BatteryStatus, Device},
+};
+
+/// Outcome of a check, ordered from best to worst
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckState {
+    Ok,
+    Unknown,
+    Warning,
+    Critical,
+}
+
+impl CheckState {
+    /// Exit code monitoring systems read the state from
+    pub fn code(self) -> i32 {
+        match self {
+            CheckState::Ok => 0,
+            CheckState::Warning => 1,
+            CheckState::Critical => 2,
+            CheckState::Unknown => 3,
+        }
+    }
+
+    /// State as printed at the start of the output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckState::Ok => "OK",
+            CheckState::Warning => "WARNING",
+            CheckState::Critical => "CRITICAL",
+            CheckState::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Battery levels in percent below which a discharging device is a problem
+#[derive(Clone, Copy, Debug)]
+pub struct Thresholds {
+    pub warning: u8,
+    pub critical: u8,
+}
+
+impl Thresholds {
+    /// Check that a device passes the warning threshold before the critical one
+    pub fn validate(self) -> Result<Self, String> {
+        if self.critical > self.warning {
+            return Err(format!(
+                "critical threshold {}% is above warning threshold {}%",
+                self.critical, self.warning
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Poll devices once, print the plugin output for every device, or only `device` (a name or
+/// alias), and return the state to exit with
+pub fn run(config: &Config, thresholds: Thresholds, device: Option<&str>) -> CheckState {
+    let polled = thresholds.validate().and_then(|thresholds| {
+        headsetcontrol_output(&HostRunner::new(config.remote.as_deref()))
+            .map(|output| (thresholds, output))
+            .map_err(|e| e.to_string())
+    });
+    let (state, output) = match polled {
+        Ok((thresholds, output)) => evaluate(config, &parse_devices(&output), thresholds, device),
+        Err(e) => (CheckState::Unknown, e),
+    };
+    println!("HEADSET {} - {}", state.as_str(), output);
+    state
+}
+
+/// Overall state of `devices`, or only `device` (a name or alias), with the text following it
+/// in the plugin output
+pub fn evaluate(
+    config: &Config,
+    devices: &[Device],
+    thresholds: Thresholds,
+    device: Option<&str>,
+) -> (CheckState, String) {
+    let name = device.map(|d| config.resolve_device(d));
+    let checked: Vec<&Device> = devices
+        .iter()
+        .filter(|d| name.is_none_or(|name| d.name == name))
+        .collect();
+    if checked.is_empty() {
+        return match device {
+            Some(device) => (CheckState::Critical, format!("{} not connected", device)),
+            None => (CheckState::Unknown, "no device connected".to_string()),
+        };
+    }
+
+    let mut state = CheckState::Ok;
+    let mut summary = Vec::new();
+    let mut perfdata = Vec::new();
+    for device in checked {
+        let display_name = config.display_name(&device.name);
+        state = state.max(device_state(device, thresholds));
+        let Some(battery) = device.battery else {
+            summary.push(format!(
+                "{} {}",
+                display_name,
+                device.battery_status.as_str()
+            ));
+            continue;
+        };
+        summary.push(format!(
+            "{} {} {}",
+            display_name,
+            battery,
+            device.battery_status.as_str()
+        ));
+        perfdata.push(format!(
+            "'{}'={}%;{}:;{}:;0;100",
+            display_name.replace('\'', "''"),
+            battery.percent(),
+            thresholds.warning,
+            thresholds.critical
+        ));
+    }
+    let mut output = summary.join(", ");
+    if !perfdata.is_empty() {
+        output = format!("{} | {}", output, perfdata.join(" "));
+    }
+    (state, output)
+}
+
+/// State of a single device
+fn device_state(device: &Device, thresholds: Thresholds) -> CheckState {
+    match (device.battery_status, device.battery) {
+        (BatteryStatus::Charging, _) => CheckState::Ok,
+        (BatteryStatus::Disconnected, _) | (_, None) => CheckState::Unknown,
+        (BatteryStatus::Discharging, Some(battery)) if battery.is_below(thresholds.critical) => {
+            CheckState::Critical
+        }
+        (BatteryStatus::Discharging, Some(battery)) if battery.is_below(thresholds.warning) => {
+            CheckState::Warning
+        }
+        (BatteryStatus::Discharging, Some(_)) => CheckState::Ok,
+    }
+}
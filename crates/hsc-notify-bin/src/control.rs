@@ -0,0 +1,42 @@
+//! Changing headset settings from the command line
+//!
+//! Settings are sent through headsetcontrol with [`control::apply`], the same path menu actions
+//! take, so the applied settings recorded per device stay complete.
+
+use std::error::Error;
+
+use hsc_backends::headsetcontrol::{headsetcontrol_output, HeadsetControl};
+use hsc_core::{
+    command::HostRunner,
+    config::Config,
+    control::{self, Setting, SettingsStore},
+    device::{parse_devices, BatteryStatus},
+};
+
+/// Name of the device a setting is for: `device` (a name or alias) if given, otherwise the first
+/// connected device
+pub fn target(config: &Config, device: Option<&str>) -> Result<String, Box<dyn Error>> {
+    if let Some(device) = device {
+        return Ok(config.resolve_device(device).to_string());
+    }
+    parse_devices(&headsetcontrol_output(&HostRunner::new(
+        config.remote.as_deref(),
+    ))?)
+    .into_iter()
+    .find(|d| d.battery_status != BatteryStatus::Disconnected)
+    .map(|d| d.name)
+    .ok_or_else(|| "no device connected".into())
+}
+
+/// Send `setting` to `device`, or the first connected device, and print what was applied
+pub fn set(config: &Config, device: Option<&str>, setting: Setting) -> Result<(), Box<dyn Error>> {
+    let name = target(config, device)?;
+    control::apply(
+        &HeadsetControl(HostRunner::new(config.remote.as_deref())),
+        &SettingsStore::default_path(),
+        &name,
+        setting,
+    )?;
+    println!("{}: {}", config.display_name(&name), setting);
+    Ok(())
+}
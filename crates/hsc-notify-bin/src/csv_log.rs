@@ -0,0 +1,99 @@
+//! Battery history logging to a CSV file
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+
+use hsc_core::device::Device;
+
+/// Header written to new files
+const HEADER: &str = "timestamp,device,level,status";
+
+/// Appends battery readings to a CSV file, flushing periodically
+pub struct CsvLogger {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    flush_interval: Duration,
+    last_flush: Instant,
+    /// Size after which the file is rotated
+    max_size: Option<u64>,
+}
+
+impl CsvLogger {
+    /// Open `path` for appending, writing a header if the file is new
+    pub fn open(path: &Path, flush_interval: Duration, max_size: Option<u64>) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(CsvLogger {
+            path: path.to_path_buf(),
+            writer: open_writer(path)?,
+            flush_interval,
+            last_flush: Instant::now(),
+            max_size,
+        })
+    }
+
+    /// Append one row per device, flushing if the flush interval has elapsed
+    pub fn log(&mut self, devices: &[Device]) -> io::Result<()> {
+        let timestamp = Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        for device in devices {
+            writeln!(
+                self.writer,
+                "{},{},{},{:?}",
+                timestamp,
+                escape(&device.name),
+                device.level().map(|b| b.to_string()).unwrap_or_default(),
+                device.battery_status
+            )?;
+        }
+
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+            self.rotate_if_full()?;
+        }
+        Ok(())
+    }
+
+    /// Move the file to `<path>.1`, replacing an older rotation, once it exceeds the size limit
+    fn rotate_if_full(&mut self) -> io::Result<()> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+        if self.writer.get_ref().metadata()?.len() < max_size {
+            return Ok(());
+        }
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        self.writer = open_writer(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Open `path` for appending, writing a header if the file is new
+fn open_writer(path: &Path) -> io::Result<BufWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let is_new = file.metadata()?.len() == 0;
+
+    let mut writer = BufWriter::new(file);
+    if is_new {
+        writeln!(writer, "{}", HEADER)?;
+    }
+    Ok(writer)
+}
+
+/// Quote a CSV field if needed
+pub fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
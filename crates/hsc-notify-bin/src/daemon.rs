@@ -0,0 +1,488 @@
+//! The long-running notification daemon
+
+#[cfg(feature = "pactl")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "extras")]
+use hsc_backends::headsetcontrol::extras_with;
+#[cfg(feature = "pactl")]
+use hsc_backends::pactl::{self, DefaultSwitcher, DescriptionAnnotator};
+#[cfg(feature = "plugins")]
+use hsc_backends::plugin::{self, PluginSink};
+#[cfg(feature = "screencast")]
+use hsc_backends::screencast;
+#[cfg(feature = "wasm")]
+use hsc_backends::wasm::{self, SharedPlugin, WasmPlugin, WasmPolicy, WasmSink};
+use hsc_backends::{
+    error::BackendError,
+    headsetcontrol::{headsetcontrol_output, HeadsetControl},
+};
+use hsc_core::{
+    command::{HostRunner, SystemRunner},
+    config::Config,
+    control::{PowerSaving, SettingsStore},
+    device::{parse_devices, Device},
+    monitor::Monitor,
+    platform::{Native, Platform},
+};
+
+#[cfg(feature = "osd")]
+use crate::osd::Osd;
+#[cfg(feature = "history")]
+use crate::recorder::Recorder;
+#[cfg(feature = "scripting")]
+use crate::rules::Rules;
+use crate::{
+    csv_log::CsvLogger,
+    table::{level_cell, status_cell, Table},
+};
+#[cfg(feature = "dbus")]
+use crate::{dbus, logind};
+
+/// How often the desktop is asked whether it prefers light or dark colors
+const COLOR_SCHEME_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Run the notifier in the foreground until killed
+pub fn run(config: Config, color: bool) {
+    println!("Starting Headset Battery Notifier...");
+    let polling_interval = config.polling_interval;
+    // Readings and settings go through SSH when watching a headset on another machine
+    let runner = HostRunner::new(config.remote.as_deref());
+    if let Some(remote) = &config.remote {
+        println!("Watching headsets on {}", remote);
+    }
+    #[cfg(feature = "pactl")]
+    let mut track_active_audio = config.audio.only_active_output || config.audio.track_usage;
+    #[cfg(feature = "pactl")]
+    let mut annotator = config
+        .audio
+        .annotate_description
+        .then(DescriptionAnnotator::default);
+    // Annotated descriptions are restored before exiting, so termination is only noted here and
+    // acted on between polls
+    #[cfg(feature = "pactl")]
+    let terminated = Arc::new(AtomicBool::new(false));
+    #[cfg(feature = "pactl")]
+    if annotator.is_some() {
+        for signal in signal_hook::consts::TERM_SIGNALS {
+            if let Err(e) = signal_hook::flag::register(*signal, terminated.clone()) {
+                eprintln!("cannot handle termination signals: {}", e);
+            }
+        }
+    }
+    let switch_default_output = config.audio.switch_default_output
+        || config
+            .devices
+            .values()
+            .any(|rule| rule.switch_default_output == Some(true));
+    #[cfg(feature = "pactl")]
+    let mut switcher = switch_default_output.then(DefaultSwitcher::default);
+    if switch_default_output && config.remote.is_some() {
+        eprintln!("switch_default_output is ignored while watching headsets on another machine");
+    }
+    #[cfg(not(feature = "pactl"))]
+    if config.audio.only_active_output
+        || config.audio.track_usage
+        || config.audio.annotate_description
+        || switch_default_output
+    {
+        eprintln!("audio options are set but this build has no PulseAudio support");
+    }
+    #[cfg(feature = "pactl")]
+    let mut watch_calls = config.hold_during_calls;
+    #[cfg(not(feature = "pactl"))]
+    if config.hold_during_calls {
+        eprintln!("hold_during_calls is set but this build has no PulseAudio support");
+    }
+    #[cfg(feature = "dbus")]
+    let shutdown_reminder = config.shutdown_reminder.enabled;
+    #[cfg(not(feature = "dbus"))]
+    if config.shutdown_reminder.enabled {
+        eprintln!("shutdown_reminder is enabled but this build has no D-Bus support");
+    }
+    #[cfg(not(feature = "osd"))]
+    if config.osd.enabled {
+        eprintln!("osd is enabled but this build has no on-screen alert support");
+    }
+    #[cfg(feature = "screencast")]
+    let mut watch_screencast = config.hold_while_screen_sharing;
+    #[cfg(not(feature = "screencast"))]
+    if config.hold_while_screen_sharing {
+        eprintln!("hold_while_screen_sharing is set but this build cannot detect screen sharing");
+    }
+    // Icons follow the desktop switching between light and dark unless a scheme is configured
+    let detect_color_scheme = config.icons.has_variants() && config.icons.scheme.is_none();
+    // Power saving only changes headsets listing the inactive time capability
+    #[cfg(feature = "extras")]
+    let mut poll_extras = config.extras.poll || config.power_saving.enabled;
+    #[cfg(not(feature = "extras"))]
+    if config.extras.poll {
+        eprintln!("extras.poll is set but this build cannot read chatmix");
+    }
+    #[cfg(not(feature = "extras"))]
+    if config.power_saving.enabled {
+        eprintln!("power_saving is enabled but this build cannot read headset capabilities");
+    }
+    let mut csv_logger = config.csv.path.as_ref().and_then(|path| {
+        CsvLogger::open(
+            path,
+            Duration::from_secs(config.csv.flush_interval),
+            config.csv.max_size,
+        )
+        .inspect_err(|e| eprintln!("cannot open {}: {}", path.display(), e))
+        .ok()
+    });
+    // Each provider is polled along with whether its last poll failed
+    #[cfg(feature = "plugins")]
+    let mut providers: Vec<_> = config
+        .plugins
+        .providers
+        .iter()
+        .map(|provider| (provider.clone(), false))
+        .collect();
+    #[cfg(feature = "plugins")]
+    let sinks = config.plugins.sinks.clone();
+    #[cfg(not(feature = "plugins"))]
+    if !config.plugins.providers.is_empty()
+        || !config.plugins.sinks.is_empty()
+        || !config.plugins.wasm.is_empty()
+    {
+        eprintln!("plugins are configured but this build has no plugin support");
+    }
+    #[cfg(feature = "wasm")]
+    let modules = load_wasm(&config);
+    #[cfg(all(feature = "plugins", not(feature = "wasm")))]
+    if !config.plugins.wasm.is_empty() {
+        eprintln!("WebAssembly plugins are configured but this build has no WebAssembly support");
+    }
+    #[cfg(not(feature = "scripting"))]
+    if !config.rules.is_empty() {
+        eprintln!("rules are configured but this build has no scripting support");
+    }
+    #[cfg(feature = "scripting")]
+    let rules = Rules::load(&config);
+    #[cfg(feature = "history")]
+    let mut recorder = Recorder::open(&config);
+    let mut monitor = Monitor::new(config);
+    match SettingsStore::load(&SettingsStore::default_path()) {
+        Ok(settings) => monitor.settings = settings,
+        Err(e) => eprintln!("{}", e),
+    }
+    monitor.subscribe(PowerSaving::new(
+        HeadsetControl(runner.clone()),
+        SettingsStore::default_path(),
+    ));
+    #[cfg(feature = "osd")]
+    monitor.subscribe(Osd::default());
+    #[cfg(feature = "plugins")]
+    for sink in sinks {
+        monitor.add_sink(PluginSink::new(sink));
+    }
+    #[cfg(feature = "wasm")]
+    let mut wasm_providers: Vec<(SharedPlugin, bool)> = {
+        for module in &modules {
+            let plugin = wasm::lock(module);
+            if plugin.is_sink() {
+                monitor.add_sink(WasmSink(module.clone()));
+            }
+            if plugin.is_policy() {
+                monitor.subscribe(WasmPolicy(module.clone()));
+            }
+        }
+        modules
+            .into_iter()
+            .filter(|module| wasm::lock(module).is_provider())
+            .map(|module| (module, false))
+            .collect()
+    };
+    #[cfg(feature = "scripting")]
+    if let Some(rules) = rules {
+        monitor.subscribe(rules);
+    }
+    let clock = monitor.clock().clone();
+    let monitor = Arc::new(Mutex::new(monitor));
+
+    #[cfg(feature = "dbus")]
+    let dbus = dbus::serve(monitor.clone())
+        .inspect_err(|e| eprintln!("D-Bus service unavailable: {}", e))
+        .ok();
+    #[cfg(feature = "dbus")]
+    if shutdown_reminder {
+        logind::watch(monitor.clone());
+    }
+
+    let mut color_scheme_checked: Option<Instant> = None;
+    let mut headsetcontrol_failing = false;
+    #[cfg(feature = "extras")]
+    let mut extras_failing = false;
+    #[cfg(feature = "pactl")]
+    let mut active_audio_failing = false;
+    #[cfg(feature = "pactl")]
+    let mut annotator_failing = false;
+    #[cfg(feature = "pactl")]
+    let mut switcher_failing = false;
+    #[cfg(feature = "pactl")]
+    let mut calls_failing = false;
+    #[cfg(feature = "screencast")]
+    let mut screencast_failing = false;
+
+    loop {
+        #[cfg(feature = "pactl")]
+        if terminated.load(Ordering::Relaxed) {
+            break;
+        }
+        // headsetcontrol is the main source of readings, so it is retried even when missing
+        #[cfg_attr(not(feature = "plugins"), allow(unused_mut))]
+        let mut readings = report(
+            &mut headsetcontrol_failing,
+            "cannot poll devices",
+            headsetcontrol_output(&runner),
+        )
+        .map(|output| parse_devices(&output))
+        .unwrap_or_default();
+        // Firmware versions reported by providers, as (device, version)
+        #[cfg_attr(not(feature = "plugins"), allow(unused_mut))]
+        let mut firmware: Vec<(String, String)> = Vec::new();
+        #[cfg(feature = "plugins")]
+        providers.retain_mut(|(provider, failing)| {
+            let result = plugin::poll(provider);
+            if !keep_polling(&provider.command, &result) {
+                return false;
+            }
+            let context = format!("plugin {} failed", provider.command);
+            let poll = report(failing, &context, result).unwrap_or_default();
+            readings.extend(poll.devices);
+            firmware.extend(poll.firmware);
+            true
+        });
+        #[cfg(feature = "wasm")]
+        wasm_providers.retain_mut(|(module, failing)| {
+            let mut module = wasm::lock(module);
+            let name = module.path().display().to_string();
+            let result = module.poll();
+            if !keep_polling(&name, &result) {
+                return false;
+            }
+            let context = format!("wasm plugin {} failed", name);
+            let poll = report(failing, &context, result).unwrap_or_default();
+            readings.extend(poll.devices);
+            firmware.extend(poll.firmware);
+            true
+        });
+        #[cfg(feature = "extras")]
+        let extras = if poll_extras {
+            let result = extras_with(&runner);
+            poll_extras = keep_polling("headsetcontrol", &result);
+            report(
+                &mut extras_failing,
+                "cannot read chatmix and capabilities",
+                result,
+            )
+            .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        #[cfg(feature = "pactl")]
+        let active_audio = if track_active_audio {
+            let result = pactl::default_nodes(&SystemRunner);
+            track_active_audio = keep_polling("pactl", &result);
+            report(
+                &mut active_audio_failing,
+                "cannot determine the active audio device",
+                result,
+            )
+        } else {
+            None
+        };
+        #[cfg(feature = "pactl")]
+        let recording = if watch_calls {
+            let result = pactl::list_sources(&SystemRunner);
+            watch_calls = keep_polling("pactl", &result);
+            report(
+                &mut calls_failing,
+                "cannot tell whether a microphone is in use",
+                result,
+            )
+        } else {
+            None
+        };
+        #[cfg(feature = "screencast")]
+        let screen_sharing = watch_screencast && {
+            let result = screencast::is_sharing();
+            watch_screencast = keep_polling("pw-dump", &result);
+            report(
+                &mut screencast_failing,
+                "cannot tell whether the screen is shared",
+                result,
+            )
+            .unwrap_or(false)
+        };
+
+        let now = clock.now();
+        let color_scheme = (detect_color_scheme
+            && color_scheme_checked.is_none_or(|t| now.duration_since(t) >= COLOR_SCHEME_INTERVAL))
+        .then(|| {
+            color_scheme_checked = Some(now);
+            Native.color_scheme(&SystemRunner)
+        });
+
+        #[cfg_attr(not(feature = "pactl"), allow(unused_variables))]
+        let (config, devices) = {
+            let mut monitor = monitor.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(scheme) = color_scheme {
+                monitor.color_scheme = scheme;
+            }
+            #[cfg(feature = "pactl")]
+            {
+                monitor.active_audio = active_audio;
+                if let Some(sources) = recording {
+                    monitor.set_recording(&sources);
+                }
+            }
+            #[cfg(feature = "screencast")]
+            monitor.set_screen_sharing(screen_sharing);
+            // Capabilities are needed by power saving while handling this poll's events
+            #[cfg(feature = "extras")]
+            for (name, extras) in extras {
+                monitor.set_extras(&name, extras);
+            }
+            let readings = monitor.update_readings(readings).readings;
+            for (name, version) in firmware {
+                monitor.set_firmware(&name, &version);
+            }
+            if let Some(logger) = csv_logger.as_mut() {
+                if let Err(e) = logger.log(&readings) {
+                    eprintln!("failed to write CSV history: {}", e);
+                }
+            }
+            #[cfg(feature = "history")]
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.update(&mut monitor, &readings);
+            }
+            if monitor.config.debug {
+                print!("{}", debug_table(&monitor).render(color));
+            }
+            let devices: Vec<Device> = monitor.devices.values().cloned().collect();
+            (monitor.config.clone(), devices)
+        };
+
+        #[cfg(feature = "dbus")]
+        if let Some(conn) = &dbus {
+            if let Err(e) = dbus::publish(conn) {
+                eprintln!("failed to publish D-Bus state: {}", e);
+            }
+        }
+        #[cfg(feature = "pactl")]
+        if let Some(active) = annotator.as_mut() {
+            let result = active.update(&SystemRunner, &config, &devices);
+            if !keep_polling("pactl", &result) {
+                annotator = None;
+            }
+            report(
+                &mut annotator_failing,
+                "cannot annotate audio device descriptions",
+                result,
+            );
+        }
+        #[cfg(feature = "pactl")]
+        if let Some(active) = switcher.as_mut() {
+            let result = active.update(&SystemRunner, &config, &devices);
+            if !keep_polling("pactl", &result) {
+                switcher = None;
+            }
+            report(
+                &mut switcher_failing,
+                "cannot switch the default audio output",
+                result,
+            );
+        }
+
+        clock.sleep(Duration::from_millis(polling_interval));
+    }
+    #[cfg(feature = "pactl")]
+    if let Some(active) = annotator.as_mut() {
+        if let Err(e) = active.restore(&SystemRunner) {
+            eprintln!("cannot restore audio device descriptions: {}", e);
+        }
+    }
+}
+
+/// Every device the monitor knows about, with its estimate and the level it was last notified
+/// about
+fn debug_table(monitor: &Monitor) -> Table {
+    let mut devices: Vec<&Device> = monitor.devices.values().collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut table = Table::new(&["DEVICE", "LEVEL", "STATUS", "ESTIMATE", "NOTIFIED"]);
+    for device in devices {
+        let estimate = monitor
+            .estimate(&device.name)
+            .map(|e| format!("{} ({:.1}%/h)", e, e.rate_per_hour))
+            .unwrap_or_default();
+        let notified = device
+            .last_notif_battery_level
+            .map(|level| format!("{}%", level))
+            .unwrap_or_default();
+        table.row(vec![
+            device.name.as_str().into(),
+            level_cell(&monitor.config, device),
+            status_cell(device),
+            estimate.into(),
+            notified.into(),
+        ]);
+    }
+    table
+}
+
+/// Load the configured WebAssembly modules, skipping those that fail
+#[cfg(feature = "wasm")]
+fn load_wasm(config: &Config) -> Vec<SharedPlugin> {
+    config
+        .plugins
+        .wasm
+        .iter()
+        .filter_map(|path| {
+            WasmPlugin::load(path)
+                .inspect_err(|e| eprintln!("cannot load {}: {}", path.display(), e))
+                .ok()
+        })
+        .map(|plugin| Arc::new(Mutex::new(plugin)))
+        .collect()
+}
+
+/// Log a failure only when the previous attempt succeeded, so persistent errors are reported once
+fn report<T>(failing: &mut bool, context: &str, result: Result<T, BackendError>) -> Option<T> {
+    match result {
+        Ok(value) => {
+            *failing = false;
+            Some(value)
+        }
+        Err(e) => {
+            if !*failing {
+                eprintln!("{}: {}", context, e);
+            }
+            *failing = true;
+            None
+        }
+    }
+}
+
+/// Whether to keep polling a backend after `result`, announcing when it is given up on
+#[cfg(any(
+    feature = "extras",
+    feature = "pactl",
+    feature = "plugins",
+    feature = "screencast"
+))]
+fn keep_polling<T>(name: &str, result: &Result<T, BackendError>) -> bool {
+    match result {
+        Err(e) if !e.is_retryable() => {
+            eprintln!("no longer polling {}: {}", name, e);
+            false
+        }
+        _ => true,
+    }
+}
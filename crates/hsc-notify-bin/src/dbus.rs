@@ -0,0 +1,314 @@
+//! D-Bus service exposing the monitor on the session bus
+//!
+//! Two interfaces are served at [`OBJECT_PATH`]: a control interface for pausing and snoozing
+//! notifications and listing devices, and an indicator interface with ready-to-render state
+//! for panel widgets.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use zbus::{
+    block_on,
+    blocking::{connection, Connection},
+    fdo, interface,
+    object_server::SignalEmitter,
+    proxy,
+};
+
+use hsc_core::monitor::{Event, Monitor};
+
+use crate::{indicator, inspect};
+
+/// Well-known bus name of the service
+pub const BUS_NAME: &str = "io.github.loseardes77.HeadsetControlNotify";
+/// Path of the service object
+pub const OBJECT_PATH: &str = "/io/github/loseardes77/HeadsetControlNotify";
+
+/// Control interface
+struct Control {
+    monitor: Arc<Mutex<Monitor>>,
+    /// Id of the last event announced through `EventRaised`
+    published_event: Option<u64>,
+}
+
+/// A notification as sent over the bus: (id, unix time, device name, message, suppressed)
+pub type EventTuple = (u64, i64, String, String, bool);
+
+/// Bus representation of an event
+fn event_tuple(event: &Event) -> EventTuple {
+    (
+        event.id,
+        event.time.timestamp(),
+        event.device.clone(),
+        event.message.clone(),
+        event.suppressed,
+    )
+}
+
+/// Indicator interface
+struct Indicator {
+    monitor: Arc<Mutex<Monitor>>,
+    /// Summary of the state last announced through `PropertiesChanged`
+    published: String,
+}
+
+/// Lock the shared monitor, recovering from a poisoned lock
+fn lock(monitor: &Mutex<Monitor>) -> MutexGuard<'_, Monitor> {
+    monitor.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[interface(name = "io.github.loseardes77.HeadsetControlNotify1")]
+impl Control {
+    /// Suppress notifications until resumed
+    async fn pause(&self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+        lock(&self.monitor).paused = true;
+        self.paused_changed(&emitter).await?;
+        Ok(())
+    }
+
+    /// Resume notifications after a pause
+    async fn resume(&self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+        lock(&self.monitor).paused = false;
+        self.paused_changed(&emitter).await?;
+        Ok(())
+    }
+
+    /// Suppress notifications for the given number of minutes, or cancel the snooze with 0
+    async fn snooze(
+        &self,
+        minutes: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let duration = (minutes > 0).then(|| std::time::Duration::from_secs(minutes as u64 * 60));
+        lock(&self.monitor).snooze(duration);
+        self.snooze_remaining_changed(&emitter).await?;
+        Ok(())
+    }
+
+    /// Notifications still held in memory, oldest first
+    fn recent_events(&self) -> Vec<EventTuple> {
+        lock(&self.monitor).events.iter().map(event_tuple).collect()
+    }
+
+    /// Everything the daemon knows about a device as (label, value) pairs, empty if it was never
+    /// seen
+    fn inspect(&self, name: &str) -> Vec<(String, String)> {
+        inspect::daemon_state(&lock(&self.monitor), name)
+    }
+
+    /// Emitted for every notification the daemon raises, including suppressed ones
+    #[zbus(signal)]
+    async fn event_raised(
+        emitter: &SignalEmitter<'_>,
+        id: u64,
+        time: i64,
+        device: &str,
+        message: &str,
+        suppressed: bool,
+    ) -> zbus::Result<()>;
+
+    /// Whether notifications are paused
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        lock(&self.monitor).paused
+    }
+
+    /// Seconds left in the current snooze, 0 if not snoozed
+    #[zbus(property)]
+    fn snooze_remaining(&self) -> u64 {
+        lock(&self.monitor)
+            .snooze_remaining()
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// Known devices as (name, display name, status, battery level or -1)
+    #[zbus(property)]
+    fn devices(&self) -> Vec<(String, String, String, i16)> {
+        let monitor = lock(&self.monitor);
+        let mut devices: Vec<_> = monitor
+            .devices
+            .values()
+            .map(|d| {
+                (
+                    d.name.clone(),
+                    monitor.config.display_name(&d.name).to_string(),
+                    d.battery_status.as_str().to_string(),
+                    d.level().map_or(-1, i16::from),
+                )
+            })
+            .collect();
+        devices.sort();
+        devices
+    }
+}
+
+#[interface(name = "io.github.loseardes77.HeadsetControlNotify1.Indicator")]
+impl Indicator {
+    /// Run one of the actions listed in `MenuActions`
+    async fn activate(
+        &self,
+        id: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        // Switching the lights waits for headsetcontrol, which must not stall the bus connection
+        let monitor = self.monitor.clone();
+        let id = id.to_string();
+        blocking::unblock(move || indicator::activate(&monitor, &id))
+            .await
+            .map_err(fdo::Error::Failed)?;
+        self.menu_actions_changed(&emitter).await?;
+        self.tooltip_markup_changed(&emitter).await?;
+        Ok(())
+    }
+
+    /// Name of the device the indicator should represent, empty if none is connected
+    #[zbus(property)]
+    fn primary_device(&self) -> String {
+        let monitor = lock(&self.monitor);
+        indicator::primary_device(&monitor)
+            .map(|d| monitor.config.display_name(&d.name).to_string())
+            .unwrap_or_default()
+    }
+
+    /// Freedesktop icon name for the primary device, in the variant for the desktop's colors
+    #[zbus(property)]
+    fn icon_name(&self) -> String {
+        let monitor = lock(&self.monitor);
+        let icon = indicator::icon_name(indicator::primary_device(&monitor));
+        let suffix = monitor.config.icons.suffix(monitor.color_scheme);
+        format!("{}{}", icon, suffix.unwrap_or_default())
+    }
+
+    /// Pango markup describing all connected devices
+    #[zbus(property)]
+    fn tooltip_markup(&self) -> String {
+        indicator::tooltip_markup(&lock(&self.monitor))
+    }
+
+    /// Available actions as (id, label) pairs
+    #[zbus(property)]
+    fn menu_actions(&self) -> Vec<(String, String)> {
+        indicator::menu_actions(&lock(&self.monitor))
+            .into_iter()
+            .map(|a| (a.id.to_string(), a.label))
+            .collect()
+    }
+}
+
+/// Client side of the control interface
+#[proxy(
+    interface = "io.github.loseardes77.HeadsetControlNotify1",
+    default_service = "io.github.loseardes77.HeadsetControlNotify",
+    default_path = "/io/github/loseardes77/HeadsetControlNotify"
+)]
+pub trait ControlClient {
+    /// Notifications still held in memory, oldest first
+    fn recent_events(&self) -> zbus::Result<Vec<EventTuple>>;
+
+    /// Everything the daemon knows about a device as (label, value) pairs
+    fn inspect(&self, name: &str) -> zbus::Result<Vec<(String, String)>>;
+
+    /// Emitted for every notification the daemon raises
+    #[zbus(signal)]
+    fn event_raised(
+        &self,
+        id: u64,
+        time: i64,
+        device: String,
+        message: String,
+        suppressed: bool,
+    ) -> zbus::Result<()>;
+
+    /// Known devices as (name, display name, status, battery level or -1)
+    #[zbus(property)]
+    fn devices(&self) -> zbus::Result<Vec<(String, String, String, i16)>>;
+}
+
+/// Client side of the indicator interface
+#[proxy(
+    interface = "io.github.loseardes77.HeadsetControlNotify1.Indicator",
+    default_service = "io.github.loseardes77.HeadsetControlNotify",
+    default_path = "/io/github/loseardes77/HeadsetControlNotify"
+)]
+pub trait IndicatorClient {
+    /// Run one of the actions listed in `MenuActions`
+    fn activate(&self, id: &str) -> zbus::Result<()>;
+
+    /// Available actions as (id, label) pairs
+    #[zbus(property)]
+    fn menu_actions(&self) -> zbus::Result<Vec<(String, String)>>;
+}
+
+/// Register the service on the session bus
+pub fn serve(monitor: Arc<Mutex<Monitor>>) -> zbus::Result<Connection> {
+    connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Control {
+                monitor: monitor.clone(),
+                published_event: None,
+            },
+        )?
+        .serve_at(
+            OBJECT_PATH,
+            Indicator {
+                monitor,
+                published: String::new(),
+            },
+        )?
+        .build()
+}
+
+/// Announce new events, and property changes if the monitor state changed since the last call
+pub fn publish(conn: &Connection) -> zbus::Result<()> {
+    let object_server = conn.object_server();
+    let indicator = object_server.interface::<_, Indicator>(OBJECT_PATH)?;
+    let control = object_server.interface::<_, Control>(OBJECT_PATH)?;
+
+    let new_events: Vec<EventTuple> = {
+        let iface = control.get();
+        let monitor = lock(&iface.monitor);
+        monitor
+            .events_since(iface.published_event)
+            .map(event_tuple)
+            .collect()
+    };
+    if let Some(&(id, ..)) = new_events.last() {
+        control.get_mut().published_event = Some(id);
+        let emitter = control.signal_emitter();
+        block_on(async {
+            for (id, time, device, message, suppressed) in &new_events {
+                Control::event_raised(emitter, *id, *time, device, message, *suppressed).await?;
+            }
+            zbus::Result::Ok(())
+        })?;
+    }
+
+    let state = {
+        let iface = indicator.get();
+        let monitor = lock(&iface.monitor);
+        format!(
+            "{:?}{:?}{}",
+            indicator::primary_device(&monitor).map(|d| &d.name),
+            monitor.color_scheme,
+            indicator::tooltip_markup(&monitor)
+        )
+    };
+    if indicator.get().published == state {
+        return Ok(());
+    }
+    indicator.get_mut().published = state;
+
+    let emitter = indicator.signal_emitter();
+    block_on(async {
+        let indicator = indicator.get();
+        indicator.primary_device_changed(emitter).await?;
+        indicator.icon_name_changed(emitter).await?;
+        indicator.tooltip_markup_changed(emitter).await?;
+        indicator.menu_actions_changed(emitter).await?;
+        let control = control.get();
+        control.devices_changed(emitter).await?;
+        control.paused_changed(emitter).await?;
+        control.snooze_remaining_changed(emitter).await
+    })
+}
@@ -0,0 +1,97 @@
+//! Printing and following recent notifications
+
+use std::error::Error;
+
+use chrono::{DateTime, Local, TimeZone};
+use zbus::blocking::Connection;
+
+use hsc_core::config::Config;
+
+use crate::dbus::ControlClientProxyBlocking;
+#[cfg(feature = "history")]
+use crate::history::HistoryStore;
+
+/// Print recent notifications and, with `follow`, keep printing new ones as they are raised
+///
+/// Events are read from the running daemon. When it is not reachable, stored notifications are
+/// read from the history store instead, if it is compiled in, which cannot be followed.
+pub fn run(config: &Config, count: usize, follow: bool) -> Result<(), Box<dyn Error>> {
+    let daemon = Connection::session()
+        .and_then(|conn| ControlClientProxyBlocking::new(&conn))
+        .and_then(|control| control.recent_events().map(|events| (control, events)));
+
+    let (control, events) = match daemon {
+        Ok(found) => found,
+        Err(e) if follow => return Err(format!("daemon not reachable: {}", e).into()),
+        #[cfg(feature = "history")]
+        Err(_) => return print_stored(config, count),
+        #[cfg(not(feature = "history"))]
+        Err(e) => return Err(format!("daemon not reachable: {}", e).into()),
+    };
+
+    // Subscribe before printing so nothing raised in between is lost
+    let signals = follow.then(|| control.receive_event_raised()).transpose()?;
+    let mut last_id = None;
+    for (id, time, device, message, suppressed) in skip_to_last(events, count) {
+        print_event(config, local_time(time), &device, &message, suppressed);
+        last_id = Some(id);
+    }
+
+    for signal in signals.into_iter().flatten() {
+        let args = signal.args()?;
+        if last_id.is_some_and(|last| args.id <= last) {
+            continue;
+        }
+        print_event(
+            config,
+            local_time(args.time),
+            &args.device,
+            &args.message,
+            args.suppressed,
+        );
+        last_id = Some(args.id);
+    }
+    Ok(())
+}
+
+/// Print the last `count` notifications from the history store
+#[cfg(feature = "history")]
+fn print_stored(config: &Config, count: usize) -> Result<(), Box<dyn Error>> {
+    let store = HistoryStore::open(&config.history.path())?;
+    let events = store.events(None, local_time(0), Local::now())?;
+    for event in skip_to_last(events, count) {
+        print_event(config, event.time, &event.device, &event.message, false);
+    }
+    Ok(())
+}
+
+/// Convert a unix timestamp sent by the daemon to local time
+fn local_time(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_default()
+}
+
+/// The last `count` items of `items`
+fn skip_to_last<T>(items: Vec<T>, count: usize) -> impl Iterator<Item = T> {
+    let skip = items.len().saturating_sub(count);
+    items.into_iter().skip(skip)
+}
+
+/// Print one notification line
+fn print_event(
+    config: &Config,
+    time: DateTime<Local>,
+    device: &str,
+    message: &str,
+    suppressed: bool,
+) {
+    println!(
+        "{}  {}: {}{}",
+        time.format("%Y-%m-%d %H:%M:%S"),
+        config.display_name(device),
+        message,
+        if suppressed { " (suppressed)" } else { "" }
+    );
+}
@@ -0,0 +1,113 @@
+//! Dumping the history store for external analysis
+
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use chrono::{DateTime, Local, SecondsFormat};
+use clap::ValueEnum;
+
+use hsc_core::schema::{Export, NotificationRecord, ReadingRecord, SCHEMA_VERSION};
+
+use crate::{
+    csv_log::escape,
+    history::{AgeRange, HistoryStore},
+};
+
+/// Output format of an export
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Write stored readings, or notifications if `events` is set, to standard output
+pub fn export(
+    store: &HistoryStore,
+    format: ExportFormat,
+    device: Option<&str>,
+    range: AgeRange,
+    events: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (since, until) = range.resolve(Local::now())?;
+    let mut out = io::stdout().lock();
+
+    if events {
+        let records: Vec<NotificationRecord> = store
+            .events(device, since, until)?
+            .into_iter()
+            .map(|e| NotificationRecord {
+                timestamp: timestamp(e.time),
+                device: e.device,
+                message: e.message,
+            })
+            .collect();
+        match format {
+            ExportFormat::Json => serde_json::to_writer_pretty(
+                &mut out,
+                &Export {
+                    schema_version: SCHEMA_VERSION,
+                    readings: None,
+                    notifications: Some(records),
+                },
+            )?,
+            ExportFormat::Csv => {
+                writeln!(out, "timestamp,device,message")?;
+                for r in &records {
+                    writeln!(
+                        out,
+                        "{},{},{}",
+                        r.timestamp,
+                        escape(&r.device),
+                        escape(&r.message)
+                    )?;
+                }
+            }
+        }
+    } else {
+        let records: Vec<ReadingRecord> = store
+            .readings(device, since, until)?
+            .into_iter()
+            .map(|r| ReadingRecord {
+                timestamp: timestamp(r.time),
+                device: r.device,
+                level: r.level,
+                status: r.status.into(),
+            })
+            .collect();
+        match format {
+            ExportFormat::Json => serde_json::to_writer_pretty(
+                &mut out,
+                &Export {
+                    schema_version: SCHEMA_VERSION,
+                    readings: Some(records),
+                    notifications: None,
+                },
+            )?,
+            ExportFormat::Csv => {
+                writeln!(out, "timestamp,device,level,status")?;
+                for r in &records {
+                    writeln!(
+                        out,
+                        "{},{},{},{}",
+                        r.timestamp,
+                        escape(&r.device),
+                        r.level.map(|l| l.to_string()).unwrap_or_default(),
+                        r.status.as_str()
+                    )?;
+                }
+            }
+        }
+    }
+
+    if let ExportFormat::Json = format {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// RFC 3339 timestamp, as written by the live CSV logger
+fn timestamp(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
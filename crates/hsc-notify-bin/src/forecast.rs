@@ -0,0 +1,59 @@
+//! Predicting how much battery the rest of a typical day of use will take
+
+use chrono::{DateTime, Days, Local, NaiveTime, Timelike};
+
+use hsc_core::forecast::UsageForecast;
+
+use crate::history::{start_of_day, HistoryStore};
+
+/// Number of past days considered
+const LOOKBACK_DAYS: u64 = 14;
+/// Minimum number of days with use needed for a forecast
+const MIN_DAYS: usize = 3;
+
+/// Forecast the rest of today's use of a device from the last two weeks
+///
+/// Uses the median over past days on which the device discharged at all, so occasional long or
+/// idle days do not skew it. Returns `None` with fewer than three such days.
+pub fn forecast(
+    store: &HistoryStore,
+    device: &str,
+    now: DateTime<Local>,
+) -> rusqlite::Result<Option<UsageForecast>> {
+    let time_of_day = now.time();
+    let mut drops = Vec::new();
+    let mut ends = Vec::new();
+
+    for days_ago in 1..=LOOKBACK_DAYS {
+        let Some(day) = now.date_naive().checked_sub_days(Days::new(days_ago)) else {
+            continue;
+        };
+        let (start, end) = (start_of_day(day), start_of_day(day + Days::new(1)));
+        let Some(last_use) = store.last_discharging(device, start, end)? else {
+            continue;
+        };
+        let from = day
+            .and_time(time_of_day)
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap_or(start);
+        drops.push(store.discharge_drop(device, from, end)?);
+        ends.push(last_use.time().num_seconds_from_midnight());
+    }
+
+    if drops.len() < MIN_DAYS {
+        return Ok(None);
+    }
+    let until = NaiveTime::from_num_seconds_from_midnight_opt(median(&mut ends), 0)
+        .unwrap_or(NaiveTime::MIN);
+    Ok(Some(UsageForecast {
+        expected_drop: median(&mut drops),
+        until,
+    }))
+}
+
+/// Median of a non-empty list, taking the lower middle value for even lengths
+fn median<T: PartialOrd + Copy>(values: &mut [T]) -> T {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    values[(values.len() - 1) / 2]
+}
@@ -0,0 +1,81 @@
+//! Terminal sparklines of stored battery history
+
+use std::{collections::BTreeMap, error::Error, time::Duration};
+
+use chrono::Local;
+
+use hsc_core::config::Config;
+
+use crate::history::HistoryStore;
+
+/// Bar characters from lowest to highest level
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Print one sparkline per device covering the last `span`, `width` columns wide
+///
+/// Every column shows the average level of the readings falling into it; columns without
+/// readings, such as while a device was switched off, are left blank.
+pub fn print(
+    config: &Config,
+    store: &HistoryStore,
+    device: Option<&str>,
+    span: Duration,
+    width: usize,
+) -> Result<(), Box<dyn Error>> {
+    let width = width.max(1);
+    let now = Local::now();
+    let since = now - chrono::Duration::from_std(span)?;
+    let seconds = span.as_secs_f64().max(1.0);
+
+    let mut columns: BTreeMap<String, Vec<(u32, u32)>> = BTreeMap::new();
+    for reading in store.readings(device, since, now)? {
+        let Some(level) = reading.level else {
+            continue;
+        };
+        let offset = (reading.time - since).num_seconds() as f64;
+        let column = ((offset / seconds * width as f64) as usize).min(width - 1);
+        let sums = columns
+            .entry(reading.device)
+            .or_insert_with(|| vec![(0, 0); width]);
+        sums[column].0 += level as u32;
+        sums[column].1 += 1;
+    }
+
+    if columns.is_empty() {
+        println!("No battery history in this period");
+        return Ok(());
+    }
+
+    for (name, sums) in columns {
+        let levels: Vec<Option<u32>> = sums
+            .iter()
+            .map(|&(sum, count)| (count > 0).then(|| sum / count))
+            .collect();
+        let known = levels.iter().flatten();
+        let (first, last) = (
+            known.clone().next().copied().unwrap_or_default(),
+            known.clone().last().copied().unwrap_or_default(),
+        );
+        println!("{}  {}% → {}%", config.display_name(&name), first, last);
+        println!("{}", sparkline(&levels));
+        let start = since.format("%H:%M").to_string();
+        println!(
+            "{}{:>pad$}",
+            start,
+            "now",
+            pad = width.saturating_sub(start.len())
+        );
+    }
+    Ok(())
+}
+
+/// Render battery percentages as bar characters, with blanks for missing values
+fn sparkline(levels: &[Option<u32>]) -> String {
+    levels
+        .iter()
+        .map(|level| match level {
+            Some(level) => BARS[(*level.min(&100) as usize * (BARS.len() - 1) + 50) / 100],
+            None => ' ',
+        })
+        .collect()
+}
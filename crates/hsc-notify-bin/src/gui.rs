@@ -0,0 +1,356 @@
+//! Graphical settings editor
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use hsc_core::config::{Config, DeviceRule, NotificationMode, Sink};
+
+/// Settings window state
+struct SettingsApp {
+    /// Where the configuration is saved
+    path: PathBuf,
+    /// Configuration being edited
+    config: Config,
+    /// Configuration as last loaded or saved
+    saved: Config,
+    /// Name typed into the "add device" field
+    new_device: String,
+    /// Result of the last save
+    status: String,
+}
+
+/// Open the settings window and block until it is closed
+pub fn run(path: PathBuf, config: Config) -> eframe::Result {
+    let app = SettingsApp {
+        path,
+        saved: config.clone(),
+        config,
+        new_device: String::new(),
+        status: String::new(),
+    };
+    eframe::run_native(
+        "headsetcontrol-notify settings",
+        eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default().with_inner_size([460.0, 560.0]),
+            ..Default::default()
+        },
+        Box::new(|_cc| Ok(Box::new(app))),
+    )
+}
+
+impl eframe::App for SettingsApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::bottom("actions").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let dirty = self.config != self.saved;
+                if ui.add_enabled(dirty, egui::Button::new("Save")).clicked() {
+                    self.save();
+                }
+                if ui.add_enabled(dirty, egui::Button::new("Revert")).clicked() {
+                    self.config = self.saved.clone();
+                    self.status.clear();
+                }
+                ui.label(&self.status);
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.general_section(ui);
+                ui.separator();
+                self.sinks_section(ui);
+                ui.separator();
+                self.audio_section(ui);
+                ui.separator();
+                self.csv_section(ui);
+                ui.separator();
+                self.devices_section(ui);
+            });
+        });
+    }
+}
+
+impl SettingsApp {
+    /// Global thresholds and polling
+    fn general_section(&mut self, ui: &mut egui::Ui) {
+        ui.heading("General");
+        egui::Grid::new("general").num_columns(2).show(ui, |ui| {
+            ui.label("Polling interval");
+            ui.add(
+                egui::DragValue::new(&mut self.config.polling_interval)
+                    .range(500..=600_000)
+                    .suffix(" ms"),
+            );
+            ui.end_row();
+
+            ui.label("Low battery threshold");
+            ui.add(egui::Slider::new(&mut self.config.battery_threshold, 0..=100).suffix("%"));
+            ui.end_row();
+
+            ui.label("Low remaining time");
+            optional_value(
+                ui,
+                &mut self.config.battery_threshold_minutes,
+                "Enable",
+                30,
+                5..=300,
+                " min",
+            );
+            ui.end_row();
+
+            ui.label("Notify every");
+            ui.add(egui::Slider::new(&mut self.config.notification_step, 1..=50).suffix("%"));
+            ui.end_row();
+
+            ui.label("Round levels to");
+            ui.add(egui::Slider::new(&mut self.config.level_bucket, 1..=25).suffix("%"));
+            ui.end_row();
+
+            ui.label("Only notify on thresholds");
+            let mut thresholds_only =
+                self.config.notification_mode == NotificationMode::ThresholdsOnly;
+            if ui.checkbox(&mut thresholds_only, "").changed() {
+                self.config.notification_mode = if thresholds_only {
+                    NotificationMode::ThresholdsOnly
+                } else {
+                    NotificationMode::Steps
+                };
+            }
+            ui.end_row();
+
+            ui.label("Notify when charging starts or stops");
+            ui.checkbox(&mut self.config.notify_charging, "");
+            ui.end_row();
+
+            ui.label("Group low battery alerts");
+            ui.checkbox(&mut self.config.group_low_alerts, "");
+            ui.end_row();
+
+            ui.label("Hold notifications while sharing the screen");
+            ui.checkbox(&mut self.config.hold_while_screen_sharing, "");
+            ui.end_row();
+
+            ui.label("Hold notifications during calls");
+            ui.checkbox(&mut self.config.hold_during_calls, "");
+            ui.end_row();
+
+            ui.label("Primary device");
+            let mut primary = self.config.primary_device.clone().unwrap_or_default();
+            if ui.text_edit_singleline(&mut primary).changed() {
+                self.config.primary_device = Some(primary).filter(|p| !p.trim().is_empty());
+            }
+            ui.end_row();
+
+            ui.label("App name");
+            let mut app_name = self.config.app_name.clone().unwrap_or_default();
+            if ui.text_edit_singleline(&mut app_name).changed() {
+                self.config.app_name = Some(app_name).filter(|a| !a.trim().is_empty());
+            }
+            ui.end_row();
+
+            ui.label("Debug output");
+            ui.checkbox(&mut self.config.debug, "");
+            ui.end_row();
+        });
+    }
+
+    /// Enabled notification sinks
+    fn sinks_section(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Notification sinks");
+        for sink in Sink::ALL {
+            let mut enabled = self.config.sinks.contains(&sink);
+            if ui.checkbox(&mut enabled, format!("{:?}", sink)).changed() {
+                if enabled {
+                    self.config.sinks.push(sink);
+                } else {
+                    self.config.sinks.retain(|s| *s != sink);
+                }
+            }
+        }
+    }
+
+    /// Sound server integration
+    fn audio_section(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Audio");
+        ui.checkbox(
+            &mut self.config.audio.annotate_description,
+            "Show battery level in audio device names",
+        );
+        ui.checkbox(
+            &mut self.config.audio.only_active_output,
+            "Only send routine notifications for the active output",
+        );
+        ui.checkbox(
+            &mut self.config.audio.track_usage,
+            "Tell idle shutdowns from lost connections",
+        );
+        ui.checkbox(
+            &mut self.config.audio.switch_default_output,
+            "Switch to the headset when it connects",
+        );
+    }
+
+    /// CSV history log
+    fn csv_section(&mut self, ui: &mut egui::Ui) {
+        ui.heading("History log");
+        egui::Grid::new("csv").num_columns(2).show(ui, |ui| {
+            ui.label("CSV file");
+            let mut path = self
+                .config
+                .csv
+                .path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            if ui.text_edit_singleline(&mut path).changed() {
+                self.config.csv.path =
+                    Some(PathBuf::from(path.trim())).filter(|p| !p.as_os_str().is_empty());
+            }
+            ui.end_row();
+
+            ui.label("Flush every");
+            ui.add(
+                egui::DragValue::new(&mut self.config.csv.flush_interval)
+                    .range(1..=3600)
+                    .suffix(" s"),
+            );
+            ui.end_row();
+        });
+    }
+
+    /// Per-device rules
+    fn devices_section(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Devices");
+        let mut removed = None;
+        for (name, rule) in self.config.devices.iter_mut() {
+            egui::CollapsingHeader::new(name)
+                .default_open(true)
+                .show(ui, |ui| {
+                    device_rule_editor(ui, name, rule);
+                    if ui.button("Remove").clicked() {
+                        removed = Some(name.clone());
+                    }
+                });
+        }
+        if let Some(name) = removed {
+            self.config.devices.remove(&name);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_device)
+                    .hint_text("Device name as reported by headsetcontrol"),
+            );
+            let name = self.new_device.trim();
+            let can_add = !name.is_empty() && !self.config.devices.contains_key(name);
+            if ui.add_enabled(can_add, egui::Button::new("Add")).clicked() {
+                self.config
+                    .devices
+                    .insert(name.to_string(), DeviceRule::default());
+                self.new_device.clear();
+            }
+        });
+    }
+
+    /// Write the edited configuration back to disk
+    fn save(&mut self) {
+        match self.config.save(&self.path) {
+            Ok(()) => {
+                self.saved = self.config.clone();
+                self.status = format!("Saved to {}", self.path.display());
+            }
+            Err(e) => self.status = format!("Failed to save: {}", e),
+        }
+    }
+}
+
+/// Edit the overrides of a single device
+fn device_rule_editor(ui: &mut egui::Ui, name: &str, rule: &mut DeviceRule) {
+    egui::Grid::new(name).num_columns(2).show(ui, |ui| {
+        ui.label("Alias");
+        let mut alias = rule.alias.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut alias).changed() {
+            rule.alias = Some(alias).filter(|a| !a.trim().is_empty());
+        }
+        ui.end_row();
+
+        ui.label("Low battery threshold");
+        optional_value(
+            ui,
+            &mut rule.battery_threshold,
+            "Override",
+            10,
+            0..=100,
+            "%",
+        );
+        ui.end_row();
+
+        ui.label("Low remaining time");
+        optional_value(
+            ui,
+            &mut rule.battery_threshold_minutes,
+            "Override",
+            30,
+            5..=300,
+            " min",
+        );
+        ui.end_row();
+
+        ui.label("Round levels to");
+        optional_value(ui, &mut rule.level_bucket, "Override", 5, 1..=25, "%");
+        ui.end_row();
+
+        ui.label("Muted");
+        ui.checkbox(&mut rule.muted, "");
+        ui.end_row();
+
+        ui.label("Icon");
+        let mut icon = rule.icon.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut icon).changed() {
+            rule.icon = Some(icon).filter(|i| !i.trim().is_empty());
+        }
+        ui.end_row();
+
+        ui.label("App name");
+        let mut app_name = rule.app_name.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut app_name).changed() {
+            rule.app_name = Some(app_name).filter(|a| !a.trim().is_empty());
+        }
+        ui.end_row();
+
+        ui.label("Group");
+        let mut group = rule.group.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut group).changed() {
+            rule.group = Some(group).filter(|g| !g.trim().is_empty());
+        }
+        ui.end_row();
+
+        ui.label("Audio device");
+        let mut audio_device = rule.audio_device.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut audio_device).changed() {
+            rule.audio_device = Some(audio_device).filter(|a| !a.trim().is_empty());
+        }
+        ui.end_row();
+    });
+}
+
+/// Edit an optional setting with a checkbox that enables a slider
+fn optional_value<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut Option<T>,
+    label: &str,
+    default: T,
+    range: std::ops::RangeInclusive<T>,
+    suffix: &str,
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *value = enabled.then_some(default);
+        }
+        if let Some(value) = value.as_mut() {
+            ui.add(egui::Slider::new(value, range).suffix(suffix));
+        }
+    });
+}
@@ -0,0 +1,119 @@
+//! Charge cycle counting and battery health estimation
+
+use std::error::Error;
+
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate};
+
+use hsc_core::{config::Config, health::BatteryHealth, session::ChargeSession};
+
+use crate::{
+    history::{start_of_day, HistoryStore},
+    session,
+};
+
+/// Span at the start of a device's history used as its "new battery" reference
+const REFERENCE_WINDOW: Duration = Duration::days(30);
+/// Span at the end of a device's history used for its current runtime
+const RECENT_WINDOW: Duration = Duration::days(14);
+
+/// Compute cycle count and runtime trend of a device from the history store
+///
+/// The reference and recent runtimes only come from disjoint windows, so a health figure needs
+/// at least six weeks of history.
+pub fn battery_health(
+    store: &HistoryStore,
+    device: &str,
+    now: DateTime<Local>,
+) -> rusqlite::Result<Option<BatteryHealth>> {
+    let Some(stats) = store.device_stats(device)? else {
+        return Ok(None);
+    };
+
+    let reference_end = stats.first_seen + REFERENCE_WINDOW;
+    let recent_start = now - RECENT_WINDOW;
+    let (reference_runtime, recent_runtime) = if reference_end <= recent_start {
+        (
+            runtime(store.discharge_rate(device, stats.first_seen, reference_end)?),
+            runtime(store.discharge_rate(device, recent_start, now)?),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(Some(BatteryHealth {
+        cycles: stats.charge_gained / 100.0,
+        reference_runtime,
+        recent_runtime,
+    }))
+}
+
+/// Hours of use per full charge at a discharge rate in %/h
+fn runtime(rate: Option<f64>) -> Option<f64> {
+    rate.filter(|r| *r > 0.0).map(|r| 100.0 / r)
+}
+
+/// Cycle count and health, and usage since the last full charge, of a device as display text
+pub fn details(
+    store: &HistoryStore,
+    device: &str,
+    now: DateTime<Local>,
+) -> rusqlite::Result<(String, String)> {
+    let mut charge_session = ChargeSession::default();
+    session::recover(&mut charge_session, store, device, now)?;
+    let health = battery_health(store, device, now)?
+        .map(|h| h.to_string())
+        .unwrap_or_default();
+    Ok((health, charge_session.describe(now)))
+}
+
+/// Print runtime per full charge for every month of history, to show whether a battery degrades
+pub fn print_report(
+    config: &Config,
+    store: &HistoryStore,
+    device: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let now = Local::now();
+    let devices = match device {
+        Some(device) => vec![device.to_string()],
+        None => store.known_devices()?,
+    };
+
+    for device in devices {
+        let Some(stats) = store.device_stats(&device)? else {
+            println!("{}: no history", config.display_name(&device));
+            continue;
+        };
+        println!(
+            "{} ({:.1} cycles since {})",
+            config.display_name(&device),
+            stats.charge_gained / 100.0,
+            stats.first_seen.format("%Y-%m-%d")
+        );
+
+        let mut first_runtime = None;
+        let mut month = first_of_month(stats.first_seen.date_naive());
+        while start_of_day(month) <= now {
+            let next = month + Months::new(1);
+            let rate = store.discharge_rate(&device, start_of_day(month), start_of_day(next))?;
+            match runtime(rate) {
+                Some(hours) => {
+                    let reference = *first_runtime.get_or_insert(hours);
+                    println!(
+                        "  {}  {:>5.1}h per charge  {:>+4.0}%",
+                        month.format("%Y-%m"),
+                        hours,
+                        (hours / reference - 1.0) * 100.0
+                    );
+                }
+                None => println!("  {}  not enough use", month.format("%Y-%m")),
+            }
+            month = next;
+        }
+    }
+    Ok(())
+}
+
+/// First day of the month `date` falls in
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
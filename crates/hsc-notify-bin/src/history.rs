@@ -0,0 +1,576 @@
+//! Persistent battery history in an embedded SQLite database
+
+use std::{collections::HashMap, error::Error, fs, path::Path, time::Duration};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use hsc_core::{
+    device::{BatteryStatus, Device},
+    monitor::Event,
+};
+
+/// Database schema, applied on every open
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS readings (
+    time INTEGER NOT NULL,
+    device TEXT NOT NULL,
+    level INTEGER,
+    status TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS readings_device_time ON readings (device, time);
+CREATE TABLE IF NOT EXISTS events (
+    time INTEGER NOT NULL,
+    device TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_device_time ON events (device, time);
+CREATE TABLE IF NOT EXISTS device_stats (
+    device TEXT PRIMARY KEY,
+    charge_gained REAL NOT NULL DEFAULT 0,
+    first_seen INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS hourly (
+    hour INTEGER NOT NULL,
+    device TEXT NOT NULL,
+    level REAL,
+    discharge_drop REAL NOT NULL,
+    discharge_seconds REAL NOT NULL,
+    charges INTEGER NOT NULL,
+    PRIMARY KEY (hour, device)
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// Columns comparing each reading with the previous one in window `w`
+const PAIR_COLUMNS: &str = "
+    LAG(time) OVER w AS prev_time,
+    LAG(level) OVER w AS prev_level,
+    LAG(status) OVER w AS prev_status";
+
+/// Aggregates over consecutive reading pairs selected with [`PAIR_COLUMNS`]
+const PAIR_TOTALS: &str = "
+    TOTAL(CASE WHEN status = 'discharging' AND prev_status = 'discharging'
+                    AND time - prev_time <= 600 AND prev_level > level
+               THEN prev_level - level ELSE 0 END) AS discharge_drop,
+    TOTAL(CASE WHEN status = 'discharging' AND prev_status = 'discharging'
+                    AND time - prev_time <= 600
+               THEN time - prev_time ELSE 0 END) AS discharge_seconds,
+    COUNT(CASE WHEN status = 'charging' AND prev_status != 'charging' THEN 1 END) AS charges";
+
+/// A stored battery reading
+pub struct Reading {
+    /// When the reading was taken
+    pub time: DateTime<Local>,
+    /// Device name
+    pub device: String,
+    /// Battery level, if reported
+    pub level: Option<u8>,
+    /// Battery status
+    pub status: BatteryStatus,
+}
+
+/// A stored notification
+pub struct StoredEvent {
+    /// When the notification was raised
+    pub time: DateTime<Local>,
+    /// Device name
+    pub device: String,
+    /// Notification body
+    pub message: String,
+}
+
+/// Discharge and charge totals over a period
+struct Totals {
+    /// Battery percentage lost while discharging
+    discharge_drop: f64,
+    /// Seconds spent discharging
+    discharge_seconds: f64,
+    /// Number of charges started
+    charges: u32,
+}
+
+/// Long-lived counters kept per device
+pub struct DeviceStats {
+    /// Total battery percentage gained while charging
+    pub charge_gained: f64,
+    /// When the device was first recorded
+    pub first_seen: DateTime<Local>,
+}
+
+/// Readings and events stored on disk
+pub struct HistoryStore {
+    conn: Connection,
+    /// Last recorded level and status per device, used to accumulate charge
+    last: HashMap<String, (Option<u8>, BatteryStatus)>,
+}
+
+impl HistoryStore {
+    /// Open the database at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(HistoryStore {
+            conn,
+            last: HashMap::new(),
+        })
+    }
+
+    /// Store one reading per device and accumulate charge gained since the previous reading
+    pub fn record_readings(
+        &mut self,
+        time: DateTime<Local>,
+        devices: &[Device],
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO readings (time, device, level, status) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut add_charge = tx.prepare_cached(
+                "INSERT INTO device_stats (device, charge_gained, first_seen) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (device) DO UPDATE SET charge_gained = charge_gained + ?2",
+            )?;
+            for device in devices {
+                insert.execute(params![
+                    time.timestamp(),
+                    device.name,
+                    device.level(),
+                    device.battery_status.as_str()
+                ])?;
+
+                let gained = match (self.last.get(&device.name), device.level()) {
+                    (Some(&(Some(previous), BatteryStatus::Charging)), Some(level))
+                        if device.battery_status == BatteryStatus::Charging =>
+                    {
+                        level.saturating_sub(previous)
+                    }
+                    _ => 0,
+                };
+                add_charge.execute(params![device.name, gained as f64, time.timestamp()])?;
+                self.last
+                    .insert(device.name.clone(), (device.level(), device.battery_status));
+            }
+        }
+        tx.commit()
+    }
+
+    /// Long-lived counters of a device, if it was ever recorded
+    pub fn device_stats(&self, device: &str) -> rusqlite::Result<Option<DeviceStats>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT charge_gained, first_seen FROM device_stats WHERE device = ?1",
+        )?;
+        let mut rows = query.query_map(params![device], |row| {
+            Ok(DeviceStats {
+                charge_gained: row.get(0)?,
+                first_seen: local_time(row.get(1)?),
+            })
+        })?;
+        rows.next().transpose()
+    }
+
+    /// Value stored under `key` by [`HistoryStore::set_meta`]
+    pub fn meta(&self, key: &str) -> rusqlite::Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Remember a small piece of daemon state across restarts
+    pub fn set_meta(&self, key: &str, value: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = ?2",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a value stored with [`HistoryStore::set_meta`]
+    pub fn remove_meta(&self, key: &str) -> rusqlite::Result<()> {
+        self.conn
+            .execute("DELETE FROM meta WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    /// Write a consistent copy of the whole database to `path`, which must not exist yet
+    pub fn copy_to(&self, path: &Path) -> rusqlite::Result<()> {
+        self.conn
+            .execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+        Ok(())
+    }
+
+    /// When a device last reached 100% while charging
+    pub fn last_full_charge(&self, device: &str) -> rusqlite::Result<Option<DateTime<Local>>> {
+        let time: Option<i64> = self.conn.query_row(
+            "SELECT MAX(time) FROM readings
+             WHERE device = ?1 AND status = 'charging' AND level >= 100",
+            params![device],
+            |row| row.get(0),
+        )?;
+        Ok(time.map(local_time))
+    }
+
+    /// Names of every device ever recorded
+    pub fn known_devices(&self) -> rusqlite::Result<Vec<String>> {
+        let mut query = self
+            .conn
+            .prepare_cached("SELECT device FROM device_stats ORDER BY device")?;
+        let rows = query.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Names of devices with readings between `since` and `until`
+    pub fn devices(
+        &self,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Vec<String>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT DISTINCT device FROM readings WHERE time >= ?1 AND time <= ?2 ORDER BY device",
+        )?;
+        let rows = query.query_map(params![since.timestamp(), until.timestamp()], |row| {
+            row.get(0)
+        })?;
+        rows.collect()
+    }
+
+    /// Battery percentage lost while discharging between `since` and `until`
+    pub fn discharge_drop(
+        &self,
+        device: &str,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<f64> {
+        Ok(self.totals(device, since, until)?.discharge_drop)
+    }
+
+    /// Time of the last discharging reading of a device between `since` and `until`
+    pub fn last_discharging(
+        &self,
+        device: &str,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Option<DateTime<Local>>> {
+        let time: Option<i64> = self.conn.query_row(
+            "SELECT MAX(time) FROM readings
+             WHERE device = ?1 AND status = 'discharging' AND time >= ?2 AND time < ?3",
+            params![device, since.timestamp(), until.timestamp()],
+            |row| row.get(0),
+        )?;
+        Ok(time.map(local_time))
+    }
+
+    /// Seconds spent discharging and number of charges started between `since` and `until`
+    pub fn usage(
+        &self,
+        device: &str,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<(f64, u32)> {
+        let totals = self.totals(device, since, until)?;
+        Ok((totals.discharge_seconds, totals.charges))
+    }
+
+    /// Discharge and charge totals from raw readings plus any compacted hours in the period
+    ///
+    /// Only intervals between consecutive discharging readings at most ten minutes apart are
+    /// counted as use, so time spent switched off or charging is left out.
+    fn totals(
+        &self,
+        device: &str,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Totals> {
+        let mut query = self.conn.prepare_cached(&format!(
+            "SELECT TOTAL(discharge_drop), TOTAL(discharge_seconds), TOTAL(charges)
+             FROM (
+                 SELECT {PAIR_TOTALS}
+                 FROM (
+                     SELECT time, level, status, {PAIR_COLUMNS}
+                     FROM readings
+                     WHERE device = ?1 AND time >= ?2 AND time <= ?3
+                     WINDOW w AS (ORDER BY time, rowid)
+                 )
+                 UNION ALL
+                 SELECT discharge_drop, discharge_seconds, charges
+                 FROM hourly
+                 WHERE device = ?1 AND hour >= ?2 AND hour <= ?3
+             )"
+        ))?;
+        query.query_row(
+            params![device, since.timestamp(), until.timestamp()],
+            |row| {
+                Ok(Totals {
+                    discharge_drop: row.get(0)?,
+                    discharge_seconds: row.get(1)?,
+                    charges: row.get::<_, f64>(2)? as u32,
+                })
+            },
+        )
+    }
+
+    /// Fold raw readings older than `raw_cutoff` into hourly aggregates, then drop aggregates and
+    /// notifications older than `aggregate_cutoff`
+    pub fn compact(
+        &mut self,
+        raw_cutoff: Option<DateTime<Local>>,
+        aggregate_cutoff: Option<DateTime<Local>>,
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        if let Some(cutoff) = raw_cutoff {
+            // Align to a whole hour so no hour is split between raw and aggregated data
+            let cutoff = cutoff.timestamp().div_euclid(3600) * 3600;
+            tx.execute(
+                &format!(
+                    "INSERT INTO hourly
+                         (hour, device, level, discharge_drop, discharge_seconds, charges)
+                     SELECT time / 3600 * 3600 AS hour, device, AVG(level), {PAIR_TOTALS}
+                     FROM (
+                         SELECT time, device, level, status, {PAIR_COLUMNS}
+                         FROM readings
+                         WHERE time < ?1
+                         WINDOW w AS (PARTITION BY device ORDER BY time, rowid)
+                     )
+                     GROUP BY device, hour
+                     ON CONFLICT (hour, device) DO UPDATE SET
+                         discharge_drop = discharge_drop + excluded.discharge_drop,
+                         discharge_seconds = discharge_seconds + excluded.discharge_seconds,
+                         charges = charges + excluded.charges"
+                ),
+                params![cutoff],
+            )?;
+            tx.execute("DELETE FROM readings WHERE time < ?1", params![cutoff])?;
+        }
+        if let Some(cutoff) = aggregate_cutoff {
+            tx.execute(
+                "DELETE FROM hourly WHERE hour < ?1",
+                params![cutoff.timestamp()],
+            )?;
+            tx.execute(
+                "DELETE FROM events WHERE time < ?1",
+                params![cutoff.timestamp()],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Store a notification
+    pub fn record_event(&self, event: &Event) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO events (time, device, message) VALUES (?1, ?2, ?3)",
+            params![event.time.timestamp(), event.device, event.message],
+        )?;
+        Ok(())
+    }
+
+    /// Readings taken between `since` and `until`, oldest first, optionally for one device only
+    pub fn readings(
+        &self,
+        device: Option<&str>,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Vec<Reading>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT time, device, level, status FROM readings
+             WHERE time >= ?1 AND time <= ?3 AND (?2 IS NULL OR device = ?2)
+             ORDER BY time, rowid",
+        )?;
+        let rows = query.query_map(
+            params![since.timestamp(), device, until.timestamp()],
+            |row| {
+                let status: String = row.get(3)?;
+                Ok(Reading {
+                    time: local_time(row.get(0)?),
+                    device: row.get(1)?,
+                    level: row.get(2)?,
+                    status: BatteryStatus::from_name(&status)
+                        .unwrap_or(BatteryStatus::Disconnected),
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// Average discharge rate of a device in %/h between `since` and `until`
+    ///
+    /// Returns `None` with less than an hour of use in the period.
+    pub fn discharge_rate(
+        &self,
+        device: &str,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Option<f64>> {
+        let totals = self.totals(device, since, until)?;
+        if totals.discharge_seconds < 3600.0 {
+            return Ok(None);
+        }
+        Ok(Some(
+            totals.discharge_drop / (totals.discharge_seconds / 3600.0),
+        ))
+    }
+
+    /// Notifications raised between `since` and `until`, oldest first, optionally for one device
+    /// only
+    pub fn events(
+        &self,
+        device: Option<&str>,
+        since: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> rusqlite::Result<Vec<StoredEvent>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT time, device, message FROM events
+             WHERE time >= ?1 AND time <= ?3 AND (?2 IS NULL OR device = ?2)
+             ORDER BY time, rowid",
+        )?;
+        let rows = query.query_map(
+            params![since.timestamp(), device, until.timestamp()],
+            |row| {
+                Ok(StoredEvent {
+                    time: local_time(row.get(0)?),
+                    device: row.get(1)?,
+                    message: row.get(2)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+}
+
+/// Convert a stored unix timestamp to local time
+pub fn local_time(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_default()
+}
+
+/// Local midnight at the start of `date`
+pub fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| midnight.and_utc().with_timezone(&Local))
+}
+
+/// Longest age accepted by [`parse_age`], ten years
+pub const MAX_AGE: Duration = Duration::from_secs(520 * 7 * 24 * 60 * 60);
+
+/// Parse a relative age such as `30m`, `12h`, `7d` or `2w`, up to [`MAX_AGE`]
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let age = age.trim();
+    let split = age
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{}' (expected m, h, d or w)", age))?;
+    let (amount, unit) = age.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid amount in '{}'", age))?;
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' (expected m, h, d or w)", unit)),
+    };
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .filter(|age| *age <= MAX_AGE)
+        .ok_or_else(|| format!("'{}' is longer than ten years", age))
+}
+
+/// The time `age` before `now`, or an error if that cannot be represented
+pub fn ago(now: DateTime<Local>, age: Duration) -> Result<DateTime<Local>, String> {
+    chrono::Duration::from_std(age)
+        .ok()
+        .and_then(|age| now.checked_sub_signed(age))
+        .ok_or_else(|| format!("{}s ago is out of range", age.as_secs()))
+}
+
+/// A period relative to now, such as the last week or the week before that
+#[derive(Clone, Copy, Debug)]
+pub struct AgeRange {
+    /// How long ago the period starts
+    pub since: Duration,
+    /// How long ago the period ends
+    pub until: Duration,
+}
+
+impl AgeRange {
+    /// Absolute start and end of the period
+    pub fn resolve(
+        self,
+        now: DateTime<Local>,
+    ) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+        Ok((ago(now, self.since)?, ago(now, self.until)?))
+    }
+}
+
+/// Parse a range of ages such as `7d` (the last week) or `2w..1w` (the week before), each up to
+/// [`MAX_AGE`]
+pub fn parse_range(range: &str) -> Result<AgeRange, String> {
+    let (since, until) = match range.split_once("..") {
+        Some((since, until)) => (parse_age(since)?, parse_age(until)?),
+        None => (parse_age(range)?, Duration::ZERO),
+    };
+    if until > since {
+        return Err(format!("'{}' ends before it starts", range));
+    }
+    Ok(AgeRange { since, until })
+}
+
+/// Print readings or notifications from the last `since` to standard output
+///
+/// Only readings that differ from the previous one for the same device are shown.
+pub fn print(
+    store: &HistoryStore,
+    device: Option<&str>,
+    since: Duration,
+    events: bool,
+) -> Result<(), Box<dyn Error>> {
+    let now = Local::now();
+    let since = ago(now, since)?;
+
+    if events {
+        for event in store.events(device, since, now)? {
+            println!(
+                "{}  {}: {}",
+                event.time.format("%Y-%m-%d %H:%M:%S"),
+                event.device,
+                event.message
+            );
+        }
+        return Ok(());
+    }
+
+    let mut last: HashMap<String, (Option<u8>, BatteryStatus)> = HashMap::new();
+    for reading in store.readings(device, since, now)? {
+        let state = (reading.level, reading.status);
+        if last.get(&reading.device) == Some(&state) {
+            continue;
+        }
+        println!(
+            "{}  {:<30} {:>4}  {}",
+            reading.time.format("%Y-%m-%d %H:%M:%S"),
+            reading.device,
+            reading
+                .level
+                .map_or_else(|| "-".to_string(), |l| format!("{}%", l)),
+            reading.status.as_str()
+        );
+        last.insert(reading.device, state);
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Presentation state for panel indicators and menus
+//!
+//! Everything a front end needs to render the notifier — which device to show, which icon,
+//! the tooltip, and the available actions — is derived here so indicators stay thin.
+
+use std::{sync::Mutex, time::Duration};
+
+use hsc_backends::headsetcontrol::HeadsetControl;
+use hsc_core::{
+    command::HostRunner,
+    control::{self, Setting, SettingsStore},
+    device::{BatteryStatus, Device},
+    monitor::Monitor,
+};
+
+/// How long the "snooze" menu action lasts
+pub const SNOOZE_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// An action offered to the user, identified by a stable id
+pub struct MenuAction {
+    /// Identifier passed back to [`activate`]
+    pub id: &'static str,
+    /// Human readable label
+    pub label: String,
+}
+
+/// The device an indicator should represent
+///
+/// This is the configured primary device if it is connected, otherwise the connected device
+/// with the lowest battery level.
+pub fn primary_device(monitor: &Monitor) -> Option<&Device> {
+    let connected = |d: &&Device| d.battery_status != BatteryStatus::Disconnected;
+
+    monitor
+        .config
+        .primary_device
+        .as_ref()
+        .and_then(|name| monitor.devices.get(name))
+        .filter(connected)
+        .or_else(|| {
+            monitor
+                .devices
+                .values()
+                .filter(connected)
+                .min_by_key(|d| (d.level().unwrap_or(u8::MAX), d.name.clone()))
+        })
+}
+
+/// Freedesktop icon name describing a device's battery
+pub fn icon_name(device: Option<&Device>) -> &'static str {
+    let Some(device) = device else {
+        return "battery-missing";
+    };
+    let charging = device.battery_status == BatteryStatus::Charging;
+    match (device.level(), charging) {
+        (None, _) => "battery-missing",
+        (Some(90..), false) => "battery-full",
+        (Some(90..), true) => "battery-full-charging",
+        (Some(50..), false) => "battery-good",
+        (Some(50..), true) => "battery-good-charging",
+        (Some(20..), false) => "battery-low",
+        (Some(20..), true) => "battery-low-charging",
+        (Some(5..), false) => "battery-caution",
+        (Some(5..), true) => "battery-caution-charging",
+        (Some(_), false) => "battery-empty",
+        (Some(_), true) => "battery-empty-charging",
+    }
+}
+
+/// Pango markup summarising every connected device
+pub fn tooltip_markup(monitor: &Monitor) -> String {
+    let mut devices: Vec<&Device> = monitor
+        .devices
+        .values()
+        .filter(|d| d.battery_status != BatteryStatus::Disconnected)
+        .collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if devices.is_empty() {
+        return "No devices connected".to_string();
+    }
+
+    let mut lines: Vec<String> = devices
+        .iter()
+        .map(|d| {
+            let level = d.battery.map_or("?".to_string(), |b| b.to_string());
+            let charging = if d.battery_status == BatteryStatus::Charging {
+                " (charging)"
+            } else {
+                ""
+            };
+            let estimate = monitor
+                .estimate(&d.name)
+                .map(|e| format!(", {}", e))
+                .unwrap_or_default();
+            let health = monitor
+                .health
+                .get(&d.name)
+                .and_then(|h| h.percent())
+                .map(|p| format!(", health ≈{:.0}%", p))
+                .unwrap_or_default();
+            let session = monitor
+                .sessions
+                .get(&d.name)
+                .map(|s| format!("\n{}", s.describe(monitor.clock().local())))
+                .unwrap_or_default();
+            let chatmix = monitor
+                .extras
+                .get(&d.name)
+                .and_then(|e| e.chatmix)
+                .map(|c| format!("\nChatmix {}", c))
+                .unwrap_or_default();
+            format!(
+                "<b>{}</b> {}{}{}{}{}{}",
+                escape_markup(monitor.config.display_name(&d.name)),
+                level,
+                charging,
+                estimate,
+                health,
+                session,
+                chatmix
+            )
+        })
+        .collect();
+
+    if monitor.paused {
+        lines.push("<i>Notifications paused</i>".to_string());
+    } else if let Some(remaining) = monitor.snooze_remaining() {
+        lines.push(format!(
+            "<i>Notifications snoozed for {}m</i>",
+            remaining.as_secs().div_ceil(60)
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Actions currently available
+pub fn menu_actions(monitor: &Monitor) -> Vec<MenuAction> {
+    let mut actions = Vec::new();
+    if monitor.paused {
+        actions.push(MenuAction {
+            id: "resume",
+            label: "Resume notifications".to_string(),
+        });
+    } else {
+        actions.push(MenuAction {
+            id: "pause",
+            label: "Pause notifications".to_string(),
+        });
+    }
+    if monitor.snooze_remaining().is_some() {
+        actions.push(MenuAction {
+            id: "unsnooze",
+            label: "Cancel snooze".to_string(),
+        });
+    } else {
+        actions.push(MenuAction {
+            id: "snooze",
+            label: format!(
+                "Snooze notifications for {}m",
+                SNOOZE_DURATION.as_secs() / 60
+            ),
+        });
+    }
+    if let Some(device) = primary_device(monitor) {
+        actions.push(MenuAction {
+            id: "toggle-lights",
+            label: if lights_on(monitor, &device.name) {
+                "Turn lights off".to_string()
+            } else {
+                "Turn lights on".to_string()
+            },
+        });
+    }
+    actions
+}
+
+/// Run the action with the given id
+///
+/// `monitor` is only locked while its state is read or changed, not while headsetcontrol runs.
+pub fn activate(shared: &Mutex<Monitor>, id: &str) -> Result<(), String> {
+    let mut monitor = shared.lock().unwrap_or_else(|e| e.into_inner());
+    match id {
+        "pause" => monitor.paused = true,
+        "resume" => monitor.paused = false,
+        "snooze" => monitor.snooze(Some(SNOOZE_DURATION)),
+        "unsnooze" => monitor.snooze(None),
+        "toggle-lights" => {
+            let name = primary_device(&monitor)
+                .map(|d| d.name.clone())
+                .ok_or("no device connected")?;
+            let setting = Setting::Lights(!lights_on(&monitor, &name));
+            let controller = HeadsetControl(HostRunner::new(monitor.config.remote.as_deref()));
+            drop(monitor);
+            let settings =
+                control::apply(&controller, &SettingsStore::default_path(), &name, setting)
+                    .map_err(|e| format!("failed to switch lights: {}", e))?;
+            shared.lock().unwrap_or_else(|e| e.into_inner()).settings = settings;
+        }
+        _ => return Err(format!("unknown action: {}", id)),
+    }
+    Ok(())
+}
+
+/// Whether the lights of the named device were last switched on, assuming they are until they
+/// were switched off
+fn lights_on(monitor: &Monitor, name: &str) -> bool {
+    monitor.settings.get(name).lights.unwrap_or(true)
+}
+
+/// Escape text for inclusion in Pango markup
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,422 @@
+//! Everything known about one device, for troubleshooting
+//!
+//! `inspect` gathers what is otherwise spread over `status`, `events`, `history` and the daemon
+//! into one dump: where readings come from and what the backend printed, how the configuration
+//! applies to the device, and, when reachable, the daemon's state machine and the history store.
+
+use std::error::Error;
+
+#[cfg(feature = "history")]
+use chrono::{Duration as ChronoDuration, Local};
+
+use hsc_backends::headsetcontrol::headsetcontrol_output;
+#[cfg(feature = "plugins")]
+use hsc_backends::plugin;
+use hsc_core::{
+    command::HostRunner,
+    config::Config,
+    control::SettingsStore,
+    device::{parse_devices, Device},
+    monitor::Monitor,
+};
+
+use crate::table::{level_cell, status_cell, Cell, Style, Table};
+
+/// Poll `device` (a name or alias) once and print every section about it to standard output,
+/// colored if `color` is set
+pub fn print(config: &Config, device: &str, color: bool) -> Result<(), Box<dyn Error>> {
+    let name = config.resolve_device(device);
+    let sections = [
+        backend(config, name),
+        configuration(config, name),
+        #[cfg(feature = "extras")]
+        extras(config, name),
+        settings(name)?,
+        #[cfg(feature = "dbus")]
+        daemon(name),
+        #[cfg(feature = "history")]
+        history(config, name)?,
+    ];
+    let sections: Vec<String> = sections.iter().map(|s| s.render(color)).collect();
+    print!("{}", sections.join("\n"));
+    Ok(())
+}
+
+/// Width labels are padded to, so the values of every section line up
+const LABEL_WIDTH: usize = 16;
+
+/// Section listing (label, value) rows under `title`
+fn section(title: &'static str, rows: Vec<(&str, Cell)>) -> Table {
+    let mut table = Table::new(&[title, ""]);
+    for (label, value) in rows {
+        field(&mut table, label, value);
+    }
+    table
+}
+
+/// Add a row showing `value` next to `label` to a section
+fn field(table: &mut Table, label: &str, value: impl Into<Cell>) {
+    table.row(vec![
+        format!("{:<LABEL_WIDTH$}", label).into(),
+        value.into(),
+    ]);
+}
+
+/// Which backend reports the device, what it printed and the reading parsed from it
+fn backend(config: &Config, name: &str) -> Table {
+    let output = match headsetcontrol_output(&HostRunner::new(config.remote.as_deref())) {
+        Ok(output) => output,
+        Err(e) => {
+            let mut table = section("BACKEND", Vec::new());
+            field(
+                &mut table,
+                "headsetcontrol",
+                Cell::new(format!("error: {}", e), Style::Red),
+            );
+            return plugin_backend(table, config, name);
+        }
+    };
+    let Some(raw) = raw_block(&output, name) else {
+        let mut table = section("BACKEND", Vec::new());
+        field(
+            &mut table,
+            "headsetcontrol",
+            Cell::new("device not reported", Style::Dim),
+        );
+        return plugin_backend(table, config, name);
+    };
+
+    let backend = match &config.remote {
+        Some(remote) => format!("headsetcontrol on {}", remote),
+        None => "headsetcontrol".to_string(),
+    };
+    let mut table = section("BACKEND", vec![("backend", backend.into())]);
+    let mut lines = raw.lines();
+    field(&mut table, "raw output", lines.next().unwrap_or_default());
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        table.note(line.trim_end());
+    }
+    if let Some(device) = parse_devices(&raw).into_iter().next() {
+        reading_rows(&mut table, config, device);
+    }
+    table
+}
+
+/// Add the device as reported by a provider plugin to `table`, if any reports it
+#[cfg(feature = "plugins")]
+fn plugin_backend(mut table: Table, config: &Config, name: &str) -> Table {
+    for provider in &config.plugins.providers {
+        let label = format!("plugin {}", provider.command);
+        match plugin::poll(provider) {
+            Ok(poll) => match poll.devices.into_iter().find(|d| d.name == name) {
+                Some(device) => {
+                    field(&mut table, "backend", label);
+                    reading_rows(&mut table, config, device);
+                    if let Some((_, version)) = poll.firmware.iter().find(|(n, _)| n == name) {
+                        field(&mut table, "firmware", version.as_str());
+                    }
+                    return table;
+                }
+                None => field(
+                    &mut table,
+                    &label,
+                    Cell::new("device not reported", Style::Dim),
+                ),
+            },
+            Err(e) => field(
+                &mut table,
+                &label,
+                Cell::new(format!("error: {}", e), Style::Red),
+            ),
+        }
+    }
+    table
+}
+
+/// Providers cannot be asked for the device, as this build has no plugin support
+#[cfg(not(feature = "plugins"))]
+fn plugin_backend(table: Table, _config: &Config, _name: &str) -> Table {
+    table
+}
+
+/// Add the status and level of a reading of the device to `table`, with the level rounded to
+/// the configured bucket
+fn reading_rows(table: &mut Table, config: &Config, mut device: Device) {
+    let exact = device.battery;
+    let bucket = config.level_bucket_for(&device.name);
+    device.battery = device.battery.map(|b| b.bucketed(bucket));
+    field(table, "status", status_cell(&device));
+    field(table, "level", level_cell(config, &device));
+    if let Some(exact) = exact.filter(|_| exact != device.battery) {
+        table.note(format!("reported as {}", exact));
+    }
+}
+
+/// Block of `headsetcontrol -b` output describing the device named `name`
+fn raw_block(output: &str, name: &str) -> Option<String> {
+    output
+        .split("Found")
+        .find(|block| parse_devices(block).iter().any(|d| d.name == name))
+        .map(|block| format!("Found{}", block))
+}
+
+/// How the configuration applies to the device
+fn configuration(config: &Config, name: &str) -> Table {
+    let rule = config.rule(name);
+    let minutes = config.threshold_minutes_for(name);
+    let options = config.notification_options(name, "battery");
+    section(
+        "CONFIGURATION",
+        vec![
+            ("name", name.into()),
+            ("display name", config.display_name(name).into()),
+            (
+                "device rule",
+                if rule.is_some() { "yes" } else { "no" }.into(),
+            ),
+            (
+                "low threshold",
+                format!("{}%", config.threshold_for(name)).into(),
+            ),
+            (
+                "time threshold",
+                minutes
+                    .map_or("-".to_string(), |m| format!("{}m", m))
+                    .into(),
+            ),
+            (
+                "level bucket",
+                format!("{}%", config.level_bucket_for(name)).into(),
+            ),
+            (
+                "muted",
+                if config.is_muted(name) { "yes" } else { "no" }.into(),
+            ),
+            ("icon", config.icon_for(name, "-").into()),
+            ("app name", options.app_name.unwrap_or("-").into()),
+            ("group", options.group.unwrap_or("-").into()),
+        ],
+    )
+}
+
+/// Capabilities and chatmix reported by headsetcontrol
+#[cfg(feature = "extras")]
+fn extras(config: &Config, name: &str) -> Table {
+    let runner = HostRunner::new(config.remote.as_deref());
+    let extras = match hsc_backends::headsetcontrol::extras_with(&runner) {
+        Ok(extras) => extras.into_iter().find(|(n, _)| n == name).map(|(_, e)| e),
+        Err(e) => {
+            return section(
+                "EXTRAS",
+                vec![("error", Cell::new(e.to_string(), Style::Red))],
+            )
+        }
+    };
+    let Some(extras) = extras else {
+        return section(
+            "EXTRAS",
+            vec![("extras", Cell::new("not reported", Style::Dim))],
+        );
+    };
+    let capabilities = if extras.capabilities.is_empty() {
+        "-".to_string()
+    } else {
+        extras.capabilities.join(", ")
+    };
+    section(
+        "EXTRAS",
+        vec![
+            ("capabilities", capabilities.into()),
+            (
+                "chatmix",
+                extras
+                    .chatmix
+                    .map_or("-".to_string(), |c| c.to_string())
+                    .into(),
+            ),
+        ],
+    )
+}
+
+/// Settings last applied through the notifier
+fn settings(name: &str) -> Result<Table, Box<dyn Error>> {
+    let applied = SettingsStore::load(&SettingsStore::default_path())?.get(name);
+    let shown = |value: Option<String>| match value {
+        Some(value) => Cell::from(value),
+        None => Cell::new("not set", Style::Dim),
+    };
+    Ok(section(
+        "APPLIED SETTINGS",
+        vec![
+            (
+                "lights",
+                shown(
+                    applied
+                        .lights
+                        .map(|on| if on { "on" } else { "off" }.to_string()),
+                ),
+            ),
+            ("sidetone", shown(applied.sidetone.map(|s| s.to_string()))),
+            (
+                "inactive time",
+                shown(applied.inactive_time.map(|m| match m {
+                    0 => "off".to_string(),
+                    m => format!("{}m", m),
+                })),
+            ),
+        ],
+    ))
+}
+
+/// State of the running daemon for the device, asked over D-Bus
+#[cfg(feature = "dbus")]
+fn daemon(name: &str) -> Table {
+    use zbus::blocking::Connection;
+
+    use crate::dbus::ControlClientProxyBlocking;
+
+    let state = Connection::session()
+        .and_then(|conn| ControlClientProxyBlocking::new(&conn))
+        .and_then(|control| control.inspect(name));
+    match state {
+        Ok(state) if state.is_empty() => section(
+            "DAEMON",
+            vec![("state", Cell::new("device not seen", Style::Dim))],
+        ),
+        Ok(state) => {
+            let mut table = section("DAEMON", Vec::new());
+            for (label, value) in state {
+                field(&mut table, &label, value);
+            }
+            table
+        }
+        Err(e) => section(
+            "DAEMON",
+            vec![(
+                "state",
+                Cell::new(format!("not reachable: {}", e), Style::Dim),
+            )],
+        ),
+    }
+}
+
+/// What `monitor` knows about the device as (label, value) pairs, empty if it was never seen
+///
+/// Served by the daemon over D-Bus for `inspect`.
+pub fn daemon_state(monitor: &Monitor, name: &str) -> Vec<(String, String)> {
+    let Some(device) = monitor.devices.get(name) else {
+        return Vec::new();
+    };
+    let shown = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let notifications = if monitor.config.is_muted(name) {
+        "muted".to_string()
+    } else if monitor.paused {
+        "paused".to_string()
+    } else if let Some(remaining) = monitor.snooze_remaining() {
+        format!("snoozed for {}m", remaining.as_secs().div_ceil(60))
+    } else if monitor.config.hold_while_screen_sharing && monitor.is_screen_sharing() {
+        "held while the screen is shared".to_string()
+    } else if monitor.config.hold_during_calls && monitor.is_in_call() {
+        "held during a call".to_string()
+    } else {
+        "active".to_string()
+    };
+    let events: Vec<_> = monitor.events.iter().filter(|e| e.device == name).collect();
+    let last_event = events.last().map(|e| {
+        format!(
+            "{} {}{}",
+            e.time.format("%H:%M"),
+            e.message,
+            if e.suppressed { " (suppressed)" } else { "" }
+        )
+    });
+    vec![
+        ("status", device.battery_status.as_str().to_string()),
+        ("level", shown(device.battery.map(|b| b.to_string()))),
+        (
+            "last notified",
+            shown(device.last_notif_battery_level.map(|l| format!("{}%", l))),
+        ),
+        (
+            "estimate",
+            shown(monitor.estimate(name).map(|e| e.to_string())),
+        ),
+        (
+            "session",
+            shown(
+                monitor
+                    .sessions
+                    .get(name)
+                    .map(|s| s.describe(monitor.clock().local())),
+            ),
+        ),
+        (
+            "health",
+            shown(monitor.health.get(name).map(|h| h.to_string())),
+        ),
+        (
+            "idle shutdown",
+            if monitor.turned_off_idle(name) {
+                "yes"
+            } else {
+                "no"
+            }
+            .to_string(),
+        ),
+        ("firmware", shown(monitor.firmware.get(name).cloned())),
+        ("notifications", notifications),
+        ("recent events", events.len().to_string()),
+        ("last event", shown(last_event)),
+    ]
+    .into_iter()
+    .map(|(label, value)| (label.to_string(), value))
+    .collect()
+}
+
+/// Summary of what the history store recorded about the device
+#[cfg(feature = "history")]
+fn history(config: &Config, name: &str) -> Result<Table, Box<dyn Error>> {
+    if !config.history.enabled {
+        return Ok(section(
+            "HISTORY",
+            vec![("history", Cell::new("disabled", Style::Dim))],
+        ));
+    }
+    let store = crate::history::HistoryStore::open(&config.history.path())?;
+    let Some(stats) = store.device_stats(name)? else {
+        return Ok(section(
+            "HISTORY",
+            vec![("history", Cell::new("device never recorded", Style::Dim))],
+        ));
+    };
+    let now = Local::now();
+    let readings = store.readings(Some(name), now - ChronoDuration::days(1), now)?;
+    let events = store.events(Some(name), now - ChronoDuration::days(7), now)?;
+    let (health, session) = crate::health::details(&store, name, now)?;
+    let time = |t: chrono::DateTime<Local>| t.format("%Y-%m-%d %H:%M").to_string();
+    let shown = |value: String| {
+        if value.is_empty() {
+            Cell::new("-", Style::Dim)
+        } else {
+            Cell::from(value)
+        }
+    };
+    Ok(section(
+        "HISTORY",
+        vec![
+            ("first seen", time(stats.first_seen).into()),
+            (
+                "last reading",
+                shown(readings.last().map(|r| time(r.time)).unwrap_or_default()),
+            ),
+            ("readings (1d)", readings.len().to_string().into()),
+            ("events (7d)", events.len().to_string().into()),
+            (
+                "last full charge",
+                shown(store.last_full_charge(name)?.map(time).unwrap_or_default()),
+            ),
+            ("health", shown(health)),
+            ("session", shown(session)),
+        ],
+    ))
+}
@@ -0,0 +1,56 @@
+//! Headset Battery Notifier
+//!
+//! The daemon, history store, D-Bus service and terminal and graphical front ends built on
+//! [`hsc_core`] and [`hsc_backends`].
+//!
+//! Optional subsystems are gated behind Cargo features: `history` for the SQLite store and
+//! everything computed from it, `dbus` for the D-Bus service and its clients, `extras` for
+//! chatmix and headset capabilities, `pactl` for PulseAudio integration, `plugins` for external
+//! providers and sinks, `screencast` for detecting screen sharing, `desktop` for desktop
+//! notifications, `tui` for the dashboard, `gui` for the settings window, `osd` for the Wayland
+//! on-screen alert, `scripting` for Rhai notification rules and `wasm` for WebAssembly plugins.
+//! All but `gui`, `osd`, `scripting` and `wasm` are enabled by default.
+
+pub mod check;
+pub mod control;
+pub mod csv_log;
+pub mod daemon;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+#[cfg(feature = "dbus")]
+pub mod events;
+#[cfg(feature = "history")]
+pub mod export;
+#[cfg(feature = "history")]
+pub mod forecast;
+#[cfg(feature = "history")]
+pub mod graph;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "history")]
+pub mod health;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod indicator;
+pub mod inspect;
+#[cfg(feature = "dbus")]
+pub mod logind;
+#[cfg(feature = "dbus")]
+pub mod menu;
+pub mod once;
+#[cfg(feature = "osd")]
+pub mod osd;
+#[cfg(feature = "history")]
+pub mod recorder;
+#[cfg(feature = "scripting")]
+pub mod rules;
+#[cfg(feature = "history")]
+pub mod session;
+#[cfg(feature = "history")]
+pub mod state;
+pub mod status;
+#[cfg(feature = "history")]
+pub mod summary;
+pub mod table;
+#[cfg(feature = "tui")]
+pub mod tui;
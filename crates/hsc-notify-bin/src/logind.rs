@@ -0,0 +1,72 @@
+//! Charge reminder before the computer shuts down, through systemd-logind
+//!
+//! A delay inhibitor holds back shutdowns and reboots just long enough to send the reminder when
+//! logind announces one with `PrepareForShutdown`. Logging out without shutting down is not
+//! covered: logind has no delay lock for the end of a session.
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use zbus::{blocking::Connection, proxy, zvariant::OwnedFd};
+
+use hsc_core::monitor::Monitor;
+
+/// Client side of the logind manager interface
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    /// Take an inhibitor lock, held until the returned descriptor is closed
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// Emitted before a shutdown or reboot with `start` set, and after a cancelled one without
+    #[zbus(signal)]
+    fn prepare_for_shutdown(&self, start: bool) -> zbus::Result<()>;
+}
+
+/// Remind to charge devices running low whenever the computer shuts down, from a background
+/// thread
+pub fn watch(monitor: Arc<Mutex<Monitor>>) {
+    thread::spawn(move || {
+        if let Err(e) = remind_on_shutdown(&monitor) {
+            eprintln!("shutdown reminder unavailable: {}", e);
+        }
+    });
+}
+
+/// Delay every shutdown until the reminder is sent
+fn remind_on_shutdown(monitor: &Mutex<Monitor>) -> zbus::Result<()> {
+    let conn = Connection::system()?;
+    let manager = Login1ManagerProxyBlocking::new(&conn)?;
+    // Subscribe before taking the lock so no shutdown goes unnoticed
+    let signals = manager.receive_prepare_for_shutdown()?;
+    let mut inhibitor = Some(inhibit(&manager)?);
+    for signal in signals {
+        if signal.args()?.start {
+            monitor
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remind_to_charge();
+            // Notifications are delivered by now, so the shutdown may go on
+            drop(inhibitor.take());
+        } else if inhibitor.is_none() {
+            // The shutdown was cancelled, so be ready for the next one
+            inhibitor = Some(inhibit(&manager)?);
+        }
+    }
+    Ok(())
+}
+
+/// Take a delay lock on shutdowns
+fn inhibit(manager: &Login1ManagerProxyBlocking) -> zbus::Result<OwnedFd> {
+    manager.inhibit(
+        "shutdown",
+        "Headset Battery Notifier",
+        "Remind to charge headsets running low",
+        "delay",
+    )
+}
@@ -0,0 +1,409 @@
+//! Command line interface of the headset battery notifier
+//!
+//! All functionality lives in the library crate; this binary only parses arguments and
+//! dispatches to it.
+
+use std::path::PathBuf;
+#[cfg(feature = "history")]
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use hsc_core::{
+    config::Config,
+    control::{Setting, INACTIVE_TIME_MAX, SIDETONE_MAX},
+};
+#[cfg(feature = "gui")]
+use hsc_notify::gui;
+use hsc_notify::{
+    check::{self, Thresholds},
+    control, daemon, inspect, once, status,
+    table::ColorChoice,
+};
+#[cfg(feature = "dbus")]
+use hsc_notify::{events, menu};
+#[cfg(feature = "history")]
+use hsc_notify::{
+    export::{self, ExportFormat},
+    graph, health,
+    history::{self, AgeRange, HistoryStore},
+    state,
+};
+
+/// Command line interface
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Path to the configuration file
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Run headsetcontrol on this SSH destination (`user@host`) instead of locally
+    #[arg(long, global = true)]
+    remote: Option<String>,
+    /// When to color tables; `auto` colors terminals unless NO_COLOR is set
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// Subcommands of `history`
+#[cfg(feature = "history")]
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Dump stored readings or notifications as CSV or JSON
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Only export this device (name or alias)
+        #[arg(long)]
+        device: Option<String>,
+        /// Period to export, e.g. 7d for the last week or 2w..1w for the week before, up to ten
+        /// years back
+        #[arg(long, default_value = "30d", value_parser = history::parse_range)]
+        range: AgeRange,
+        /// Export notifications instead of battery readings
+        #[arg(long)]
+        events: bool,
+    },
+}
+
+/// State given to `set-lights`
+#[derive(Clone, Copy, ValueEnum)]
+enum Switch {
+    On,
+    Off,
+}
+
+/// Subcommands of `config`
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the JSON Schema of the configuration file
+    Schema,
+}
+
+/// Available subcommands
+#[derive(Subcommand)]
+enum Commands {
+    /// Run the notification daemon (default)
+    Run,
+    /// Poll once and notify about changes since the last run, for cron or systemd timers
+    Once,
+    /// Show a live dashboard of devices, battery history and recent events, without notifying
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Print a dmenu/rofi menu of devices and actions, or run the selected action
+    #[cfg(feature = "dbus")]
+    Menu {
+        /// A line previously printed by this command
+        selection: Option<String>,
+    },
+    /// Print the current state of every device, with charge cycles and battery health
+    Status {
+        /// Print a JSON status report instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check battery levels as a Nagios/Icinga plugin, exiting with the state
+    Check {
+        /// Warn about discharging devices below this level
+        #[arg(short, long, default_value_t = 20, value_parser = clap::value_parser!(u8).range(..=100))]
+        warning: u8,
+        /// Report discharging devices below this level as critical, at most the warning level
+        #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(..=100))]
+        critical: u8,
+        /// Only check this device (name or alias), critical if it is not connected
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Print everything known about one device, for troubleshooting
+    Inspect {
+        /// Device to inspect (name or alias)
+        device: String,
+    },
+    /// Switch the headset lights on or off
+    SetLights {
+        #[arg(value_enum)]
+        state: Switch,
+        /// Device to change (name or alias), the first connected one by default
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Set how loud the microphone is played back in the headset
+    SetSidetone {
+        /// Level from 0 (off) to 128
+        #[arg(value_parser = clap::value_parser!(u8).range(..=SIDETONE_MAX as i64))]
+        level: u8,
+        /// Device to change (name or alias), the first connected one by default
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Set after how long without audio the headset turns itself off
+    SetInactiveTime {
+        /// Minutes from 1 to 90, or 0 to never turn off
+        #[arg(value_parser = clap::value_parser!(u8).range(..=INACTIVE_TIME_MAX as i64))]
+        minutes: u8,
+        /// Device to change (name or alias), the first connected one by default
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Print recent notifications, including ones that were paused or snoozed
+    #[cfg(feature = "dbus")]
+    Events {
+        /// Keep running and print new notifications as they are raised
+        #[arg(long, short)]
+        follow: bool,
+        /// Number of past notifications to print
+        #[arg(long, short = 'n', default_value_t = 20)]
+        count: usize,
+    },
+    /// Compare runtime per full charge across months to spot battery degradation
+    #[cfg(feature = "history")]
+    Report {
+        /// Only report on this device (name or alias)
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Show stored battery readings or notifications
+    #[cfg(feature = "history")]
+    #[command(args_conflicts_with_subcommands = true)]
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+        /// Only show this device (name or alias)
+        #[arg(long)]
+        device: Option<String>,
+        /// How far back to look, e.g. 30m, 12h, 7d or 2w, up to ten years
+        #[arg(long, default_value = "1d", value_parser = history::parse_age)]
+        since: Duration,
+        /// Show notifications instead of battery readings
+        #[arg(long)]
+        events: bool,
+    },
+    /// Draw a sparkline of stored battery levels
+    #[cfg(feature = "history")]
+    Graph {
+        /// Only show this device (name or alias)
+        #[arg(long)]
+        device: Option<String>,
+        /// How many hours back to show, up to ten years
+        #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u64).range(1..=24 * 366 * 10))]
+        hours: u64,
+        /// Number of columns of the graph
+        #[arg(long, default_value_t = 60)]
+        width: usize,
+    },
+    /// Bundle the configuration and battery history into one file for another machine
+    #[cfg(feature = "history")]
+    ExportState {
+        /// File to create
+        path: PathBuf,
+    },
+    /// Restore the configuration and battery history from a bundle made by export-state
+    #[cfg(feature = "history")]
+    ImportState {
+        /// Bundle to read
+        path: PathBuf,
+        /// Replace an existing configuration and history database
+        #[arg(long)]
+        force: bool,
+    },
+    /// Open the settings window
+    #[cfg(feature = "gui")]
+    Settings,
+    /// Inspect the configuration file format
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Commands::Run);
+    // Works without a valid configuration, which is what it helps to write
+    if let Commands::Config {
+        action: ConfigAction::Schema,
+    } = command
+    {
+        match serde_json::to_string_pretty(&Config::json_schema()) {
+            Ok(schema) => println!("{}", schema),
+            Err(e) => {
+                eprintln!("schema error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let config_path = cli.config.unwrap_or_else(Config::default_path);
+    let mut config = match Config::load(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if cli.remote.is_some() {
+        config.remote = cli.remote;
+    }
+
+    match command {
+        Commands::Run => daemon::run(config, cli.color.enabled()),
+        Commands::Once => {
+            if let Err(e) = once::run(config) {
+                eprintln!("once error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "tui")]
+        Commands::Tui => {
+            if let Err(e) = hsc_notify::tui::run(hsc_core::Monitor::new(config)) {
+                eprintln!("tui error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "dbus")]
+        Commands::Menu { selection } => {
+            if let Err(e) = menu::run(selection.as_deref()) {
+                eprintln!("menu error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Status { json } => {
+            let result = if json {
+                status::print_json(&config)
+            } else {
+                status::print_status(&config, cli.color.enabled())
+            };
+            if let Err(e) = result {
+                eprintln!("status error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::History {
+            action:
+                Some(HistoryAction::Export {
+                    format,
+                    device,
+                    range,
+                    events,
+                }),
+            ..
+        } => {
+            let device = device.as_deref().map(|d| config.resolve_device(d));
+            let result = HistoryStore::open(&config.history.path())
+                .and_then(|store| export::export(&store, format, device, range, events));
+            if let Err(e) = result {
+                eprintln!("export error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::History {
+            action: None,
+            device,
+            since,
+            events,
+        } => {
+            let device = device.as_deref().map(|d| config.resolve_device(d));
+            let result = HistoryStore::open(&config.history.path())
+                .and_then(|store| history::print(&store, device, since, events));
+            if let Err(e) = result {
+                eprintln!("history error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Check {
+            warning,
+            critical,
+            device,
+        } => {
+            let thresholds = Thresholds { warning, critical };
+            let state = check::run(&config, thresholds, device.as_deref());
+            std::process::exit(state.code());
+        }
+        Commands::Inspect { device } => {
+            if let Err(e) = inspect::print(&config, &device, cli.color.enabled()) {
+                eprintln!("inspect error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SetLights { state, device } => {
+            let setting = Setting::Lights(matches!(state, Switch::On));
+            if let Err(e) = control::set(&config, device.as_deref(), setting) {
+                eprintln!("set-lights error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SetSidetone { level, device } => {
+            let setting = Setting::Sidetone(level);
+            if let Err(e) = control::set(&config, device.as_deref(), setting) {
+                eprintln!("set-sidetone error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SetInactiveTime { minutes, device } => {
+            let setting = Setting::InactiveTime(minutes);
+            if let Err(e) = control::set(&config, device.as_deref(), setting) {
+                eprintln!("set-inactive-time error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "dbus")]
+        Commands::Events { follow, count } => {
+            if let Err(e) = events::run(&config, count, follow) {
+                eprintln!("events error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::Report { device } => {
+            let device = device.as_deref().map(|d| config.resolve_device(d));
+            let result = HistoryStore::open(&config.history.path())
+                .and_then(|store| health::print_report(&config, &store, device));
+            if let Err(e) = result {
+                eprintln!("report error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::Graph {
+            device,
+            hours,
+            width,
+        } => {
+            let device = device.as_deref().map(|d| config.resolve_device(d));
+            let span = Duration::from_secs(hours * 60 * 60);
+            let result = HistoryStore::open(&config.history.path())
+                .and_then(|store| graph::print(&config, &store, device, span, width));
+            if let Err(e) = result {
+                eprintln!("graph error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::ExportState { path } => {
+            if let Err(e) = state::export(&config, &path) {
+                eprintln!("export error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "history")]
+        Commands::ImportState { path, force } => {
+            if let Err(e) = state::import(&config_path, &path, force) {
+                eprintln!("import error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "gui")]
+        Commands::Settings => {
+            if let Err(e) = gui::run(config_path, config) {
+                eprintln!("settings window error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Config { .. } => unreachable!("handled before loading the configuration"),
+    }
+}
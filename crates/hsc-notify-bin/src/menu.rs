@@ -0,0 +1,52 @@
+//! dmenu/rofi compatible control menu
+//!
+//! Without a selection the menu entries are printed one per line; passing one of those lines
+//! back runs the matching action on the running daemon. This works both as a rofi script mode
+//! (`rofi -show headset -modi "headset:headsetcontrol-notify menu"`) and piped through dmenu.
+
+use std::{env, error::Error};
+
+use zbus::blocking::Connection;
+
+use crate::dbus::{ControlClientProxyBlocking, IndicatorClientProxyBlocking};
+
+/// Print the menu, or run the action matching `selection`
+pub fn run(selection: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::session()?;
+    let indicator = IndicatorClientProxyBlocking::new(&conn)?;
+    let actions = indicator.menu_actions()?;
+
+    if let Some(selection) = selection {
+        // Device lines are informational only, so selecting one does nothing
+        if let Some((id, _)) = actions.iter().find(|(_, label)| label == selection.trim()) {
+            indicator.activate(id)?;
+        }
+        return Ok(());
+    }
+
+    let rofi = env::var_os("ROFI_RETV").is_some();
+    if rofi {
+        println!("\0prompt\x1fheadset");
+    }
+
+    let control = ControlClientProxyBlocking::new(&conn)?;
+    for (_, display_name, status, level) in control.devices()? {
+        let level = if level < 0 {
+            "?".to_string()
+        } else {
+            format!("{}%", level)
+        };
+        if rofi {
+            println!(
+                "{}: {} ({})\0nonselectable\x1ftrue",
+                display_name, level, status
+            );
+        } else {
+            println!("{}: {} ({})", display_name, level, status);
+        }
+    }
+    for (_, label) in actions {
+        println!("{}", label);
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! A single poll, for running from cron or a systemd timer instead of a resident daemon
+//!
+//! Each run restores the devices as the previous run left them from a [`Snapshot`], polls once,
+//! notifies about what changed since, and saves the snapshot for the next run. Notifications
+//! depending on the daemon running, like idle shutdown detection or discharge rate warnings,
+//! are not raised.
+
+use std::error::Error;
+
+#[cfg(feature = "extras")]
+use hsc_backends::headsetcontrol::extras_with;
+use hsc_backends::headsetcontrol::{headsetcontrol_output, HeadsetControl};
+#[cfg(feature = "plugins")]
+use hsc_backends::plugin::{self, PluginSink};
+use hsc_core::{
+    command::{HostRunner, SystemRunner},
+    config::Config,
+    control::{PowerSaving, SettingsStore},
+    device::parse_devices,
+    monitor::Monitor,
+    platform::Native,
+    snapshot::Snapshot,
+};
+
+#[cfg(feature = "history")]
+use crate::recorder::Recorder;
+
+/// Poll once, notifying about changes since the last run, and save the snapshot again
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let path = Snapshot::default_path();
+    let snapshot = Snapshot::load(&path)?;
+    let runner = HostRunner::new(config.remote.as_deref());
+    #[cfg_attr(not(feature = "plugins"), allow(unused_mut))]
+    let mut readings = parse_devices(&headsetcontrol_output(&runner)?);
+    #[cfg_attr(not(feature = "plugins"), allow(unused_mut))]
+    let mut firmware: Vec<(String, String)> = Vec::new();
+    #[cfg(feature = "plugins")]
+    for provider in &config.plugins.providers {
+        match plugin::poll(provider) {
+            Ok(poll) => {
+                readings.extend(poll.devices);
+                firmware.extend(poll.firmware);
+            }
+            Err(e) => eprintln!("plugin {} failed: {}", provider.command, e),
+        }
+    }
+    // Power saving only changes headsets listing the inactive time capability
+    #[cfg(feature = "extras")]
+    let extras = if config.extras.poll || config.power_saving.enabled {
+        extras_with(&runner)?
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "extras"))]
+    if config.power_saving.enabled {
+        eprintln!("power_saving is enabled but this build cannot read headset capabilities");
+    }
+    #[cfg(feature = "plugins")]
+    let sinks = config.plugins.sinks.clone();
+    #[cfg(feature = "history")]
+    let mut recorder = Recorder::open(&config);
+
+    let mut monitor = Monitor::new(config);
+    if monitor.config.icons.has_variants() {
+        monitor.color_scheme = monitor.config.icons.color_scheme(&Native, &SystemRunner);
+    }
+    monitor.settings = SettingsStore::load(&SettingsStore::default_path())?;
+    monitor.subscribe(PowerSaving::new(
+        HeadsetControl(runner),
+        SettingsStore::default_path(),
+    ));
+    #[cfg(feature = "plugins")]
+    for sink in sinks {
+        monitor.add_sink(PluginSink::new(sink));
+    }
+    snapshot.restore(&mut monitor);
+    #[cfg(feature = "extras")]
+    for (name, extras) in extras {
+        monitor.set_extras(&name, extras);
+    }
+
+    #[cfg_attr(not(feature = "history"), allow(unused_variables))]
+    let readings = monitor.update_readings(readings).readings;
+    for (name, version) in firmware {
+        monitor.set_firmware(&name, &version);
+    }
+    #[cfg(feature = "history")]
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.update(&mut monitor, &readings);
+    }
+    Snapshot::of(&monitor).save(&path)?;
+    Ok(())
+}
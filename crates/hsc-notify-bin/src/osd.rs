@@ -0,0 +1,302 @@
+//! On-screen low battery bar for Wayland compositors with the wlr layer shell
+//!
+//! Fullscreen games on Sway, Hyprland and similar compositors usually cover notifications. The
+//! bar is drawn on the overlay layer above them, across the top of the focused output, filled in
+//! proportion to the battery left. It takes no input and disappears after
+//! [`OsdConfig::duration`](hsc_core::config::OsdConfig::duration) seconds.
+
+use std::{
+    error::Error,
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState, Region},
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+    output::{OutputHandler, OutputState},
+    reexports::client::{
+        globals::registry_queue_init,
+        protocol::{wl_output, wl_shm, wl_surface},
+        Connection, QueueHandle,
+    },
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    shell::{
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+        WaylandSurface,
+    },
+    shm::{slot::SlotPool, Shm, ShmHandler},
+};
+
+use hsc_core::{
+    bus::Subscriber,
+    device::{DeviceEvent, Threshold},
+    monitor::Monitor,
+};
+
+/// Part of the bar standing for the charge left, opaque red in ARGB
+const FILLED: u32 = 0xffe0_1b24;
+/// Rest of the bar, translucent dark grey in premultiplied ARGB
+const EMPTY: u32 = 0xc018_1818;
+
+/// Subscriber flashing the bar when a discharging device drops below its low threshold
+#[derive(Default)]
+pub struct Osd {
+    /// Whether a bar is on screen, so alerts raised meanwhile do not stack up
+    showing: Arc<AtomicBool>,
+}
+
+impl Subscriber for Osd {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        let config = &monitor.config.osd;
+        let DeviceEvent::ThresholdCrossed {
+            name,
+            battery,
+            threshold: Threshold::Low,
+            charging: false,
+        } = event
+        else {
+            return;
+        };
+        if !config.enabled || monitor.is_muted() || monitor.config.is_muted(name) {
+            return;
+        }
+        if self.showing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let (percent, height) = (battery.percent(), config.height);
+        let duration = Duration::from_secs(config.duration);
+        let showing = self.showing.clone();
+        thread::spawn(move || {
+            if let Err(e) = flash(percent, height, duration) {
+                eprintln!("cannot show the on-screen alert: {}", e);
+            }
+            showing.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Show a bar `height` pixels high filled to `percent` for `duration`
+pub fn flash(percent: u8, height: u32, duration: Duration) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init(&conn)?;
+    let qh = queue.handle();
+    let compositor = CompositorState::bind(&globals, &qh)?;
+    let layer_shell = LayerShell::bind(&globals, &qh)?;
+    let shm = Shm::bind(&globals, &qh)?;
+
+    let surface = compositor.create_surface(&qh);
+    let layer = layer_shell.create_layer_surface(
+        &qh,
+        surface,
+        Layer::Overlay,
+        Some("headsetcontrol-notify"),
+        None,
+    );
+    layer.set_anchor(Anchor::TOP | Anchor::LEFT | Anchor::RIGHT);
+    layer.set_size(0, height);
+    layer.set_exclusive_zone(-1);
+    layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+    // An empty input region lets clicks through to the game underneath
+    let input = Region::new(&compositor)?;
+    layer.set_input_region(Some(input.wl_region()));
+    // The compositor answers the first commit with the size to draw at
+    layer.commit();
+
+    let mut bar = Bar {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        pool: SlotPool::new(height as usize * 1920 * 4, &shm)?,
+        shm,
+        layer,
+        percent,
+        height,
+        drawn: false,
+        closed: false,
+        error: None,
+    };
+    while !bar.drawn && !bar.closed {
+        queue.blocking_dispatch(&mut bar)?;
+    }
+    if let Some(e) = bar.error {
+        return Err(e);
+    }
+    conn.flush()?;
+    thread::sleep(duration);
+    Ok(())
+}
+
+/// State of the bar while it is on screen
+struct Bar {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    shm: Shm,
+    pool: SlotPool,
+    layer: LayerSurface,
+    /// Battery level the bar is filled to
+    percent: u8,
+    /// Height asked for, used if the compositor leaves it to the client
+    height: u32,
+    /// A frame was attached to the surface
+    drawn: bool,
+    /// The compositor closed the surface
+    closed: bool,
+    /// Failure while drawing, reported once the event loop is left
+    error: Option<Box<dyn Error>>,
+}
+
+impl Bar {
+    /// Attach a frame of `width` by `height` pixels to the surface
+    fn draw(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        let stride = width as i32 * 4;
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            stride,
+            wl_shm::Format::Argb8888,
+        )?;
+        let filled = (width as usize * self.percent.min(100) as usize) / 100;
+        for (index, pixel) in canvas.chunks_exact_mut(4).enumerate() {
+            let color = if index % (width as usize) < filled {
+                FILLED
+            } else {
+                EMPTY
+            };
+            pixel.copy_from_slice(&color.to_le_bytes());
+        }
+
+        let surface = self.layer.wl_surface();
+        surface.damage_buffer(0, 0, width as i32, height as i32);
+        buffer.attach_to(surface)?;
+        self.layer.commit();
+        Ok(())
+    }
+}
+
+impl LayerShellHandler for Bar {
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface) {
+        self.closed = true;
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        let width = NonZeroU32::new(configure.new_size.0).map_or(1920, NonZeroU32::get);
+        let height = NonZeroU32::new(configure.new_size.1).map_or(self.height, NonZeroU32::get);
+        if let Err(e) = self.draw(width, height) {
+            self.error = Some(e);
+            self.closed = true;
+        }
+        self.drawn = true;
+    }
+}
+
+impl CompositorHandler for Bar {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_factor: i32,
+    ) {
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for Bar {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl ShmHandler for Bar {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for Bar {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(Bar);
+delegate_output!(Bar);
+delegate_shm!(Bar);
+delegate_layer!(Bar);
+delegate_registry!(Bar);
@@ -0,0 +1,119 @@
+//! Keeping the history store up to date from the daemon
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use hsc_core::{config::Config, device::Device, monitor::Monitor};
+
+use crate::{forecast, health, history::HistoryStore, session, summary};
+
+/// How often baselines are refreshed and the store compacted
+const BASELINE_REFRESH: Duration = Duration::from_secs(60 * 60);
+/// How far back discharge baselines look
+const BASELINE_WINDOW: chrono::Duration = chrono::Duration::days(14);
+
+/// Records readings and notifications, and feeds figures learned from history back to the monitor
+pub struct Recorder {
+    store: HistoryStore,
+    /// Id of the last notification stored
+    last_event: Option<u64>,
+    /// When baselines were last refreshed
+    last_refresh: Option<Instant>,
+}
+
+impl Recorder {
+    /// Open the configured history store, or return `None` if history is disabled or unavailable
+    pub fn open(config: &Config) -> Option<Self> {
+        if !config.history.enabled {
+            return None;
+        }
+        let path = config.history.path();
+        let store = HistoryStore::open(&path)
+            .inspect_err(|e| eprintln!("cannot open {}: {}", path.display(), e))
+            .ok()?;
+        Some(Recorder {
+            store,
+            last_event: None,
+            last_refresh: None,
+        })
+    }
+
+    /// Handle one poll: store it, and hourly compact the store and refresh learned figures
+    pub fn update(&mut self, monitor: &mut Monitor, readings: &[Device]) {
+        let store = &mut self.store;
+        if let Err(e) = record_history(store, monitor, readings, &mut self.last_event) {
+            eprintln!("failed to write history: {}", e);
+        }
+        let clock = monitor.clock().clone();
+        let refresh_due = self
+            .last_refresh
+            .is_none_or(|t| clock.now().duration_since(t) >= BASELINE_REFRESH);
+        if refresh_due && !readings.is_empty() {
+            if let Err(e) = compact_history(store, &monitor.config, clock.local()) {
+                eprintln!("failed to compact history: {}", e);
+            }
+            if let Err(e) = refresh_baselines(store, monitor) {
+                eprintln!("failed to compute discharge baselines: {}", e);
+            }
+            self.last_refresh = Some(clock.now());
+        }
+        let summary = monitor.config.summary.clone();
+        if let Err(e) = summary::send_if_due(&summary, store, monitor, clock.local()) {
+            eprintln!("failed to compute usage summary: {}", e);
+        }
+    }
+}
+
+/// Store this poll's readings and any notifications raised since the last poll
+fn record_history(
+    store: &mut HistoryStore,
+    monitor: &Monitor,
+    readings: &[Device],
+    last_event: &mut Option<u64>,
+) -> rusqlite::Result<()> {
+    store.record_readings(monitor.clock().local(), readings)?;
+    for event in monitor.events_since(*last_event) {
+        store.record_event(event)?;
+        *last_event = Some(event.id);
+    }
+    Ok(())
+}
+
+/// Apply the configured retention periods to the history store
+fn compact_history(
+    store: &mut HistoryStore,
+    config: &Config,
+    now: DateTime<Local>,
+) -> rusqlite::Result<()> {
+    let cutoff = |days: u32| (days > 0).then(|| now - chrono::Duration::days(days.into()));
+    store.compact(
+        cutoff(config.history.raw_retention_days),
+        cutoff(config.history.aggregate_retention_days),
+    )
+}
+
+/// Recompute the typical discharge rate, usage forecast and battery health of every known device,
+/// and recover charge sessions that started before the daemon
+fn refresh_baselines(store: &HistoryStore, monitor: &mut Monitor) -> rusqlite::Result<()> {
+    let now = monitor.clock().local();
+    let names: Vec<String> = monitor.devices.keys().cloned().collect();
+    for name in names {
+        match store.discharge_rate(&name, now - BASELINE_WINDOW, now)? {
+            Some(rate) => monitor.baselines.insert(name.clone(), rate),
+            None => monitor.baselines.remove(&name),
+        };
+        let charge_session = monitor.sessions.entry(name.clone()).or_default();
+        if charge_session.full_at.is_none() {
+            session::recover(charge_session, store, &name, now)?;
+        }
+        match forecast::forecast(store, &name, now)? {
+            Some(forecast) => monitor.forecasts.insert(name.clone(), forecast),
+            None => monitor.forecasts.remove(&name),
+        };
+        if let Some(health) = health::battery_health(store, &name, now)? {
+            monitor.set_health(&name, health);
+        }
+    }
+    Ok(())
+}
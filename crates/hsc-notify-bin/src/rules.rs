@@ -0,0 +1,196 @@
+//! Custom notification rules written in Rhai
+//!
+//! Every script listed under `rules` runs on each device event with two variables in scope:
+//!
+//! - `event`: a map with the event `kind` (`connected`, `disconnected`, `level_changed`,
+//!   `threshold_crossed`, `charging_started`, `charging_stopped` or `full`) and its details:
+//!   `from` and `to` for level changes, `threshold` (`low` or `step`) and `charging` for
+//!   threshold crossings
+//! - `dev`: a map with the device `name`, `display_name`, `status` and `level`, which is `()` when
+//!   unknown
+//!
+//! Scripts can call `hour()`, `minute()` and `weekday()` (0 for Monday) for the local time, and
+//! raise notifications with `notify(message)` or `notify_critical(message)`:
+//!
+//! ```rhai
+//! if event.kind == "level_changed" && dev.level < 15 && hour() >= 22 {
+//!     notify_critical(`Charge me before bed, only ${dev.level}% left`);
+//! }
+//! ```
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+use hsc_core::{
+    bus::Subscriber,
+    config::Config,
+    device::{DeviceEvent, Threshold},
+    monitor::Monitor,
+};
+
+/// Upper bound on the work a script may do for a single event
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// State shared with the functions scripts can call
+#[derive(Default)]
+struct Context {
+    /// Local time of the event being handled
+    now: Option<DateTime<Local>>,
+    /// Notifications raised by the script, with their icon
+    raised: Vec<(String, &'static str)>,
+}
+
+/// Subscriber running the configured rule scripts on every event
+pub struct Rules {
+    engine: Engine,
+    scripts: Vec<(PathBuf, AST)>,
+    context: Arc<Mutex<Context>>,
+}
+
+impl Rules {
+    /// Compile the scripts configured in `config`, skipping and reporting any that fail
+    ///
+    /// Returns `None` when no script could be loaded.
+    pub fn load(config: &Config) -> Option<Self> {
+        let context = Arc::new(Mutex::new(Context::default()));
+        let engine = engine(&context);
+        let scripts: Vec<(PathBuf, AST)> = config
+            .rules
+            .iter()
+            .filter_map(|path| {
+                let compiled = fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| engine.compile(source).map_err(|e| e.to_string()));
+                match compiled {
+                    Ok(ast) => Some((path.clone(), ast)),
+                    Err(e) => {
+                        eprintln!("cannot load rule {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        (!scripts.is_empty()).then_some(Rules {
+            engine,
+            scripts,
+            context,
+        })
+    }
+}
+
+impl Subscriber for Rules {
+    fn handle(&mut self, monitor: &mut Monitor, event: &DeviceEvent) {
+        let name = event.device();
+        lock(&self.context).now = Some(monitor.clock().local());
+        let dev = device_map(monitor, name);
+        let event_map = event_map(event);
+
+        for (path, ast) in &self.scripts {
+            let mut scope = Scope::new();
+            scope.push("event", event_map.clone());
+            scope.push("dev", dev.clone());
+            if let Err(e) = self.engine.run_ast_with_scope(&mut scope, ast) {
+                eprintln!("rule {} failed: {}", path.display(), e);
+            }
+        }
+
+        let raised = std::mem::take(&mut lock(&self.context).raised);
+        for (message, icon) in raised {
+            monitor.notify(name, &message, icon);
+        }
+    }
+}
+
+/// Engine with the functions available to scripts registered
+fn engine(context: &Arc<Mutex<Context>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let now = |context: &Arc<Mutex<Context>>| lock(context).now.unwrap_or_else(Local::now);
+    let ctx = context.clone();
+    engine.register_fn("hour", move || now(&ctx).hour() as i64);
+    let ctx = context.clone();
+    engine.register_fn("minute", move || now(&ctx).minute() as i64);
+    let ctx = context.clone();
+    engine.register_fn("weekday", move || {
+        now(&ctx).weekday().num_days_from_monday() as i64
+    });
+    let ctx = context.clone();
+    engine.register_fn("notify", move |message: &str| {
+        lock(&ctx).raised.push((message.to_string(), "battery"));
+    });
+    let ctx = context.clone();
+    engine.register_fn("notify_critical", move |message: &str| {
+        lock(&ctx)
+            .raised
+            .push((message.to_string(), "battery-caution"));
+    });
+    engine
+}
+
+/// The `dev` variable for the named device
+fn device_map(monitor: &Monitor, name: &str) -> Map {
+    let mut map = Map::new();
+    map.insert("name".into(), name.into());
+    map.insert(
+        "display_name".into(),
+        monitor.config.display_name(name).into(),
+    );
+    let device = monitor.devices.get(name);
+    map.insert(
+        "status".into(),
+        device
+            .map_or("disconnected", |d| d.battery_status.as_str())
+            .into(),
+    );
+    map.insert(
+        "level".into(),
+        device
+            .and_then(|d| d.level())
+            .map_or(Dynamic::UNIT, |l| (l as i64).into()),
+    );
+    map
+}
+
+/// The `event` variable for `event`
+fn event_map(event: &DeviceEvent) -> Map {
+    let mut map = Map::new();
+    let kind = match *event {
+        DeviceEvent::Connected { .. } => "connected",
+        DeviceEvent::Disconnected { .. } => "disconnected",
+        DeviceEvent::ChargingStarted { .. } => "charging_started",
+        DeviceEvent::ChargingStopped { .. } => "charging_stopped",
+        DeviceEvent::LevelChanged { from, to, .. } => {
+            map.insert("from".into(), (from.percent() as i64).into());
+            map.insert("to".into(), (to.percent() as i64).into());
+            "level_changed"
+        }
+        DeviceEvent::ThresholdCrossed {
+            threshold,
+            charging,
+            ..
+        } => {
+            let threshold = match threshold {
+                Threshold::Low => "low",
+                Threshold::Step => "step",
+            };
+            map.insert("threshold".into(), threshold.into());
+            map.insert("charging".into(), charging.into());
+            "threshold_crossed"
+        }
+        DeviceEvent::Full { .. } => "full",
+    };
+    map.insert("kind".into(), kind.into());
+    map
+}
+
+/// Lock the script context, recovering it if a script panicked while holding it
+fn lock(context: &Mutex<Context>) -> std::sync::MutexGuard<'_, Context> {
+    context.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,24 @@
+//! Recovering charge sessions from the history store
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use hsc_core::session::ChargeSession;
+
+use crate::history::{local_time, HistoryStore};
+
+/// Recover the current session of a device from the history store, up to `now`
+///
+/// Without a recorded full charge, usage is counted from the start of the retained readings.
+pub fn recover(
+    session: &mut ChargeSession,
+    store: &HistoryStore,
+    device: &str,
+    now: DateTime<Local>,
+) -> rusqlite::Result<()> {
+    session.full_at = store.last_full_charge(device)?;
+    let since = session.full_at.unwrap_or_else(|| local_time(0));
+    let (seconds, _) = store.usage(device, since, now)?;
+    session.usage = Duration::from_secs_f64(seconds);
+    Ok(())
+}
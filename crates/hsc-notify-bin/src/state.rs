@@ -0,0 +1,60 @@
+//! Moving configuration and history between machines
+//!
+//! A state bundle is a copy of the history database with the configuration stored in its `meta`
+//! table. Discharge baselines, cycle counts and battery health are all derived from the history,
+//! so they carry over with it.
+
+use std::{error::Error, fs, path::Path};
+
+use hsc_core::config::Config;
+
+use crate::history::HistoryStore;
+
+/// Meta key holding the configuration inside a bundle
+const CONFIG_KEY: &str = "bundled_config";
+
+/// Write the configuration and history database to a bundle at `bundle`
+pub fn export(config: &Config, bundle: &Path) -> Result<(), Box<dyn Error>> {
+    if bundle.exists() {
+        return Err(format!("{} already exists", bundle.display()).into());
+    }
+    HistoryStore::open(&config.history.path())?.copy_to(bundle)?;
+    HistoryStore::open(bundle)?.set_meta(CONFIG_KEY, &toml::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+/// Restore the configuration to `config_path` and the history database from a bundle
+///
+/// Existing files are only replaced with `force`. The daemon should not be running meanwhile.
+pub fn import(config_path: &Path, bundle: &Path, force: bool) -> Result<(), Box<dyn Error>> {
+    if !bundle.is_file() {
+        return Err(format!("{} does not exist", bundle.display()).into());
+    }
+    let source = HistoryStore::open(bundle)?;
+    let config: Config = match source.meta(CONFIG_KEY)? {
+        Some(config) => toml::from_str(&config)?,
+        None => return Err(format!("{} is not a state bundle", bundle.display()).into()),
+    };
+
+    let history_path = config.history.path();
+    for path in [config_path, history_path.as_path()] {
+        if path.exists() && !force {
+            return Err(format!(
+                "{} already exists, use --force to replace it",
+                path.display()
+            )
+            .into());
+        }
+    }
+
+    if history_path.exists() {
+        fs::remove_file(&history_path)?;
+    }
+    if let Some(parent) = history_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    source.copy_to(&history_path)?;
+    HistoryStore::open(&history_path)?.remove_meta(CONFIG_KEY)?;
+    config.save(config_path)?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! One-shot summary of connected devices
+
+use std::{collections::HashMap, error::Error};
+
+#[cfg(feature = "extras")]
+use hsc_backends::headsetcontrol::extras_with;
+use hsc_backends::headsetcontrol::headsetcontrol_output;
+use hsc_core::{
+    command::HostRunner,
+    config::Config,
+    control::SettingsStore,
+    device::{parse_devices, Device},
+    extras::Extras,
+    schema::{DeviceState, StatusReport},
+};
+
+use crate::table::{level_cell, status_cell, Table};
+
+/// Poll devices once and print their state as a table to standard output, colored if `color`
+/// is set
+///
+/// Cycle counts, health and usage since the last full charge come from the history store and are
+/// left out when it is disabled or not compiled in.
+pub fn print_status(config: &Config, color: bool) -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "history")]
+    let store = if config.history.enabled {
+        Some(crate::history::HistoryStore::open(&config.history.path())?)
+    } else {
+        None
+    };
+    #[cfg(feature = "history")]
+    let now = chrono::Local::now();
+    let extras = read_extras(config);
+    let settings = SettingsStore::load(&SettingsStore::default_path())?;
+
+    let mut table = Table::new(&["DEVICE", "LEVEL", "STATUS", "HEALTH"]);
+    for device in read_devices(config)? {
+        #[cfg(feature = "history")]
+        let (health, session) = match &store {
+            Some(store) => crate::health::details(store, &device.name, now)?,
+            None => (String::new(), String::new()),
+        };
+        #[cfg(not(feature = "history"))]
+        let (health, session) = (String::new(), String::new());
+
+        table.row(vec![
+            config.display_name(&device.name).into(),
+            level_cell(config, &device),
+            status_cell(&device),
+            health.into(),
+        ]);
+        if !session.is_empty() {
+            table.note(session);
+        }
+        if let Some(chatmix) = extras.get(&device.name).and_then(|e| e.chatmix) {
+            table.note(format!("chatmix {}", chatmix));
+        }
+        let applied = settings.get(&device.name).settings();
+        if !applied.is_empty() {
+            let applied: Vec<String> = applied.iter().map(ToString::to_string).collect();
+            table.note(applied.join(", "));
+        }
+    }
+    print!("{}", table.render(color));
+    Ok(())
+}
+
+/// Poll devices once and print a [`StatusReport`] to standard output
+pub fn print_json(config: &Config) -> Result<(), Box<dyn Error>> {
+    let extras = read_extras(config);
+    let devices = read_devices(config)?
+        .iter()
+        .map(|device| {
+            let state = DeviceState::new(config, device);
+            match extras.get(&device.name) {
+                Some(extras) => state.with_extras(extras),
+                None => state,
+            }
+        })
+        .collect();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&StatusReport::new(devices))?
+    );
+    Ok(())
+}
+
+/// Poll devices once, with levels rounded to the configured buckets
+fn read_devices(config: &Config) -> Result<Vec<Device>, Box<dyn Error>> {
+    let mut devices = parse_devices(&headsetcontrol_output(&HostRunner::new(
+        config.remote.as_deref(),
+    ))?);
+    for device in devices.iter_mut() {
+        let bucket = config.level_bucket_for(&device.name);
+        device.battery = device.battery.map(|b| b.bucketed(bucket));
+    }
+    Ok(devices)
+}
+
+/// Chatmix and capabilities of every device, keyed by name
+///
+/// These are extras: versions of headsetcontrol without JSON output just have none.
+#[cfg(feature = "extras")]
+fn read_extras(config: &Config) -> HashMap<String, Extras> {
+    extras_with(&HostRunner::new(config.remote.as_deref()))
+        .map(|extras| extras.into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// Chatmix and capabilities of every device, which this build cannot read
+#[cfg(not(feature = "extras"))]
+fn read_extras(_config: &Config) -> HashMap<String, Extras> {
+    HashMap::new()
+}
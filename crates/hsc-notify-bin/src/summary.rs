@@ -0,0 +1,68 @@
+//! Daily and weekly usage summaries computed from the history store
+
+use chrono::{DateTime, Datelike, Days, Local, Timelike, Weekday};
+
+use hsc_core::{
+    config::{SummaryConfig, SummarySchedule},
+    monitor::Monitor,
+};
+
+use crate::history::{start_of_day, HistoryStore};
+
+/// Meta key holding the date the last summary was sent
+const LAST_SENT_KEY: &str = "summary_last_sent";
+
+/// Send the scheduled summary if it is due and has not been sent yet
+///
+/// The date of the last summary is kept in the history store, so restarting the daemon does not
+/// repeat it.
+pub fn send_if_due(
+    config: &SummaryConfig,
+    store: &HistoryStore,
+    monitor: &mut Monitor,
+    now: DateTime<Local>,
+) -> rusqlite::Result<()> {
+    let today = now.date_naive();
+    let days = match config.schedule {
+        SummarySchedule::Off => return Ok(()),
+        SummarySchedule::Daily => 1,
+        SummarySchedule::Weekly if today.weekday() == Weekday::Mon => 7,
+        SummarySchedule::Weekly => return Ok(()),
+    };
+    if now.hour() < config.hour {
+        return Ok(());
+    }
+    let today_str = today.to_string();
+    if store.meta(LAST_SENT_KEY)?.as_deref() == Some(today_str.as_str()) {
+        return Ok(());
+    }
+
+    let until = start_of_day(today);
+    let since = start_of_day(today - Days::new(days));
+    let period = if days == 1 { "Yesterday" } else { "Last week" };
+    for device in store.devices(since, until)? {
+        let (seconds, charges) = store.usage(&device, since, until)?;
+        let mut message = format!(
+            "{}: used {}, {} charge{}",
+            period,
+            format_hours(seconds),
+            charges,
+            if charges == 1 { "" } else { "s" }
+        );
+        if let Some(rate) = store.discharge_rate(&device, since, until)? {
+            message.push_str(&format!(", avg drain {:.1}%/h", rate));
+        }
+        monitor.notify(&device, &message, "battery");
+    }
+    store.set_meta(LAST_SENT_KEY, &today_str)
+}
+
+/// Format seconds of use as whole hours, or minutes below an hour
+fn format_hours(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h", (minutes as f64 / 60.0).round())
+    }
+}
@@ -0,0 +1,194 @@
+//! Aligned, optionally colored tables for terminal output
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use clap::ValueEnum;
+
+use hsc_core::{
+    config::Config,
+    device::{BatteryStatus, Device},
+};
+
+/// When to color terminal output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color terminals, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether standard output should be colored
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // https://no-color.org: any non-empty value disables colors
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+/// How the text of a cell is shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Bold,
+    Dim,
+    Red,
+    Yellow,
+    Green,
+    Cyan,
+}
+
+impl Style {
+    /// ANSI escape sequence selecting the style
+    fn code(self) -> Option<&'static str> {
+        match self {
+            Style::Plain => None,
+            Style::Bold => Some("\x1b[1m"),
+            Style::Dim => Some("\x1b[2m"),
+            Style::Red => Some("\x1b[31m"),
+            Style::Yellow => Some("\x1b[33m"),
+            Style::Green => Some("\x1b[32m"),
+            Style::Cyan => Some("\x1b[36m"),
+        }
+    }
+
+    /// `text`, styled if `color` is set
+    fn paint(self, text: &str, color: bool) -> String {
+        match self.code() {
+            Some(code) if color => format!("{}{}\x1b[0m", code, text),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// Text of a table cell with its style
+#[derive(Clone, Debug)]
+pub struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    /// Cell showing `text` in `style`
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Cell {
+            text: text.into(),
+            style,
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::new(text, Style::Plain)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::new(text, Style::Plain)
+    }
+}
+
+/// Rows printed in aligned columns under a header, each followed by optional notes indented to
+/// the second column
+pub struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<(Vec<Cell>, Vec<String>)>,
+}
+
+impl Table {
+    /// Empty table with the given column titles
+    pub fn new(header: &[&'static str]) -> Self {
+        Table {
+            header: header.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row of cells, one per column
+    pub fn row(&mut self, cells: Vec<Cell>) {
+        self.rows.push((cells, Vec::new()));
+    }
+
+    /// Add a line of additional information under the last row
+    pub fn note(&mut self, note: impl Into<String>) {
+        if let Some((_, notes)) = self.rows.last_mut() {
+            notes.push(note.into());
+        }
+    }
+
+    /// The table as text, with a line per row and note, colored if `color` is set
+    pub fn render(&self, color: bool) -> String {
+        let mut widths: Vec<usize> = self.header.iter().map(|h| h.chars().count()).collect();
+        for (cells, _) in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.text.chars().count());
+            }
+        }
+        let line = |cells: Vec<Cell>| {
+            let columns: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| {
+                    let padding = width.saturating_sub(cell.text.chars().count());
+                    format!(
+                        "{}{}",
+                        cell.style.paint(&cell.text, color),
+                        " ".repeat(padding)
+                    )
+                })
+                .collect();
+            format!("{}\n", columns.join("  ").trim_end())
+        };
+
+        let header = self.header.iter().map(|h| Cell::new(*h, Style::Bold));
+        let mut out = line(header.collect());
+        let indent = " ".repeat(widths.first().map_or(0, |w| w + 2));
+        for (cells, notes) in &self.rows {
+            out.push_str(&line(cells.clone()));
+            for note in notes {
+                out.push_str(&format!("{}{}\n", indent, Style::Dim.paint(note, color)));
+            }
+        }
+        out
+    }
+}
+
+/// Cell showing the battery reading of a device, colored by how much charge is left
+pub fn level_cell(config: &Config, device: &Device) -> Cell {
+    let Some(battery) = device.battery else {
+        return Cell::new("-", Style::Dim);
+    };
+    let style = match device.battery_status {
+        BatteryStatus::Disconnected => Style::Dim,
+        BatteryStatus::Charging => Style::Cyan,
+        BatteryStatus::Discharging if battery.is_below(config.threshold_for(&device.name)) => {
+            Style::Red
+        }
+        BatteryStatus::Discharging if battery.percent() < 50 => Style::Yellow,
+        BatteryStatus::Discharging => Style::Green,
+    };
+    Cell::new(battery.to_string(), style)
+}
+
+/// Cell showing whether a device is charging, discharging or disconnected
+pub fn status_cell(device: &Device) -> Cell {
+    let style = match device.battery_status {
+        BatteryStatus::Charging => Style::Cyan,
+        BatteryStatus::Discharging => Style::Plain,
+        BatteryStatus::Disconnected => Style::Dim,
+    };
+    Cell::new(device.battery_status.as_str(), style)
+}
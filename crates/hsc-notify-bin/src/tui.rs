@@ -0,0 +1,212 @@
+//! Interactive terminal dashboard
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, List, ListItem, Paragraph, Row, Sparkline, Table},
+    DefaultTerminal, Frame,
+};
+
+use hsc_backends::headsetcontrol::headsetcontrol_output;
+use hsc_core::{command::HostRunner, device::BatteryStatus, monitor::Monitor};
+
+/// How often the UI checks for key presses
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Run the dashboard until the user quits
+///
+/// Notifications are only listed under the recent events: a running daemon already sends them,
+/// and would otherwise be joined by a duplicate from the dashboard. Pausing and snoozing are
+/// therefore left to the daemon, through `menu` or its D-Bus interface.
+pub fn run(mut monitor: Monitor) -> io::Result<()> {
+    monitor.config.debug = false;
+    monitor.config.sinks.clear();
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut monitor);
+    ratatui::restore();
+    result
+}
+
+/// Poll devices, redraw, and handle key presses
+fn event_loop(terminal: &mut DefaultTerminal, monitor: &mut Monitor) -> io::Result<()> {
+    let polling_interval = Duration::from_millis(monitor.config.polling_interval);
+    let mut last_poll: Option<Instant> = None;
+    let runner = HostRunner::new(monitor.config.remote.as_deref());
+
+    loop {
+        let now = monitor.clock().now();
+        if last_poll.is_none_or(|t| now.duration_since(t) >= polling_interval) {
+            // Errors cannot be printed over the dashboard; devices keep their last state instead
+            if let Ok(output) = headsetcontrol_output(&runner) {
+                monitor.update(&output);
+            }
+            last_poll = Some(now);
+        }
+
+        terminal.draw(|frame| draw(frame, monitor))?;
+
+        if !event::poll(TICK_RATE)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('r') => last_poll = None,
+            _ => {}
+        }
+    }
+}
+
+/// Render the whole dashboard
+fn draw(frame: &mut Frame, monitor: &Monitor) {
+    let mut names: Vec<&String> = monitor.devices.keys().collect();
+    names.sort();
+
+    let [header, devices, history, events, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(names.len() as u16 + 3),
+        Constraint::Length(names.len().max(1) as u16 * 3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    draw_header(frame, header, monitor);
+    draw_devices(frame, devices, monitor, &names);
+    draw_history(frame, history, monitor, &names);
+    draw_events(frame, events, monitor);
+    frame.render_widget(Paragraph::new(" q quit · r poll now".dark_gray()), footer);
+}
+
+/// Render the title bar
+fn draw_header(frame: &mut Frame, area: Rect, monitor: &Monitor) {
+    let line = Line::from(vec![
+        " headsetcontrol-notify ".bold(),
+        format!("│ polling every {}ms", monitor.config.polling_interval).into(),
+    ]);
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+/// Render the table of known devices
+fn draw_devices(frame: &mut Frame, area: Rect, monitor: &Monitor, names: &[&String]) {
+    let rows = names.iter().map(|name| {
+        let device = &monitor.devices[*name];
+        let status_style = match device.battery_status {
+            BatteryStatus::Charging => Style::new().fg(Color::Cyan),
+            BatteryStatus::Discharging => Style::new(),
+            BatteryStatus::Disconnected => Style::new().fg(Color::DarkGray),
+        };
+        let battery_style = match device.level() {
+            Some(b) if b < monitor.config.threshold_for(&device.name) => {
+                Style::new().fg(Color::Red)
+            }
+            _ => Style::new(),
+        };
+        Row::new(vec![
+            Line::from(monitor.config.display_name(&device.name).to_string()),
+            Line::styled(format!("{:?}", device.battery_status), status_style),
+            Line::styled(
+                device
+                    .battery
+                    .map_or_else(|| "-".to_string(), |b| b.to_string()),
+                battery_style,
+            ),
+            Line::from(format_level(device.last_notif_battery_level)),
+            Line::from(
+                monitor
+                    .estimate(&device.name)
+                    .map(|e| e.to_string())
+                    .unwrap_or_default(),
+            ),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(1),
+            Constraint::Length(14),
+            Constraint::Length(9),
+            Constraint::Length(14),
+            Constraint::Length(20),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Device",
+            "Status",
+            "Battery",
+            "Last notified",
+            "Estimate",
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title(" Devices "));
+    frame.render_widget(table, area);
+}
+
+/// Render a battery sparkline for every device
+fn draw_history(frame: &mut Frame, area: Rect, monitor: &Monitor, names: &[&String]) {
+    if names.is_empty() {
+        frame.render_widget(
+            Paragraph::new(" No devices found".dark_gray()).block(Block::bordered()),
+            area,
+        );
+        return;
+    }
+
+    let areas = Layout::vertical(vec![Constraint::Length(3); names.len()]).split(area);
+    for (name, area) in names.iter().zip(areas.iter()) {
+        let data: Vec<u64> = monitor
+            .history
+            .get(*name)
+            .map(|h| h.iter().map(|&b| b as u64).collect())
+            .unwrap_or_default();
+        let sparkline = Sparkline::default()
+            .block(
+                Block::bordered().title(format!(" {} history ", monitor.config.display_name(name))),
+            )
+            .data(&data)
+            .max(100)
+            .style(Style::new().fg(Color::Green));
+        frame.render_widget(sparkline, *area);
+    }
+}
+
+/// Render the most recent notifications, newest first
+fn draw_events(frame: &mut Frame, area: Rect, monitor: &Monitor) {
+    let items = monitor.events.iter().rev().map(|event| {
+        let line = format!(
+            "{} {}: {}",
+            event.time.format("%H:%M:%S"),
+            monitor.config.display_name(&event.device),
+            event.message
+        );
+        if event.suppressed {
+            ListItem::new(format!("{} (muted)", line)).dark_gray()
+        } else {
+            ListItem::new(line)
+        }
+    });
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Recent events ")),
+        area,
+    );
+}
+
+/// Format an optional battery level as a percentage
+fn format_level(level: Option<u8>) -> String {
+    level.map_or_else(|| "-".to_string(), |b| format!("{}%", b))
+}
@@ -0,0 +1,568 @@
+//! Runs the daemon against the scripted `fake-headsetcontrol` binary and checks the
+//! notifications it sends through `notify-send`
+
+#![cfg(feature = "desktop")]
+
+use std::{
+    fs,
+    os::unix::fs::symlink,
+    path::Path,
+    process::{Child, Command, Output, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use tempfile::TempDir;
+
+/// How long a scripted run may take before the test gives up
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A device block of `headsetcontrol -b` output
+fn reading(name: &str, status: &str, level: u8) -> String {
+    format!(
+        "Found {}!\n\nBattery:\n\tStatus: {}\n\tLevel: {}%\n",
+        name, status, level
+    )
+}
+
+/// Run the daemon until it has polled every block of `script`, returning the notifications
+/// sent as `(device, message, icon)`
+fn run(config: &str, script: &[String]) -> Vec<(String, String, String)> {
+    run_with_json(config, script, &[])
+}
+
+/// Like [`run`], answering `headsetcontrol -o json` with the blocks of `json_script`
+fn run_with_json(
+    config: &str,
+    script: &[String],
+    json_script: &[String],
+) -> Vec<(String, String, String)> {
+    let dir = TempDir::new().unwrap();
+    let fake = env!("CARGO_BIN_EXE_fake-headsetcontrol");
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    symlink(fake, bin.join("headsetcontrol")).unwrap();
+    symlink(fake, bin.join("notify-send")).unwrap();
+
+    let script_path = dir.path().join("script");
+    fs::write(&script_path, script.join("---\n")).unwrap();
+    let json_script_path = dir.path().join("json_script");
+    fs::write(&json_script_path, json_script.join("---\n")).unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "polling_interval = 20\n{}\n[history]\nenabled = false\n",
+            config
+        ),
+    )
+    .unwrap();
+    let log_path = dir.path().join("notifications");
+
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_headsetcontrol-notify"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("run")
+        .env("PATH", &bin)
+        .env("XDG_DATA_HOME", dir.path().join("data"))
+        .env("DBUS_SESSION_BUS_ADDRESS", "unix:path=/nonexistent")
+        .env("FAKE_HSC_SCRIPT", &script_path)
+        .env("FAKE_HSC_JSON_SCRIPT", &json_script_path)
+        .env("FAKE_NOTIFY_LOG", &log_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // One extra poll guarantees the last scripted block has been fully handled
+    let finished = wait_for_polls(
+        &mut daemon,
+        &dir.path().join("script.count"),
+        script.len() + 1,
+    );
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+    assert!(finished, "daemon did not get through the script in time");
+
+    fs::read_to_string(&log_path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            let mut fields = line.split('\t').map(str::to_string);
+            let mut next = || fields.next().unwrap_or_default();
+            (next(), next(), next())
+        })
+        .collect()
+}
+
+/// Wait until the fake binary has answered `polls` battery queries
+fn wait_for_polls(daemon: &mut Child, count_path: &Path, polls: usize) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if daemon.try_wait().unwrap().is_some() {
+            return false;
+        }
+        let count: usize = fs::read_to_string(count_path)
+            .ok()
+            .and_then(|c| c.trim().parse().ok())
+            .unwrap_or(0);
+        if count >= polls {
+            return true;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+/// Shorthand for an expected notification
+fn notification(device: &str, message: &str, icon: &str) -> (String, String, String) {
+    (
+        device.to_string(),
+        message.to_string(),
+        format!("--icon={}", icon),
+    )
+}
+
+#[test]
+fn discharge_notifies_on_steps_and_low_battery() {
+    let levels = [32, 31, 30, 25, 20, 12, 9, 9, 8];
+    let script: Vec<String> = levels
+        .iter()
+        .map(|&l| reading("Arctis 7", "BATTERY_AVAILABLE", l))
+        .collect();
+    let sent = run("notification_step = 10\nbattery_threshold = 10", &script);
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 32%", "battery"),
+            notification("Arctis 7", "Battery level: 30%", "battery"),
+            notification("Arctis 7", "Battery level: 20%", "battery"),
+            notification("Arctis 7", "Battery level low: 9%", "battery-low"),
+            notification("Arctis 7", "Battery level low: 8%", "battery-low"),
+        ]
+    );
+}
+
+#[test]
+fn thresholds_only_mode_skips_steps() {
+    let levels = [32, 30, 20, 9, 8];
+    let script: Vec<String> = levels
+        .iter()
+        .map(|&l| reading("Arctis 7", "BATTERY_AVAILABLE", l))
+        .collect();
+    let sent = run(
+        "notification_step = 10\nnotification_mode = \"thresholds_only\"",
+        &script,
+    );
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 32%", "battery"),
+            notification("Arctis 7", "Battery level low: 9%", "battery-low"),
+            notification("Arctis 7", "Battery level low: 8%", "battery-low"),
+        ]
+    );
+}
+
+#[test]
+fn charging_notifies_until_full() {
+    let levels = [85, 90, 95, 99, 100, 100];
+    let script: Vec<String> = levels
+        .iter()
+        .map(|&l| reading("Arctis 7", "BATTERY_CHARGING", l))
+        .collect();
+    let sent = run("notification_step = 5", &script);
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 85%", "battery"),
+            notification("Arctis 7", "Charging 90%", "battery"),
+            notification("Arctis 7", "Charging 95%", "battery"),
+            notification("Arctis 7", "Battery level full: 100%", "battery"),
+        ]
+    );
+}
+
+#[test]
+fn plugging_in_and_out_is_notified() {
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 42),
+        reading("Arctis 7", "BATTERY_CHARGING", 42),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 43),
+    ];
+    let sent = run("", &script);
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 42%", "battery"),
+            notification(
+                "Arctis 7",
+                "Charging started at 42%",
+                "battery-good-charging"
+            ),
+            notification("Arctis 7", "Charging stopped at 43%", "battery"),
+        ]
+    );
+
+    let sent = run("notify_charging = false", &script);
+    assert_eq!(sent.len(), 2, "{:?}", sent);
+}
+
+#[test]
+fn device_icon_overrides_every_notification() {
+    let script = [[
+        reading("Arctis 7", "BATTERY_AVAILABLE", 42),
+        reading("G305", "BATTERY_AVAILABLE", 80),
+    ]
+    .concat()];
+    let sent = run(
+        "[devices.\"G305\"]\nicon = \"/usr/share/icons/mouse.png\"\n",
+        &script,
+    );
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 42%", "battery"),
+            notification("G305", "New device connected", "/usr/share/icons/mouse.png"),
+            notification("G305", "Battery level: 80%", "/usr/share/icons/mouse.png"),
+        ]
+    );
+}
+
+#[test]
+fn disconnect_and_reconnect() {
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 50),
+        "Found Arctis 7!\n\nBattery:\n\tStatus: BATTERY_UNAVAILABLE\n\tLevel: 50%\n".to_string(),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 49),
+    ];
+    let sent = run("", &script);
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 50%", "battery"),
+            notification("Arctis 7", "Device disconnected", "battery-caution"),
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 49%", "battery"),
+        ]
+    );
+}
+
+#[test]
+fn muted_device_sends_nothing() {
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 50),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 40),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 5),
+    ];
+    let sent = run("[devices.\"Arctis 7\"]\nmuted = true", &script);
+    assert!(sent.is_empty(), "{:?}", sent);
+}
+
+#[test]
+fn chatmix_changes_are_notified() {
+    let script = vec![reading("Arctis 7", "BATTERY_AVAILABLE", 50); 3];
+    let json_script: Vec<String> = [64, 64, 96]
+        .iter()
+        .map(|chatmix| {
+            format!(
+                r#"{{"devices": [{{"device": "Arctis 7", "chatmix": {}}}]}}"#,
+                chatmix
+            )
+        })
+        .collect();
+    let sent = run_with_json(
+        "[extras]\npoll = true\nnotify_chatmix = true",
+        &script,
+        &json_script,
+    );
+    assert_eq!(
+        sent,
+        [
+            notification("Arctis 7", "New device connected", "battery"),
+            notification("Arctis 7", "Battery level: 50%", "battery"),
+            notification(
+                "Arctis 7",
+                "Chatmix: game 25% / chat 75%",
+                "dialog-information"
+            ),
+        ]
+    );
+}
+
+/// Run a one-shot command with `headsetcontrol -b` printing `script` and the data directory
+/// under `dir`, returning its standard output
+fn run_command(dir: &Path, script: &str, args: &[&str]) -> String {
+    let output = command_output(dir, script, args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Like [`run_command`], for commands that may fail
+fn command_output(dir: &Path, script: &str, args: &[&str]) -> Output {
+    let bin = dir.join("bin");
+    if !bin.exists() {
+        fs::create_dir(&bin).unwrap();
+        symlink(
+            env!("CARGO_BIN_EXE_fake-headsetcontrol"),
+            bin.join("headsetcontrol"),
+        )
+        .unwrap();
+    }
+    let script_path = dir.join("script");
+    fs::write(&script_path, script).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_headsetcontrol-notify"));
+    // `headsetcontrol -o json` is answered from `json_script` if the test wrote one
+    let json_script_path = dir.join("json_script");
+    if json_script_path.exists() {
+        command.env("FAKE_HSC_JSON_SCRIPT", &json_script_path);
+    }
+    command
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .env("PATH", &bin)
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("FAKE_HSC_SCRIPT", &script_path)
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn set_lights_is_recorded_and_shown_in_status() {
+    let dir = TempDir::new().unwrap();
+    let script = reading("Arctis 7", "BATTERY_AVAILABLE", 50);
+    let notifier = |args: &[&str]| run_command(dir.path(), &script, args);
+
+    assert_eq!(notifier(&["set-lights", "off"]), "Arctis 7: lights off\n");
+    assert_eq!(
+        notifier(&["set-sidetone", "32", "--device", "Arctis 7"]),
+        "Arctis 7: sidetone 32\n"
+    );
+    let status = notifier(&["status"]);
+    assert!(status.contains("lights off, sidetone 32"), "{}", status);
+    assert!(dir
+        .path()
+        .join("data/headsetcontrol-notify/settings.toml")
+        .exists());
+}
+
+#[test]
+fn status_is_a_table_colored_on_request() {
+    let dir = TempDir::new().unwrap();
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 60),
+        reading("G435", "BATTERY_CHARGING", 5),
+    ]
+    .concat();
+
+    let plain = run_command(dir.path(), &script, &["status", "--color", "never"]);
+    let row = |prefix| plain.lines().any(|line| line.starts_with(prefix));
+    assert!(plain.starts_with("DEVICE    LEVEL  STATUS       HEALTH\n"));
+    assert!(row("Arctis 7  60%    discharging"), "{}", plain);
+    assert!(row("G435      5%     charging"), "{}", plain);
+    assert!(!plain.contains('\x1b'));
+
+    let colored = run_command(dir.path(), &script, &["status", "--color", "always"]);
+    assert!(colored.contains("\x1b[32m60%\x1b[0m"), "{:?}", colored);
+    assert!(colored.contains("\x1b[36m5%\x1b[0m"), "{:?}", colored);
+}
+
+#[test]
+fn inspect_dumps_one_device() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "[devices.\"Arctis 7\"]\nalias = \"Desk\"\nbattery_threshold = 30\n",
+    )
+    .unwrap();
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 60),
+        reading("G435", "BATTERY_CHARGING", 5),
+    ]
+    .concat();
+
+    let out = run_command(
+        dir.path(),
+        &script,
+        &["inspect", "Desk", "--color", "never"],
+    );
+    // Rows of every section, with columns separated by single spaces
+    let rows: Vec<String> = out
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    let row = |text: &str| rows.iter().any(|r| r == text);
+    assert!(out.starts_with("BACKEND\n"), "{}", out);
+    assert!(row("backend headsetcontrol"), "{}", out);
+    assert!(row("raw output Found Arctis 7!"), "{}", out);
+    assert!(row("Level: 60%"), "{}", out);
+    assert!(row("level 60%"), "{}", out);
+    assert!(row("display name Desk"), "{}", out);
+    assert!(row("low threshold 30%"), "{}", out);
+    assert!(row("sidetone not set"), "{}", out);
+    assert!(!out.contains("G435"), "{}", out);
+}
+
+#[test]
+fn check_prints_plugin_output_and_exits_with_state() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "[devices.\"Arctis 7\"]\nalias = \"Desk\"\n",
+    )
+    .unwrap();
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 15),
+        reading("G435", "BATTERY_CHARGING", 5),
+    ]
+    .concat();
+    let check = |args: &[&str]| {
+        let output = command_output(dir.path(), &script, &[&["check"], args].concat());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        (output.status.code(), stdout)
+    };
+
+    assert_eq!(
+        check(&[]),
+        (
+            Some(1),
+            "HEADSET WARNING - Desk 15% discharging, G435 5% charging \
+             | 'Desk'=15%;20:;10:;0;100 'G435'=5%;20:;10:;0;100\n"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        check(&["-w", "30", "-c", "20", "--device", "Desk"]),
+        (
+            Some(2),
+            "HEADSET CRITICAL - Desk 15% discharging | 'Desk'=15%;30:;20:;0;100\n".to_string()
+        )
+    );
+    assert_eq!(
+        check(&["--device", "G435"]),
+        (
+            Some(0),
+            "HEADSET OK - G435 5% charging | 'G435'=5%;20:;10:;0;100\n".to_string()
+        )
+    );
+    assert_eq!(
+        check(&["--device", "G502"]),
+        (
+            Some(2),
+            "HEADSET CRITICAL - G502 not connected\n".to_string()
+        )
+    );
+    assert_eq!(
+        check(&["-w", "10", "-c", "20"]),
+        (
+            Some(3),
+            "HEADSET UNKNOWN - critical threshold 20% is above warning threshold 10%\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn once_notifies_about_changes_since_the_last_run() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "sinks = [\"stdout\"]\n[history]\nenabled = false\n",
+    )
+    .unwrap();
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 50),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 45),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 8),
+    ]
+    .join("---\n");
+    let once = || run_command(dir.path(), &script, &["once"]);
+
+    assert_eq!(
+        once(),
+        "Arctis 7: New device connected\nArctis 7: Battery level: 50%\n"
+    );
+    assert_eq!(once(), "Arctis 7: Battery level: 45%\n");
+    assert!(once().starts_with("Arctis 7: Battery level low: 8%"));
+    // The script repeats its last reading, which is no change
+    assert_eq!(once(), "");
+    assert!(dir
+        .path()
+        .join("data/headsetcontrol-notify/state.toml")
+        .exists());
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn once_restores_the_inactive_time_shortened_by_an_earlier_run() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "sinks = [\"stdout\"]\n[history]\nenabled = false\n[power_saving]\nenabled = true\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("json_script"),
+        r#"{"devices": [{"device": "Arctis 7", "capabilities_str": ["battery", "inactive time"]}]}"#,
+    )
+    .unwrap();
+    // Applying a setting looks the headset up in another battery query
+    let script = [
+        reading("Arctis 7", "BATTERY_AVAILABLE", 50),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 8),
+        reading("Arctis 7", "BATTERY_AVAILABLE", 8),
+        reading("Arctis 7", "BATTERY_CHARGING", 100),
+    ]
+    .join("---\n");
+    let settings = dir.path().join("data/headsetcontrol-notify/settings.toml");
+    let once = || run_command(dir.path(), &script, &["once"]);
+
+    once();
+    once();
+    assert!(fs::read_to_string(&settings)
+        .unwrap()
+        .contains("inactive_time = 5"));
+    once();
+    assert!(fs::read_to_string(&settings)
+        .unwrap()
+        .contains("inactive_time = 30"));
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn graph_rejects_spans_it_cannot_show() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("config.toml"), "").unwrap();
+    for hours in ["0", "18446744073709551615"] {
+        let output = command_output(dir.path(), "", &["graph", "--hours", hours]);
+        assert_eq!(output.status.code(), Some(2), "--hours {}", hours);
+    }
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn history_rejects_ages_it_cannot_look_back() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("config.toml"), "").unwrap();
+    for since in ["100000000w", "99999999999999999w"] {
+        let output = command_output(dir.path(), "", &["history", "--since", since]);
+        assert_eq!(output.status.code(), Some(2), "--since {}", since);
+    }
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn export_rejects_ranges_it_cannot_look_back() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("config.toml"), "").unwrap();
+    for range in ["100000000w", "100000000w..1w", "99999999999999999w"] {
+        let output = command_output(dir.path(), "", &["history", "export", "--range", range]);
+        assert_eq!(output.status.code(), Some(2), "--range {}", range);
+    }
+}
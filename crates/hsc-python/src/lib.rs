@@ -0,0 +1,244 @@
+//! Python bindings for the battery monitor
+//!
+//! Builds the `headsetcontrol_notify` extension module with maturin (`maturin develop` or
+//! `maturin build` in this directory), giving scripts and notebooks the same parsing, backends
+//! and event stream as the daemon:
+//!
+//! ```python
+//! import headsetcontrol_notify as hsc
+//!
+//! print(hsc.list_devices())
+//! monitor = hsc.Monitor()
+//! monitor.subscribe(lambda event: print(event.kind, event.device))
+//! monitor.poll()
+//! ```
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, TryLockError},
+};
+
+use pyo3::{
+    exceptions::{PyOSError, PyRuntimeError},
+    prelude::*,
+};
+
+use hsc_backends::{error::BackendError, headsetcontrol::get_headsetcontrol_output};
+use hsc_core::{
+    config::Config,
+    device::{self, BatteryReading, DeviceEvent, Threshold},
+    monitor,
+};
+
+/// State of a device at the last reading
+#[pyclass(frozen, get_all, skip_from_py_object, module = "headsetcontrol_notify")]
+#[derive(Clone)]
+struct Device {
+    /// Name reported by the device
+    name: String,
+    /// `"charging"`, `"discharging"` or `"disconnected"`
+    status: &'static str,
+    /// Battery percentage, None if unknown
+    level: Option<u8>,
+}
+
+#[pymethods]
+impl Device {
+    fn __repr__(&self) -> String {
+        format!(
+            "Device(name={:?}, status={:?}, level={})",
+            self.name,
+            self.status,
+            self.level.map_or("None".to_string(), |l| l.to_string())
+        )
+    }
+}
+
+impl From<&device::Device> for Device {
+    fn from(device: &device::Device) -> Self {
+        Device {
+            name: device.name.clone(),
+            status: device.battery_status.as_str(),
+            level: device.level(),
+        }
+    }
+}
+
+/// A change in the state of a device
+#[pyclass(frozen, get_all, skip_from_py_object, module = "headsetcontrol_notify")]
+#[derive(Clone)]
+struct Event {
+    /// `"connected"`, `"disconnected"`, `"charging_started"`, `"charging_stopped"`,
+    /// `"level_changed"`, `"threshold_crossed"` or `"full"`
+    kind: &'static str,
+    /// Name of the device the event concerns
+    device: String,
+    /// Level before a level change
+    previous: Option<u8>,
+    /// Level after a connection, level change or threshold crossing, if known
+    level: Option<u8>,
+    /// `"low"` or `"step"` for threshold crossings
+    threshold: Option<&'static str>,
+    /// Whether the device was charging when it crossed the threshold
+    charging: Option<bool>,
+}
+
+#[pymethods]
+impl Event {
+    fn __repr__(&self) -> String {
+        format!("Event(kind={:?}, device={:?})", self.kind, self.device)
+    }
+}
+
+impl From<&DeviceEvent> for Event {
+    fn from(event: &DeviceEvent) -> Self {
+        let mut converted = Event {
+            kind: "connected",
+            device: event.device().to_string(),
+            previous: None,
+            level: None,
+            threshold: None,
+            charging: None,
+        };
+        match *event {
+            DeviceEvent::Connected { battery, .. } => {
+                converted.level = battery.map(BatteryReading::percent)
+            }
+            DeviceEvent::Disconnected { .. } => converted.kind = "disconnected",
+            DeviceEvent::ChargingStarted { .. } => converted.kind = "charging_started",
+            DeviceEvent::ChargingStopped { .. } => converted.kind = "charging_stopped",
+            DeviceEvent::LevelChanged { from, to, .. } => {
+                converted.kind = "level_changed";
+                converted.previous = Some(from.percent());
+                converted.level = Some(to.percent());
+            }
+            DeviceEvent::ThresholdCrossed {
+                battery,
+                threshold,
+                charging,
+                ..
+            } => {
+                converted.kind = "threshold_crossed";
+                converted.level = Some(battery.percent());
+                converted.threshold = Some(match threshold {
+                    Threshold::Low => "low",
+                    Threshold::Step => "step",
+                });
+                converted.charging = Some(charging);
+            }
+            DeviceEvent::Full { .. } => converted.kind = "full",
+        }
+        converted
+    }
+}
+
+/// The battery monitor, raising notifications as its configuration says
+#[pyclass(module = "headsetcontrol_notify")]
+struct Monitor {
+    monitor: Mutex<monitor::Monitor>,
+    /// First exception raised by a callback during the current update
+    error: Arc<Mutex<Option<PyErr>>>,
+}
+
+#[pymethods]
+impl Monitor {
+    /// Create a monitor from the configuration at `config_path`, or the default configuration
+    #[new]
+    #[pyo3(signature = (config_path = None))]
+    fn new(config_path: Option<PathBuf>) -> PyResult<Self> {
+        let path = config_path.unwrap_or_else(Config::default_path);
+        let config = Config::load(&path).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(Monitor {
+            monitor: Mutex::new(monitor::Monitor::new(config)),
+            error: Arc::default(),
+        })
+    }
+
+    /// Run headsetcontrol and return the events raised by its output
+    fn poll(&self) -> PyResult<Vec<Event>> {
+        self.update(&get_headsetcontrol_output().map_err(backend_error)?)
+    }
+
+    /// Update the monitor with headsetcontrol output and return the events raised
+    fn update(&self, output: &str) -> PyResult<Vec<Event>> {
+        let events = self.lock()?.update(output).events;
+        if let Some(e) = lock(&self.error).take() {
+            return Err(e);
+        }
+        Ok(events.iter().map(Event::from).collect())
+    }
+
+    /// Current state of every known device, sorted by name
+    fn devices(&self) -> PyResult<Vec<Device>> {
+        let monitor = self.lock()?;
+        let mut devices: Vec<Device> = monitor.devices.values().map(Device::from).collect();
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(devices)
+    }
+
+    /// Call `callback` with every event raised by later updates
+    ///
+    /// An exception raised by the callback is re-raised by the update that triggered it.
+    fn subscribe(&self, callback: Py<PyAny>) -> PyResult<()> {
+        let error = self.error.clone();
+        self.lock()?
+            .subscribe(move |_: &mut monitor::Monitor, event: &DeviceEvent| {
+                Python::attach(|py| {
+                    if let Err(e) = callback.call1(py, (Event::from(event),)) {
+                        lock(&error).get_or_insert(e);
+                    }
+                })
+            });
+        Ok(())
+    }
+}
+
+impl Monitor {
+    /// Lock the monitor, failing instead of deadlocking when a callback calls back into it
+    fn lock(&self) -> PyResult<MutexGuard<'_, monitor::Monitor>> {
+        match self.monitor.try_lock() {
+            Ok(monitor) => Ok(monitor),
+            Err(TryLockError::Poisoned(e)) => Ok(e.into_inner()),
+            Err(TryLockError::WouldBlock) => Err(PyRuntimeError::new_err(
+                "the monitor cannot be used from its own callbacks",
+            )),
+        }
+    }
+}
+
+/// Lock a mutex, recovering it if a previous holder panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Parse headsetcontrol output into devices
+#[pyfunction]
+fn parse_devices(output: &str) -> Vec<Device> {
+    device::parse_devices(output)
+        .iter()
+        .map(Device::from)
+        .collect()
+}
+
+/// Run headsetcontrol and return the devices it reports
+#[pyfunction]
+fn list_devices() -> PyResult<Vec<Device>> {
+    Ok(parse_devices(
+        &get_headsetcontrol_output().map_err(backend_error)?,
+    ))
+}
+
+/// Raise a backend failure as an `OSError`
+fn backend_error(e: BackendError) -> PyErr {
+    PyOSError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn headsetcontrol_notify(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Device>()?;
+    m.add_class::<Event>()?;
+    m.add_class::<Monitor>()?;
+    m.add_function(wrap_pyfunction!(parse_devices, m)?)?;
+    m.add_function(wrap_pyfunction!(list_devices, m)?)?;
+    Ok(())
+}
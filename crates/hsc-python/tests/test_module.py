@@ -0,0 +1,66 @@
+"""Tests for the Python module; run `maturin develop` then `python -m unittest discover tests`"""
+
+import os
+import tempfile
+import unittest
+
+import headsetcontrol_notify as hsc
+
+MULTIPLE = os.path.join(
+    os.path.dirname(__file__), "..", "..", "hsc-core", "tests", "fixtures", "multiple.txt"
+)
+
+
+def output(level):
+    return (
+        "Found Logitech G533 Wireless Headset!\n\nBattery:\n"
+        f"\tStatus: BATTERY_AVAILABLE\n\tLevel: {level}%\n"
+    )
+
+
+class ModuleTest(unittest.TestCase):
+    def setUp(self):
+        self.dir = tempfile.TemporaryDirectory()
+        self.config = os.path.join(self.dir.name, "config.toml")
+        with open(self.config, "w") as f:
+            f.write('sinks = ["stdout"]\ndebug = false\n')
+
+    def tearDown(self):
+        self.dir.cleanup()
+
+    def test_parse_devices(self):
+        with open(MULTIPLE) as f:
+            devices = hsc.parse_devices(f.read())
+        self.assertEqual(
+            [(d.name, d.status, d.level) for d in devices],
+            [
+                ("SteelSeries Arctis 7", "discharging", 62),
+                ("Corsair VOID Elite Wireless", "charging", 15),
+            ],
+        )
+
+    def test_subscribers_receive_events(self):
+        monitor = hsc.Monitor(self.config)
+        events = []
+        monitor.subscribe(events.append)
+        monitor.update(output(60))
+        returned = monitor.update(output(59))
+
+        self.assertEqual([e.kind for e in events], ["connected", "level_changed"])
+        self.assertEqual([e.kind for e in returned], ["level_changed"])
+        self.assertEqual((events[1].previous, events[1].level), (60, 59))
+        self.assertEqual([d.level for d in monitor.devices()], [59])
+
+    def test_callback_exceptions_propagate(self):
+        monitor = hsc.Monitor(self.config)
+
+        def fail(event):
+            raise ValueError(event.kind)
+
+        monitor.subscribe(fail)
+        with self.assertRaisesRegex(ValueError, "connected"):
+            monitor.update(output(60))
+
+
+if __name__ == "__main__":
+    unittest.main()
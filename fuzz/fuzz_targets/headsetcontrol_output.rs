@@ -0,0 +1,25 @@
+//! Arbitrary `headsetcontrol -b` output, parsed and fed through the monitor twice so state
+//! transitions between readings are exercised too
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use hsc_core::{config::Config, device::parse_devices, Monitor};
+
+fuzz_target!(|data: &[u8]| {
+    let output = String::from_utf8_lossy(data);
+    let (first, second) = output.split_at(output.floor_char_boundary(output.len() / 2));
+
+    parse_devices(&output);
+    let config = Config {
+        sinks: Vec::new(),
+        ..Config::default()
+    };
+    let mut monitor = Monitor::new(config);
+    // Paused so connecting devices do not wait between notifications
+    monitor.paused = true;
+    monitor.update(first);
+    monitor.update(second);
+    monitor.update(&output);
+});
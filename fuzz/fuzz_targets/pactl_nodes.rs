@@ -0,0 +1,12 @@
+//! Arbitrary `pactl --format=json list` output
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use hsc_backends::pactl::parse_nodes;
+use hsc_core::audio::NodeKind;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_nodes(data, NodeKind::Sink);
+});
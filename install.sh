@@ -4,7 +4,7 @@
 USER_NAME=$(whoami)
 sed -i "s/USER_NAME/$USER_NAME/g" ./headsetcontrol-notifyd.service
 
-cargo install --path .
+cargo install --path crates/hsc-notify-bin --bin headsetcontrol-notify
 
 sudo cp ./headsetcontrol-notifyd.service /etc/systemd/user/headsetcontrol-notifyd.service
 